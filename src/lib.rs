@@ -0,0 +1,20 @@
+//! # Newton
+//!
+//! The `.newton` language, as a library. Hosts embed the pieces they need and extend the
+//! language from there.
+
+pub mod newton_ast;
+pub mod newton_codes;
+pub mod newton_cst;
+pub mod newton_diag;
+pub mod newton_dump;
+pub mod newton_highlight;
+pub mod newton_intern;
+pub mod newton_lex;
+pub mod newton_lint;
+pub mod newton_parse;
+pub mod newton_print;
+pub mod newton_query;
+pub mod newton_source;
+pub mod newton_visit;
+pub mod newton_workspace;
@@ -1,5 +1,156 @@
-mod newton_lex;
+//! # Newton
+//!
+//! The `newton` command. For now it dumps tokens, which is handy when working on the lexer,
+//! and checks files for lex and parse errors.
+//!
+//! ```text
+//! newton tokens hello_world.newton
+//! newton tokens --json hello_world.newton
+//! newton check hello_world.newton
+//! newton check --json hello_world.newton
+//! newton check --sarif hello_world.newton
+//! newton explain N0001
+//! ```
 
-fn main() {
-    println!("Hello, world!");
+use std::process::ExitCode;
+
+use newton::newton_codes;
+use newton::newton_diag::{json_lines, sarif, ColorChoice, Diagnostic};
+use newton::newton_lex::{Lexer, LexerOptions};
+use newton::newton_lint::LintLevels;
+use newton::newton_parse::parse;
+use newton::newton_source::SourceMap;
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+
+    match args.as_slice() {
+        [command, path] if command == "tokens" => tokens(path, false),
+        [command, flag, path] if command == "tokens" && flag == "--json" => tokens(path, true),
+        [command, path] if command == "check" => check(path, Output::Human),
+        [command, flag, path] if command == "check" && flag == "--json" => {
+            check(path, Output::Json)
+        }
+        [command, flag, path] if command == "check" && flag == "--sarif" => {
+            check(path, Output::Sarif)
+        }
+        [command, code] if command == "explain" => explain(code),
+        _ => {
+            eprintln!("usage: newton tokens [--json] <file.newton>");
+            eprintln!("       newton check [--json | --sarif] <file.newton>");
+            eprintln!("       newton explain <code>");
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// prints every token in a file, one per line, and any errors after, with the code they
+/// point at
+///
+/// With `json`, everything goes to stdout as one JSON document instead.
+fn tokens(path: &str, json: bool) -> ExitCode {
+    let mut map = SourceMap::new();
+    let file = match map.load(path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("error: couldn't read {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let source = map.file(file);
+    let mut lexer = Lexer::new(&source.text);
+
+    if json {
+        println!("{}", lexer.lexeme_json());
+
+        return match lexer.errors.is_empty() {
+            true => ExitCode::SUCCESS,
+            false => ExitCode::FAILURE,
+        };
+    }
+
+    for token in lexer.lexeme() {
+        println!("{}", token.render(source));
+    }
+
+    let color = ColorChoice::Auto.enabled();
+
+    for diagnostic in lexer.errors.iter().map(Diagnostic::from) {
+        eprint!("{}", diagnostic.render_with(source, color));
+    }
+
+    match lexer.errors.is_empty() {
+        true => ExitCode::SUCCESS,
+        false => ExitCode::FAILURE,
+    }
+}
+
+/// how `check` reports what it found
+enum Output {
+    Human, // rendered with the source, to stderr
+    Json,  // a line of JSON per diagnostic, to stdout
+    Sarif, // a SARIF log, to stdout
+}
+
+/// reports every error in a file, or says nothing if there are none
+///
+/// Warnings go through the file's own `%allow`, `%warn`, and `%deny` directives first.
+fn check(path: &str, output: Output) -> ExitCode {
+    let mut map = SourceMap::new();
+    let file = match map.load(path) {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("error: couldn't read {}: {}", path, e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let source = map.file(file);
+    let mut lexer = Lexer::new_with(&source.text, LexerOptions::new().trivia(true));
+
+    let tokens = lexer.lexeme();
+    let levels = LintLevels::new().directives(&tokens);
+
+    let mut diagnostics: Vec<Diagnostic> = lexer.errors.iter().map(Diagnostic::from).collect();
+    let (_, parsed) = parse(tokens);
+
+    diagnostics.extend(parsed);
+
+    let diagnostics = levels.apply_all(diagnostics);
+
+    match output {
+        Output::Human => {
+            let color = ColorChoice::Auto.enabled();
+
+            for diagnostic in &diagnostics {
+                eprint!("{}", diagnostic.render_with(source, color));
+            }
+        }
+        Output::Json => print!("{}", json_lines(&diagnostics, source)),
+        Output::Sarif => {
+            let located: Vec<_> = diagnostics.iter().map(|d| (file, d.clone())).collect();
+
+            println!("{}", sarif(&map, &located));
+        }
+    }
+
+    match diagnostics.iter().any(Diagnostic::is_error) {
+        true => ExitCode::FAILURE,
+        false => ExitCode::SUCCESS,
+    }
+}
+
+/// prints the long explanation of an error code
+fn explain(code: &str) -> ExitCode {
+    match newton_codes::explain(&code.to_ascii_uppercase()) {
+        Some(explanation) => {
+            print!("{}", explanation);
+            ExitCode::SUCCESS
+        }
+        None => {
+            eprintln!("error: {} isn't an error code", code);
+            ExitCode::FAILURE
+        }
+    }
 }
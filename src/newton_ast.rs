@@ -0,0 +1,711 @@
+//! # Newton AST
+//!
+//! The shape of a program, as the parser leaves it. Every node carries the span of source
+//! it came from, see `Spanned`, so later stages can point back at it.
+//!
+//! ```text
+//! Program
+//! ├── Include              include! "core/internal"
+//! ├── Use                  use core::str
+//! ├── FnDecl               fn greet(name) { ... }
+//! │   └── Doc              ;;; says hello
+//! ├── StructDecl           new struct Point { x, y }
+//! ├── EnumDecl             new enum State { Idle, Running }
+//! └── BlockDecl            new hello_world { ... }
+//!     ├── Doc              ;;; prints things
+//!     ├── ConditionsBlock  conditions { ... }
+//!     │   └── Condition    expect ident 'print', %override
+//!     └── LogicBlock       logic { ... }
+//!         └── Stmt         ::stdout write $::1
+//!             └── Expr     $::1, 1 + 2 * 3
+//! ```
+//!
+//! Statements and expressions are a `kind` and a `span`, so every one of them has a span
+//! in the same place, whatever kind it is.
+//!
+//! With the `serde` feature every node can be serialized, and read back into the same tree,
+//! spans and all, so a tool can take a parsed program as JSON, or a host can keep one on
+//! disk rather than parse the file again. Enums are written the way serde writes them by
+//! default, a variant's name as the key of its contents.
+
+use crate::newton_lex::Span;
+
+/// # Spanned
+///
+/// A node that knows where it came from.
+///
+/// ```
+/// # use newton::newton_ast::Spanned;
+/// # use newton::newton_lex::Lexer;
+/// # use newton::newton_parse::parse;
+/// let source = "new x { }";
+/// let (program, _) = parse(Lexer::new(source).lexeme());
+///
+/// assert_eq!(program.items[0].span().slice_clamped(source), "new x { }");
+/// ```
+pub trait Spanned {
+    /// the source the node covers
+    fn span(&self) -> Span;
+}
+
+/// # Programs
+///
+/// A whole file, its items in order.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Program {
+    pub items: Vec<Item>, // what's at the top level
+    pub span: Span,       // the whole file
+}
+
+impl Spanned for Program {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Items
+///
+/// What can be at the top level of a file.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Item {
+    Block(BlockDecl),   // new hello_world { ... }
+    Include(Include),   // include! "core/internal"
+    Use(Use),           // use core::str
+    Fn(FnDecl),         // fn greet(name) { ... }
+    Struct(StructDecl), // new struct Point { x, y }
+    Enum(EnumDecl),     // new enum State { Idle, Running }
+}
+
+impl Spanned for Item {
+    fn span(&self) -> Span {
+        match self {
+            Item::Block(block) => block.span,
+            Item::Include(include) => include.span,
+            Item::Use(import) => import.span,
+            Item::Fn(function) => function.span,
+            Item::Struct(record) => record.span,
+            Item::Enum(choice) => choice.span,
+        }
+    }
+}
+
+/// # Includes
+///
+/// `include! "core/internal"`, another file to pull in.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Include {
+    pub path: String, // the path, escapes and all worked out
+    pub span: Span,   // from `include` to the path
+}
+
+impl Spanned for Include {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Uses
+///
+/// `use core::str`, a module from another file, by its name rather than its path. Where the
+/// file is is up to whoever loads it, the parser only keeps the name.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Use {
+    pub path: Vec<Ident>, // core, str, outermost first
+    pub span: Span,       // from `use` to the last name
+}
+
+impl Spanned for Use {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Function Declarations
+///
+/// `fn greet(name) { ... }`, logic with a name, for `logic` blocks and other functions to
+/// call as `greet("world")`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct FnDecl {
+    pub doc: Option<Doc>,   // the doc comment right before it
+    pub name: Ident,        // greet, empty if it's missing
+    pub params: Vec<Ident>, // name, what it's called with
+    pub body: Vec<Stmt>,    // what it does
+    pub span: Span,         // from `fn` to the closing `}`
+}
+
+impl Spanned for FnDecl {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Struct Declarations
+///
+/// `new struct Point { x, y }`, a name for values that go together, each one reached as
+/// `p.x`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StructDecl {
+    pub name: Ident,        // Point
+    pub fields: Vec<Ident>, // x, y, in the order they're written
+    pub span: Span,         // from `new` to the closing `}`
+}
+
+impl Spanned for StructDecl {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Enum Declarations
+///
+/// `new enum State { Idle, Running }`, a value that's one of a closed set, each one written
+/// `State::Idle`, both to make one and to `match` on it.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumDecl {
+    pub name: Ident,          // State
+    pub variants: Vec<Ident>, // Idle, Running, in the order they're written
+    pub span: Span,           // from `new` to the closing `}`
+}
+
+impl Spanned for EnumDecl {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Block Declarations
+///
+/// `new hello_world { ... }`, a new language construct, with the sections that say when it
+/// applies and what it does.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct BlockDecl {
+    pub doc: Option<Doc>,       // the doc comment right before it
+    pub name: Ident,            // hello_world, empty if it's missing
+    pub sections: Vec<Section>, // in the order they're written
+    pub span: Span,             // from `new` to the closing `}`
+}
+
+impl Spanned for BlockDecl {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Doc Comments
+///
+/// `;;; prints things`, the documentation of a block declaration or a function, from the
+/// doc comment lines right before it.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Doc {
+    pub text: String, // the lines without their prefix, or the space after it, joined by `\n`
+    pub span: Span,   // from the first line's prefix to the end of the last line
+}
+
+impl Spanned for Doc {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Sections
+///
+/// What can be inside a block declaration.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Section {
+    Conditions(ConditionsBlock), // conditions { ... }
+    Logic(LogicBlock),           // logic { ... }
+}
+
+impl Spanned for Section {
+    fn span(&self) -> Span {
+        match self {
+            Section::Conditions(conditions) => conditions.span,
+            Section::Logic(logic) => logic.span,
+        }
+    }
+}
+
+/// # Conditions Blocks
+///
+/// `conditions { ... }`, which statements a block declaration matches.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ConditionsBlock {
+    pub conditions: Vec<Condition>, // in the order they're written
+    pub span: Span,                 // from `conditions` to the closing `}`
+}
+
+impl Spanned for ConditionsBlock {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Conditions
+///
+/// One entry of a `conditions` block. A predicate says what a statement has to look like to
+/// match, and a directive changes how the matching goes.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Condition {
+    pub kind: ConditionKind, // what it is
+    pub span: Span,          // all of it
+}
+
+impl Spanned for Condition {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ConditionKind {
+    Predicate { name: Ident, args: Vec<Expr> }, // any, expect ident 'print'
+    Directive(Ident),                           // %override, the name without the `%`
+}
+
+/// # Logic Blocks
+///
+/// `logic { ... }`, what a block declaration does when it matches.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LogicBlock {
+    pub body: Vec<Stmt>, // what it does, in order
+    pub span: Span,      // from `logic` to the closing `}`
+}
+
+impl Spanned for LogicBlock {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Identifiers
+///
+/// A name, and where it was written.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Ident {
+    pub name: String, // hello_world
+    pub span: Span,   // where it is
+}
+
+impl Spanned for Ident {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Statements
+///
+/// One thing a `logic` block does.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Stmt {
+    pub kind: StmtKind, // what it is
+    pub span: Span,     // all of it
+}
+
+impl Spanned for Stmt {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StmtKind {
+    Expr(Expr),           // an expression on its own
+    Collect(CollectStmt), // collect as $
+    For(ForStmt),         // for $ as var { ... }
+    Call(Call),           // ::stdout write_newline var
+    Let(LetStmt),         // let x = 5
+    Assign(Assign),       // x = 5, x += 1
+    If(IfStmt),           // if x > 1 { ... } else { ... }
+    While(WhileStmt),     // while x > 1 { ... }
+    Return(Option<Expr>), // return x, or just return
+    Break,                // break, out of the loop it's in
+    Continue,             // continue, on to the loop's next time around
+}
+
+/// # Collect Statements
+///
+/// `collect as $`, which binds the statement's input to a sigil.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct CollectStmt {
+    pub target: Sigil, // $, or $args
+}
+
+/// # Sigils
+///
+/// `$` or `$name`, where collected input is kept.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Sigil {
+    pub name: String, // args, the name without the `$`, empty for `$` on its own
+    pub span: Span,   // where it is, `$` and all
+}
+
+impl Spanned for Sigil {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # For Statements
+///
+/// `for $ as var { ... }`, the body once for each thing in `source`, which can be a range,
+/// `for 1..10 as i { ... }`. The binding can take things apart too,
+/// `for $ as (key, value) { ... }`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ForStmt {
+    pub source: Expr,     // $, what's looped over
+    pub binding: Pattern, // var, each thing in turn, an empty name if it's missing
+    pub body: Vec<Stmt>,  // what's done with it
+}
+
+/// # Calls
+///
+/// `::stdout write_newline var`, a function of a module, and what it's given.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Call {
+    pub module: Ident,   // stdout
+    pub function: Ident, // write_newline
+    pub args: Vec<Expr>, // var
+}
+
+/// # Let Statements
+///
+/// `let x = 5`, a new variable, and what it starts out as. Without the `= 5` it starts out
+/// without a value, and `let (a, b) = pair` takes the value apart into two.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LetStmt {
+    pub pattern: Pattern,    // x, an empty name if it's missing
+    pub value: Option<Expr>, // 5
+}
+
+/// # Assignments
+///
+/// `x = 5`, a new value for something that already has one, or `x += 1`, the old value
+/// with `op` applied. Only names, sigils, and their members can be assigned to, see
+/// `Expr::is_assignable`, but the parser keeps the others too, after reporting them.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Assign {
+    pub target: Expr,      // x
+    pub op: Option<BinOp>, // +, for `+=`, `None` for a plain `=`
+    pub value: Expr,       // 1
+}
+
+/// # If Statements
+///
+/// `if x > 1 { ... }`, the body only when `condition` holds, and what to do otherwise, if
+/// anything.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct IfStmt {
+    pub condition: Expr,         // x > 1
+    pub then: Vec<Stmt>,         // when it holds
+    pub otherwise: Option<Else>, // when it doesn't
+}
+
+/// # Else Branches
+///
+/// What comes after `else`, another `if` for `else if` chains, or a body.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Else {
+    If(Box<Stmt>),    // else if y { ... }, always an `If` statement
+    Block(Vec<Stmt>), // else { ... }
+}
+
+/// # While Statements
+///
+/// `while x > 1 { ... }`, the body over and over, for as long as `condition` holds.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WhileStmt {
+    pub condition: Expr, // x > 1
+    pub body: Vec<Stmt>, // what's done each time
+}
+
+/// # Expressions
+///
+/// Something that has a value.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Expr {
+    pub kind: ExprKind, // what it is
+    pub span: Span,     // all of it
+}
+
+impl Spanned for Expr {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Expr {
+    /// if the expression names somewhere a value can be kept, `x`, `$args`, `$::1`, `p.x`,
+    /// or `xs[0]`
+    pub fn is_assignable(&self) -> bool {
+        match &self.kind {
+            ExprKind::Ident(_) | ExprKind::Sigil(_) => true,
+            ExprKind::Member { base, .. }
+            | ExprKind::Field { base, .. }
+            | ExprKind::Index { base, .. } => base.is_assignable(),
+            _ => false,
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ExprKind {
+    Number(f64),                   // 123, 0x1F
+    String(String),                // "abc", escapes worked out
+    Interpolated(Vec<StringPart>), // "hello {name}!"
+    Char(char),                    // 'a'
+    Ident(String),                 // var
+    Sigil(String),                 // $, $args, the name without the `$`
+    Member {
+        base: Box<Expr>,   // $
+        member: Box<Expr>, // 1, a name or a number
+    }, // $::1, with nothing between the parts
+    Unary {
+        op: UnaryOp,        // -
+        operand: Box<Expr>, // x
+    }, // -x
+    Binary {
+        op: BinOp,      // +
+        lhs: Box<Expr>, // 1
+        rhs: Box<Expr>, // 2 * 3
+    }, // 1 + 2 * 3
+    Call(Call),                    // ::str upper var, as an argument of another call
+    Match(Match),                  // match x { 1 => "one", _ => "many" }
+    FnCall {
+        callee: Box<Expr>, // greet
+        args: Vec<Expr>,   // "world"
+    }, // greet("world"), with nothing between the callee and the `(`
+    ListLit(Vec<Expr>),            // [1, 2, "three"]
+    MapLit(Vec<MapEntry>),         // #{ "key": value }
+    Lambda(Lambda),                // fn (x) { ... }
+    Conditional {
+        condition: Box<Expr>, // x > 1
+        then: Box<Expr>,      // "many", if it's true
+        otherwise: Box<Expr>, // "few", if it isn't
+    }, // x > 1 ? "many" : "few"
+    Range {
+        start: Box<Expr>, // 1
+        end: Box<Expr>,   // 10
+        inclusive: bool,  // if it's `..=`, with `end` in the range
+    }, // 1..10, 1..=10
+    Field {
+        base: Box<Expr>, // p
+        field: Ident,    // x
+    }, // p.x, with nothing between the parts
+    Index {
+        base: Box<Expr>,  // xs
+        index: Box<Expr>, // 0
+    }, // xs[0], with nothing between the base and the `[`
+}
+
+/// # String Parts
+///
+/// One piece of an interpolated string, in the order they're written.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum StringPart {
+    Text(String), // "hello ", escapes worked out
+    Expr(Expr),   // name, from between the `{` and `}`
+}
+
+/// # Lambdas
+///
+/// `fn (x) { ... }`, a function without a name, as a value.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Lambda {
+    pub params: Vec<Ident>, // x
+    pub body: Vec<Stmt>,    // what it does
+}
+
+/// # Map Entries
+///
+/// `"key": value`, one entry of a map literal.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapEntry {
+    pub key: Expr,   // "key"
+    pub value: Expr, // value
+}
+
+/// # Match Expressions
+///
+/// `match x { 1 => "one", _ => "many" }`, the first arm whose pattern fits the value.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Match {
+    pub scrutinee: Box<Expr>, // x, what's matched on
+    pub arms: Vec<MatchArm>,  // in the order they're tried
+}
+
+/// # Match Arms
+///
+/// `1 => "one"`, or `1 => { ... }` for more than one statement.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MatchArm {
+    pub pattern: Pattern, // 1
+    pub body: Vec<Stmt>,  // "one", what's done when it fits
+    pub span: Span,       // from the pattern to the end of the body
+}
+
+impl Spanned for MatchArm {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+/// # Patterns
+///
+/// What a value has to be for a match arm to be taken, and the names it takes apart into,
+/// for `match`, `for`, and `let` alike.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pattern {
+    pub kind: PatternKind, // what it is
+    pub span: Span,        // all of it
+}
+
+impl Spanned for Pattern {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl Pattern {
+    /// the names the pattern gives values to, in the order they're written
+    pub fn bindings(&self) -> Vec<&str> {
+        match &self.kind {
+            PatternKind::Ident(name) => vec![name.as_str()],
+            PatternKind::Tuple(items) => items.iter().flat_map(Pattern::bindings).collect(),
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PatternKind {
+    Number(f64),         // 1, -1
+    String(String),      // "abc"
+    Char(char),          // 'a'
+    Wildcard,            // _, anything at all
+    Ident(String),       // x, anything at all, kept as x
+    Tuple(Vec<Pattern>), // (a, b), a value made of others, each taken apart in turn
+    Variant {
+        name: Ident,    // State
+        variant: Ident, // Idle
+    }, // State::Idle
+}
+
+/// # Unary Operators
+///
+/// An operator in front of its operand.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum UnaryOp {
+    Neg, // -
+}
+
+impl UnaryOp {
+    /// the operator as it's written
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            UnaryOp::Neg => "-",
+        }
+    }
+}
+
+/// # Binary Operators
+///
+/// An operator between two operands. All of them are left-associative, `a - b - c` is
+/// `(a - b) - c`, and the ones with a higher `precedence` bind tighter.
+///
+/// Some of them work on strings too, see `takes_strings`. `+` joins two strings, `"a" + "b"`
+/// is `"ab"`, and with a number on one side the number is joined as it's printed, so
+/// `"n = " + 1` is `"n = 1"`. The comparisons go character by character, like words in a
+/// dictionary, `"apple" < "banana"`, and a string is never equal to a number.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BinOp {
+    Add, // +
+    Sub, // -
+    Mul, // *
+    Div, // /
+    Rem, // %
+    Eq,  // ==
+    Ne,  // !=
+    Lt,  // <
+    Le,  // <=
+    Gt,  // >
+    Ge,  // >=
+    And, // &&
+    Or,  // ||
+}
+
+impl BinOp {
+    /// how tightly the operator binds, `*` over `+` over `<` over `&&` over `||`
+    pub fn precedence(&self) -> u8 {
+        match self {
+            BinOp::Or => 1,
+            BinOp::And => 2,
+            BinOp::Eq | BinOp::Ne | BinOp::Lt | BinOp::Le | BinOp::Gt | BinOp::Ge => 3,
+            BinOp::Add | BinOp::Sub => 4,
+            BinOp::Mul | BinOp::Div | BinOp::Rem => 5,
+        }
+    }
+
+    /// if the operator works on strings, `+` and the comparisons
+    pub fn takes_strings(&self) -> bool {
+        !matches!(
+            self,
+            BinOp::Sub | BinOp::Mul | BinOp::Div | BinOp::Rem | BinOp::And | BinOp::Or
+        )
+    }
+
+    /// the operator as it's written
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinOp::Add => "+",
+            BinOp::Sub => "-",
+            BinOp::Mul => "*",
+            BinOp::Div => "/",
+            BinOp::Rem => "%",
+            BinOp::Eq => "==",
+            BinOp::Ne => "!=",
+            BinOp::Lt => "<",
+            BinOp::Le => "<=",
+            BinOp::Gt => ">",
+            BinOp::Ge => ">=",
+            BinOp::And => "&&",
+            BinOp::Or => "||",
+        }
+    }
+}
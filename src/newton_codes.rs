@@ -0,0 +1,308 @@
+//! # Newton Error Codes
+//!
+//! Every error the lexer and parser report has a stable code, like `N0001`, so tools can
+//! link to it and people can search for it. `explain` has the long story behind each one,
+//! and `newton explain N0001` prints it.
+//!
+//! Lexer errors are `N00xx`, parser errors `N01xx`, and workspace errors `N02xx`. A code is never reused, even if the
+//! error it was for goes away.
+//!
+//! ```
+//! # use newton::newton_codes::explain;
+//! # use newton::newton_lex::LexErrorKind;
+//! let code = LexErrorKind::UnterminatedString.code();
+//!
+//! assert_eq!(code, "N0001");
+//! assert_eq!(explain(code).unwrap().title, "unterminated string");
+//! ```
+
+/// # Explanations
+///
+/// What an error code means, and a small program that causes it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Explanation {
+    pub code: &'static str,        // e.g. `N0001`
+    pub title: &'static str,       // a few words, e.g. `unterminated string`
+    pub description: &'static str, // what causes it and how to fix it
+    pub example: &'static str,     // a program with the error
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "{}: {}", self.code, self.title)?;
+        writeln!(f)?;
+        writeln!(f, "{}", self.description)?;
+        writeln!(f)?;
+        writeln!(f, "For example:")?;
+        writeln!(f)?;
+
+        for line in self.example.lines() {
+            writeln!(f, "    {}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// every error code there is, in order
+pub const EXPLANATIONS: &[Explanation] = &[
+    Explanation {
+        code: "N0001",
+        title: "unterminated string",
+        description: "A string was opened with `\"` (or `\"\"\"`, or `r\"`) but never closed. \
+                      Strings can't run past the end of the file, so the lexer reports the \
+                      opening quote. Add the closing quote where the string should end.",
+        example: "::stdout write \"hello",
+    },
+    Explanation {
+        code: "N0002",
+        title: "invalid number",
+        description: "A number is malformed. A number has at most one decimal point, with \
+                      digits on both sides, `_` separators only go between two digits, an \
+                      exponent needs digits after the `e`, and `0x`, `0o`, and `0b` numbers \
+                      need at least one digit that fits their radix, and have to fit in 64 \
+                      bits.",
+        example: "x = 1..2\ny = 0b102\nz = 1e",
+    },
+    Explanation {
+        code: "N0003",
+        title: "unexpected character",
+        description: "A character that can't start any token, like a stray backtick or a \
+                      symbol the language doesn't use. Remove it, or put it in a string if \
+                      it was meant as text.",
+        example: "new x { ` }",
+    },
+    Explanation {
+        code: "N0004",
+        title: "unterminated block comment",
+        description: "A block comment was opened with `;*` but never closed with `*;`. Block \
+                      comments nest, so every `;*` inside needs its own `*;` too.",
+        example: ";* the outer comment ;* an inner one *;\nnew x { }",
+    },
+    Explanation {
+        code: "N0005",
+        title: "invalid escape",
+        description: "A `\\` in a string or character starts an escape the language doesn't \
+                      have, or a malformed one. The escapes are `\\n`, `\\t`, `\\r`, `\\0`, \
+                      `\\\\`, `\\\"`, `\\'`, `\\{`, `\\}`, `\\xNN` up to `\\x7F`, and \
+                      `\\u{NNNN}`. Use a raw string, `r\"...\"`, for text full of backslashes.",
+        example: "::stdout write \"C:\\new\\q\"",
+    },
+    Explanation {
+        code: "N0006",
+        title: "unterminated interpolation",
+        description: "A `{` in a string starts an interpolation, which has to be closed with \
+                      `}` before the string ends. Escape the brace as `\\{` if it's meant \
+                      literally.",
+        example: "::stdout write \"hello {name\"",
+    },
+    Explanation {
+        code: "N0007",
+        title: "invalid character literal",
+        description: "A character literal holds exactly one character, or one escape, \
+                      between single quotes, all on one line. Use a string for more than one \
+                      character.",
+        example: "a = ''\nb = 'ab'\nc = 'c",
+    },
+    Explanation {
+        code: "N0008",
+        title: "token too long",
+        description: "A token is longer than the host allows, see \
+                      `LexerOptions::max_token_len`. This usually means generated input went \
+                      wrong, like a string that was never closed.",
+        example: "x = \"a very, very long string\"",
+    },
+    Explanation {
+        code: "N0100",
+        title: "unexpected token",
+        description: "The parser needed one kind of token and found another, like a `new` \
+                      block without a name.",
+        example: "new { }",
+    },
+    Explanation {
+        code: "N0101",
+        title: "expected an item",
+        description: "Only `new` blocks, `fn`s, `include`s, and `use`s can be at the top level \
+                      of a file. Statements go in a `logic` block, inside a `new` block, or in \
+                      a function.",
+        example: "::stdout write \"hi\"",
+    },
+    Explanation {
+        code: "N0102",
+        title: "expected a section",
+        description: "A `new` block only holds `conditions` and `logic` sections. Move the \
+                      statement into one of them.",
+        example: "new x {\n    collect as $\n}",
+    },
+    Explanation {
+        code: "N0103",
+        title: "expected a condition",
+        description: "A `conditions` block holds predicates, like `any` or `expect ident`, \
+                      and directives, like `%override`. Literals only go after an `expect`, \
+                      as what the token has to be.",
+        example: "new x {\n    conditions { 1 }\n}",
+    },
+    Explanation {
+        code: "N0104",
+        title: "expected a statement",
+        description: "A `logic` block holds statements, like `collect as $`, `for $ as var \
+                      { }`, and calls like `::stdout write var`, one to a line. Something that \
+                      can't start one was found.",
+        example: "new x {\n    logic { = 1 }\n}",
+    },
+    Explanation {
+        code: "N0105",
+        title: "expected an expression",
+        description: "A value was needed, like a number, a string, a name, or a sigil, and \
+                      something else was found.",
+        example: "new x {\n    logic { for { } }\n}",
+    },
+    Explanation {
+        code: "N0106",
+        title: "can't assign to this expression",
+        description: "Only names, sigils, and their members, like `x`, `$args`, or `$::1`, \
+                      have somewhere to keep a value. Assigning to anything else, like a \
+                      number or a sum, has nowhere for the value to go.",
+        example: "new x {\n    logic { 1 = x }\n}",
+    },
+    Explanation {
+        code: "N0107",
+        title: "expected a pattern",
+        description: "Each arm of a `match` starts with a pattern, what the value has to be \
+                      for the arm to be taken: a number, a string, a character, an enum \
+                      variant like `State::Idle`, or `_` for anything. A name takes anything \
+                      too, and keeps it, and `(a, b)` takes a value apart.",
+        example: "new x {\n    logic {\n        match x { $ => 1 }\n    }\n}",
+    },
+    Explanation {
+        code: "N0108",
+        title: "this operator doesn't work on strings",
+        description: "Strings only have `+`, which joins them, and the comparisons, which go \
+                      character by character. The other operators, like `-`, `*`, or `&&`, \
+                      only make sense for numbers.",
+        example: "new x {\n    logic { let x = \"ab\" - \"b\" }\n}",
+    },
+    Explanation {
+        code: "N0109",
+        title: "unclosed parenthesis",
+        description: "A `(` in an expression needs its `)` before the end of the line. The \
+                      error points at the `(`, and the label at where the `)` was expected.",
+        example: "new x {\n    logic { let x = (1 + 2 }\n}",
+    },
+    Explanation {
+        code: "N0110",
+        title: "program too deeply nested",
+        description: "Blocks, expressions, and patterns can only go so deep inside each \
+                      other, 64 levels unless the host says otherwise, so a file can't run \
+                      the parser out of stack. Pull the inner parts out into variables or \
+                      functions.",
+        example: "new x {\n    logic {\n        let x = ((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((((1))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))))\n    }\n}",
+    },
+    Explanation {
+        code: "N0200",
+        title: "unresolved import",
+        description: "An `include` or a `use` points at a file that isn't in the workspace. \
+                      Paths are relative to the file they're written in, and the `.newton` \
+                      at the end can be left off, so `include \"core\"` and `use core` in \
+                      `src/main.newton` both look for `src/core.newton`. Check the spelling, \
+                      or add the file.",
+        example: "include \"missing\"",
+    },
+    Explanation {
+        code: "N0201",
+        title: "declared more than once",
+        description: "Two blocks, functions, structs, or enums have the same name, in one file \
+                      or across the files of a workspace, so a reference to it can't tell \
+                      which is meant. Rename one of them.",
+        example: "fn greet(name) { }\nfn greet() { }",
+    },
+];
+
+/// the explanation behind an error code, `None` if there's no such code
+pub fn explain(code: &str) -> Option<&'static Explanation> {
+    EXPLANATIONS.iter().find(|e| e.code == code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_ast::BinOp;
+    use crate::newton_lex::{LexErrorKind, Radix, Type};
+    use crate::newton_parse::ParseErrorKind;
+    use crate::newton_workspace::ResolveErrorKind;
+
+    #[test]
+    pub fn test_every_code_is_explained() {
+        let codes = [
+            LexErrorKind::UnterminatedBlockComment.code(),
+            LexErrorKind::UnknownEscape('q').code(),
+            LexErrorKind::InvalidHexEscape.code(),
+            LexErrorKind::InvalidUnicodeEscape.code(),
+            LexErrorKind::UnterminatedString.code(),
+            LexErrorKind::UnterminatedInterpolation.code(),
+            LexErrorKind::EmptyChar.code(),
+            LexErrorKind::CharTooLong.code(),
+            LexErrorKind::UnterminatedChar.code(),
+            LexErrorKind::InvalidDigit('2', Radix::Binary).code(),
+            LexErrorKind::MissingDigits(Radix::Hexadecimal).code(),
+            LexErrorKind::MissingExponent.code(),
+            LexErrorKind::NumberTooLarge(Radix::Octal).code(),
+            LexErrorKind::MisplacedDecimalPoint.code(),
+            LexErrorKind::MisplacedUnderscore.code(),
+            LexErrorKind::UnexpectedChar('`').code(),
+            LexErrorKind::TokenTooLong(1).code(),
+            ParseErrorKind::Expected {
+                expected: Type::Ident,
+                found: Type::OpenBrace,
+            }
+            .code(),
+            ParseErrorKind::ExpectedItem { found: Type::Ident }.code(),
+            ParseErrorKind::ExpectedSection { found: Type::Ident }.code(),
+            ParseErrorKind::ExpectedCondition {
+                found: Type::Number,
+            }
+            .code(),
+            ParseErrorKind::ExpectedStmt { found: Type::Equal }.code(),
+            ParseErrorKind::ExpectedExpr { found: Type::Eof }.code(),
+            ParseErrorKind::ExpectedKeyword {
+                keyword: "as".to_owned(),
+                found: Type::Sigil,
+            }
+            .code(),
+            ParseErrorKind::NotAssignable.code(),
+            ParseErrorKind::ExpectedPattern { found: Type::Ident }.code(),
+            ParseErrorKind::NotOnStrings { op: BinOp::Sub }.code(),
+            ParseErrorKind::UnclosedParen.code(),
+            ParseErrorKind::TooDeep { max: 1 }.code(),
+            ResolveErrorKind::UnresolvedInclude {
+                path: "core".to_owned(),
+            }
+            .code(),
+            ResolveErrorKind::Duplicate {
+                name: "greet".to_owned(),
+            }
+            .code(),
+        ];
+
+        for code in codes {
+            assert!(explain(code).is_some(), "{} isn't explained", code);
+        }
+
+        assert!(explain("N9999").is_none());
+    }
+
+    #[test]
+    pub fn test_codes_are_unique_and_ordered() {
+        for pair in EXPLANATIONS.windows(2) {
+            assert!(pair[0].code < pair[1].code, "{}", pair[1].code);
+        }
+    }
+
+    #[test]
+    pub fn test_explanation_display() {
+        let text = explain("N0003").unwrap().to_string();
+
+        assert!(text.starts_with("N0003: unexpected character\n\n"));
+        assert!(text.ends_with("For example:\n\n    new x { ` }\n"));
+    }
+}
@@ -0,0 +1,575 @@
+//! # Newton Concrete Syntax Trees
+//!
+//! The AST keeps what a program means, and leaves out how it was written. A `SyntaxNode`
+//! keeps all of it: every token, comment, and space, in order, grouped into the nodes the
+//! AST has, so a formatter or a refactoring tool can change a part and write the rest back
+//! byte for byte.
+//!
+//! ```
+//! # use newton::newton_cst::{parse_cst, SyntaxKind};
+//! let source = "new x { ; hi\n  logic { let a = 1 } }";
+//! let (tree, diagnostics) = parse_cst(source);
+//!
+//! assert!(diagnostics.is_empty());
+//! assert_eq!(tree.text(), source);
+//! assert_eq!(tree.children().next().unwrap().kind, SyntaxKind::BlockDecl);
+//! ```
+//!
+//! The tree is built from the tokens and the spans of the AST, so it has the same shape as
+//! the AST, broken parts and all. Whatever a node doesn't cover, like the space between two
+//! statements, belongs to the node around it.
+
+use crate::newton_ast::{
+    ConditionKind, Else, Expr, ExprKind, Item, MatchArm, Pattern, PatternKind, Program, Section,
+    Spanned, Stmt, StmtKind,
+};
+use crate::newton_diag::Diagnostic;
+use crate::newton_lex::{Lexer, LexerOptions, Span, Type};
+use crate::newton_parse::parse;
+
+/// # Syntax Kinds
+///
+/// What a node is, one for each node of the AST with a span of its own.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SyntaxKind {
+    Program,    // the whole file
+    Include,    // include! "core/internal"
+    Use,        // use core::str
+    FnDecl,     // fn greet(name) { ... }
+    StructDecl, // new struct Point { x, y }
+    EnumDecl,   // new enum State { Idle, Running }
+    BlockDecl,  // new hello_world { ... }
+    Conditions, // conditions { ... }
+    Condition,  // expect ident 'print'
+    Logic,      // logic { ... }
+    Stmt,       // let x = 5
+    Expr,       // 1 + 2
+    MatchArm,   // 1 => "one"
+    Pattern,    // (a, b)
+}
+
+/// # Syntax Tokens
+///
+/// A token as it was written, trivia included. Bytes the lexer couldn't make a token of are
+/// kept too, as an `Error` token.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SyntaxToken {
+    pub ty: Type,     // what it is
+    pub text: String, // exactly what was written
+    pub span: Span,   // where it was written
+}
+
+/// # Syntax Elements
+///
+/// A child of a node, another node or a token.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// # Syntax Nodes
+///
+/// A node, and everything written inside it, in order.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SyntaxNode {
+    pub kind: SyntaxKind,             // what it is
+    pub elements: Vec<SyntaxElement>, // its nodes and tokens
+    pub span: Span,                   // where it was written, its tokens and all
+}
+
+impl Spanned for SyntaxNode {
+    fn span(&self) -> Span {
+        self.span
+    }
+}
+
+impl SyntaxNode {
+    /// the source the node was made from, or what it is now after changing its tokens
+    pub fn text(&self) -> String {
+        let mut text = String::new();
+
+        for token in self.tokens() {
+            text.push_str(&token.text);
+        }
+
+        text
+    }
+
+    /// the nodes right inside this one
+    pub fn children(&self) -> impl Iterator<Item = &SyntaxNode> {
+        self.elements.iter().filter_map(|element| match element {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// every token inside the node, however deep, in order
+    pub fn tokens(&self) -> Vec<&SyntaxToken> {
+        let mut tokens = Vec::new();
+
+        for element in &self.elements {
+            match element {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+
+    /// the token at `offset`, and the nodes it's in, the innermost first, out to this one
+    ///
+    /// Comments and spaces are tokens too. Between two tokens it's the one starting at
+    /// `offset`, and at the very end it's the last one. `None` outside the node.
+    ///
+    /// ```
+    /// # use newton::newton_cst::{parse_cst, SyntaxKind};
+    /// let (tree, _) = parse_cst("new x { logic { let a = 1 } }");
+    /// let (token, ancestors) = tree.token_at(20).unwrap();
+    ///
+    /// assert_eq!(token.text, "a");
+    /// assert_eq!(ancestors[0].kind, SyntaxKind::Pattern);
+    /// assert_eq!(ancestors.last().unwrap().kind, SyntaxKind::Program);
+    /// ```
+    pub fn token_at(&self, offset: usize) -> Option<(&SyntaxToken, Vec<&SyntaxNode>)> {
+        let mut tokens = self.tokens().into_iter();
+
+        let token = match offset == self.span.end {
+            true => tokens.rfind(|token| !token.span.is_empty())?,
+            false => tokens.find(|token| token.span.contains(offset))?,
+        };
+
+        let mut ancestors = Vec::new();
+
+        self.ancestors_of(token, &mut ancestors);
+        Some((token, ancestors))
+    }
+
+    /// adds the nodes `token` is in to `ancestors`, the innermost first, if it's in this one
+    fn ancestors_of<'a>(
+        &'a self,
+        token: &SyntaxToken,
+        ancestors: &mut Vec<&'a SyntaxNode>,
+    ) -> bool {
+        let found = self.elements.iter().any(|element| match element {
+            SyntaxElement::Token(other) => std::ptr::eq(other, token),
+            SyntaxElement::Node(node) => node.ancestors_of(token, ancestors),
+        });
+
+        if found {
+            ancestors.push(self);
+        }
+
+        found
+    }
+
+    /// every token inside the node, however deep, to change in place
+    pub fn tokens_mut(&mut self) -> Vec<&mut SyntaxToken> {
+        let mut tokens = Vec::new();
+
+        for element in &mut self.elements {
+            match element {
+                SyntaxElement::Node(node) => tokens.extend(node.tokens_mut()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+}
+
+/// lexes and parses `source` into a tree that keeps all of it, with the same diagnostics as
+/// `parse_source`
+pub fn parse_cst(source: &str) -> (SyntaxNode, Vec<Diagnostic>) {
+    let mut lexer = Lexer::new_with(source, LexerOptions::new().trivia(true));
+    let mut diagnostics = Vec::new();
+
+    let tokens = lexer.lexeme_into(&mut diagnostics);
+    let syntax = syntax_tokens(source, tokens.iter().map(|t| (t.ty.clone(), t.span)));
+    let (program, parsed) = parse(tokens);
+
+    diagnostics.extend(parsed);
+    (build(source, &program, syntax), diagnostics)
+}
+
+/// the tree of `program`, made from `tokens`, which have to be the tokens it was parsed
+/// from, trivia and all
+pub fn build(source: &str, program: &Program, tokens: Vec<SyntaxToken>) -> SyntaxNode {
+    let shape = Shape {
+        kind: SyntaxKind::Program,
+        span: Span::new(0, source.len()),
+        children: program.items.iter().map(item).collect(),
+    };
+    let mut tokens = tokens.into_iter().peekable();
+
+    let mut root = grow(&shape, &mut tokens);
+
+    // anything the program doesn't cover still belongs to the file
+    for token in tokens {
+        root.elements.push(SyntaxElement::Token(token));
+    }
+
+    root
+}
+
+/// tokens as they were written, with the bytes between them, that no token covers, as
+/// `Error` tokens, so together they're all of `source`
+fn syntax_tokens(source: &str, tokens: impl Iterator<Item = (Type, Span)>) -> Vec<SyntaxToken> {
+    let mut syntax = Vec::new();
+    let mut at = 0;
+
+    for (ty, span) in tokens {
+        if span.start > at {
+            syntax.push(SyntaxToken {
+                ty: Type::Error,
+                text: source[at..span.start].to_owned(),
+                span: Span::new(at, span.start),
+            });
+        }
+
+        syntax.push(SyntaxToken {
+            ty,
+            text: span.slice_clamped(source).to_owned(),
+            span,
+        });
+        at = at.max(span.end);
+    }
+
+    if at < source.len() {
+        syntax.push(SyntaxToken {
+            ty: Type::Error,
+            text: source[at..].to_owned(),
+            span: Span::new(at, source.len()),
+        });
+    }
+
+    syntax
+}
+
+/// the node `shape` stands for, taking the tokens it covers from `tokens`
+fn grow(
+    shape: &Shape,
+    tokens: &mut std::iter::Peekable<impl Iterator<Item = SyntaxToken>>,
+) -> SyntaxNode {
+    let mut elements = Vec::new();
+    let mut children = shape.children.iter().peekable();
+
+    loop {
+        let next = tokens.peek().map(|token| token.span);
+
+        match (children.peek(), next) {
+            (Some(child), Some(next)) if next.start >= child.span.start => {
+                elements.push(SyntaxElement::Node(grow(child, tokens)));
+                children.next();
+            }
+            (_, Some(next)) if next.start < shape.span.end => {
+                elements.push(SyntaxElement::Token(tokens.next().unwrap()));
+            }
+            (Some(_), _) => {
+                // a child past the last token, like a missing name, is still a node
+                elements.push(SyntaxElement::Node(grow(children.next().unwrap(), tokens)));
+            }
+            (None, _) => break,
+        }
+    }
+
+    SyntaxNode {
+        kind: shape.kind,
+        elements,
+        span: shape.span,
+    }
+}
+
+/// the kind and span of a node, and of the nodes inside it
+struct Shape {
+    kind: SyntaxKind, // what it is
+    span: Span,       // where it is
+    children: Vec<Shape>,
+}
+
+impl Shape {
+    fn new(kind: SyntaxKind, span: Span, mut children: Vec<Shape>) -> Self {
+        children.sort_by_key(|child| child.span.start);
+
+        Self {
+            kind,
+            span,
+            children,
+        }
+    }
+}
+
+fn item(item: &Item) -> Shape {
+    let (kind, children) = match item {
+        Item::Include(_) => (SyntaxKind::Include, Vec::new()),
+        Item::Use(_) => (SyntaxKind::Use, Vec::new()),
+        Item::Fn(function) => (SyntaxKind::FnDecl, stmts(&function.body)),
+        Item::Struct(_) => (SyntaxKind::StructDecl, Vec::new()),
+        Item::Enum(_) => (SyntaxKind::EnumDecl, Vec::new()),
+        Item::Block(block) => {
+            let sections = block.sections.iter().map(section).collect();
+
+            (SyntaxKind::BlockDecl, sections)
+        }
+    };
+
+    Shape::new(kind, item.span(), children)
+}
+
+fn section(section: &Section) -> Shape {
+    match section {
+        Section::Conditions(conditions) => {
+            let children = conditions
+                .conditions
+                .iter()
+                .map(|condition| {
+                    let args = match &condition.kind {
+                        ConditionKind::Predicate { args, .. } => args.iter().map(expr).collect(),
+                        ConditionKind::Directive(_) => Vec::new(),
+                    };
+
+                    Shape::new(SyntaxKind::Condition, condition.span, args)
+                })
+                .collect();
+
+            Shape::new(SyntaxKind::Conditions, conditions.span, children)
+        }
+        Section::Logic(logic) => Shape::new(SyntaxKind::Logic, logic.span, stmts(&logic.body)),
+    }
+}
+
+fn stmts(stmts: &[Stmt]) -> Vec<Shape> {
+    stmts.iter().map(stmt).collect()
+}
+
+fn stmt(stmt: &Stmt) -> Shape {
+    let children = match &stmt.kind {
+        StmtKind::Expr(value) => vec![expr(value)],
+        StmtKind::Collect(_) | StmtKind::Break | StmtKind::Continue => Vec::new(),
+        StmtKind::For(for_stmt) => {
+            let mut children = vec![expr(&for_stmt.source), pattern(&for_stmt.binding)];
+
+            children.extend(stmts(&for_stmt.body));
+            children
+        }
+        StmtKind::Call(call) => call.args.iter().map(expr).collect(),
+        StmtKind::Let(let_stmt) => {
+            let mut children = vec![pattern(&let_stmt.pattern)];
+
+            children.extend(let_stmt.value.iter().map(expr));
+            children
+        }
+        StmtKind::Assign(assign) => vec![expr(&assign.target), expr(&assign.value)],
+        StmtKind::If(if_stmt) => {
+            let mut children = vec![expr(&if_stmt.condition)];
+
+            children.extend(stmts(&if_stmt.then));
+
+            match &if_stmt.otherwise {
+                Some(Else::If(nested)) => children.push(self::stmt(nested)),
+                Some(Else::Block(body)) => children.extend(stmts(body)),
+                None => {}
+            }
+
+            children
+        }
+        StmtKind::While(while_stmt) => {
+            let mut children = vec![expr(&while_stmt.condition)];
+
+            children.extend(stmts(&while_stmt.body));
+            children
+        }
+        StmtKind::Return(value) => value.iter().map(expr).collect(),
+    };
+
+    Shape::new(SyntaxKind::Stmt, stmt.span, children)
+}
+
+fn expr(value: &Expr) -> Shape {
+    let children = match &value.kind {
+        ExprKind::Number(_)
+        | ExprKind::String(_)
+        | ExprKind::Interpolated(_)
+        | ExprKind::Char(_)
+        | ExprKind::Ident(_)
+        | ExprKind::Sigil(_) => Vec::new(),
+        ExprKind::Member { base, member } => vec![expr(base), expr(member)],
+        ExprKind::Unary { operand, .. } => vec![expr(operand)],
+        ExprKind::Binary { lhs, rhs, .. } => vec![expr(lhs), expr(rhs)],
+        ExprKind::Call(call) => call.args.iter().map(expr).collect(),
+        ExprKind::Match(matched) => {
+            let mut children = vec![expr(&matched.scrutinee)];
+
+            children.extend(matched.arms.iter().map(arm));
+            children
+        }
+        ExprKind::FnCall { callee, args } => {
+            let mut children = vec![expr(callee)];
+
+            children.extend(args.iter().map(expr));
+            children
+        }
+        ExprKind::ListLit(items) => items.iter().map(expr).collect(),
+        ExprKind::MapLit(entries) => entries
+            .iter()
+            .flat_map(|entry| [expr(&entry.key), expr(&entry.value)])
+            .collect(),
+        ExprKind::Lambda(lambda) => stmts(&lambda.body),
+        ExprKind::Conditional {
+            condition,
+            then,
+            otherwise,
+        } => vec![expr(condition), expr(then), expr(otherwise)],
+        ExprKind::Range { start, end, .. } => vec![expr(start), expr(end)],
+        ExprKind::Field { base, .. } => vec![expr(base)],
+        ExprKind::Index { base, index } => vec![expr(base), expr(index)],
+    };
+
+    Shape::new(SyntaxKind::Expr, value.span, children)
+}
+
+fn arm(arm: &MatchArm) -> Shape {
+    let mut children = vec![pattern(&arm.pattern)];
+
+    children.extend(stmts(&arm.body));
+    Shape::new(SyntaxKind::MatchArm, arm.span, children)
+}
+
+fn pattern(pattern: &Pattern) -> Shape {
+    let children = match &pattern.kind {
+        PatternKind::Tuple(items) => items.iter().map(self::pattern).collect(),
+        _ => Vec::new(),
+    };
+
+    Shape::new(SyntaxKind::Pattern, pattern.span, children)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// the kinds of the tree, as `Kind(Child Child)`
+    fn kinds(node: &SyntaxNode) -> String {
+        let children: Vec<String> = node.children().map(kinds).collect();
+
+        match children.is_empty() {
+            true => format!("{:?}", node.kind),
+            false => format!("{:?}({})", node.kind, children.join(" ")),
+        }
+    }
+
+    #[test]
+    pub fn test_cst_lossless() {
+        let sources = [
+            "new x { ; hi\n  logic { let a = 1 } }",
+            ";;; docs\nfn f(a) {\n  return a + 1 ;* block *;\n}\n\n",
+            "new x {\n\tconditions { expect ident 'print', %override }\n}",
+            "new x { logic { match x { (a, 1) => a, _ => { } } } }   ",
+            "new x { logic { let a = ` ! (1 + } }", // broken, and unexpected characters
+            "new x { logic { let s = \"a\\nb\" + r\"\\n\" } ",
+            "",
+        ];
+
+        for source in sources {
+            let (tree, _) = parse_cst(source);
+
+            assert_eq!(tree.text(), source);
+            assert_eq!(tree.span, Span::new(0, source.len()));
+        }
+    }
+
+    #[test]
+    pub fn test_cst_shape() {
+        let source = "new x {\n  logic {\n    let a = -(1 + 2) ; three\n    f(a)\n  }\n}\n";
+        let (tree, _) = parse_cst(source);
+
+        assert_eq!(
+            kinds(&tree),
+            "Program(BlockDecl(Logic(Stmt(Pattern Expr(Expr(Expr Expr))) Stmt(Expr(Expr Expr)))))"
+        );
+
+        let block = tree.children().next().unwrap();
+        let logic = block.children().next().unwrap();
+        let stmt = logic.children().next().unwrap();
+
+        assert_eq!(stmt.text(), "let a = -(1 + 2)");
+
+        // the comment after it is the logic block's, between its statements
+        let comments: Vec<&str> = logic
+            .elements
+            .iter()
+            .filter_map(|element| match element {
+                SyntaxElement::Token(token) if token.ty == Type::Comment => {
+                    Some(token.text.as_str())
+                }
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(comments, vec!["; three"]);
+    }
+
+    #[test]
+    pub fn test_cst_token_at() {
+        let source = "new x {\n  logic { let a = 1 ; one\n  }\n}";
+        let (tree, _) = parse_cst(source);
+        let at = |offset| {
+            let (token, ancestors) = tree.token_at(offset).unwrap();
+            let kinds: Vec<SyntaxKind> = ancestors.iter().map(|node| node.kind).collect();
+
+            (token.ty.clone(), token.text.as_str(), kinds)
+        };
+
+        use SyntaxKind::*;
+
+        assert_eq!(
+            at(source.find('a').unwrap()),
+            (
+                Type::Ident,
+                "a",
+                vec![Pattern, Stmt, Logic, BlockDecl, Program]
+            )
+        );
+        assert_eq!(
+            at(source.find(" a").unwrap()),
+            (Type::Whitespace, " ", vec![Stmt, Logic, BlockDecl, Program])
+        );
+        assert_eq!(
+            at(source.find(';').unwrap() + 2),
+            (Type::Comment, "; one", vec![Logic, BlockDecl, Program])
+        );
+        assert_eq!(
+            at(0),
+            (Type::ReservedKeyword, "new", vec![BlockDecl, Program])
+        );
+        assert_eq!(
+            at(source.len()),
+            (Type::CloseBrace, "}", vec![BlockDecl, Program])
+        );
+
+        assert!(tree.token_at(source.len() + 1).is_none());
+        assert!(parse_cst("").0.token_at(0).is_none());
+    }
+
+    #[test]
+    pub fn test_cst_rewrite() {
+        let source = "new x {\n  logic { let a = a + 1 } ; keep me\n}";
+        let (mut tree, _) = parse_cst(source);
+
+        for token in tree.tokens_mut() {
+            if token.ty == Type::Ident && token.text == "a" {
+                token.text = "count".to_owned();
+            }
+        }
+
+        assert_eq!(
+            tree.text(),
+            "new x {\n  logic { let count = count + 1 } ; keep me\n}"
+        );
+    }
+}
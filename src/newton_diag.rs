@@ -0,0 +1,1078 @@
+//! # Newton Diagnostics
+//!
+//! What every stage reports to the user. Lex errors, parse errors, and whatever comes after
+//! them all end up as a `Diagnostic`, so a host has one thing to show, however it shows it.
+//!
+//! ```
+//! # use newton::newton_diag::{Diagnostic, Severity};
+//! # use newton::newton_lex::Lexer;
+//! let mut lexer = Lexer::new("new x \"open");
+//! lexer.lexeme();
+//!
+//! let diagnostics: Vec<Diagnostic> = lexer.errors.iter().map(Diagnostic::from).collect();
+//!
+//! assert_eq!(diagnostics[0].severity, Severity::Error);
+//! assert_eq!(diagnostics[0].to_string(), "error[N0001]: unterminated string");
+//! ```
+
+use std::fmt::Write;
+
+use crate::newton_dump::string;
+use crate::newton_lex::{LexError, Span};
+use crate::newton_parse::ParseError;
+use crate::newton_source::{FileId, SourceFile, SourceMap};
+use crate::newton_workspace::ResolveError;
+
+/// # Severity
+///
+/// How bad a diagnostic is. Only errors stop a program from running.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Severity {
+    Help,    // a hint on how to fix something
+    Note,    // extra context
+    Warning, // works, but probably isn't what was meant
+    Error,   // can't go on
+}
+
+impl Severity {
+    /// the ANSI color code it's shown in, red errors, yellow warnings, cyan notes, and green
+    /// help
+    pub fn color(&self) -> &'static str {
+        match self {
+            Severity::Help => "32",
+            Severity::Note => "36",
+            Severity::Warning => "33",
+            Severity::Error => "31",
+        }
+    }
+}
+
+impl std::fmt::Display for Severity {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Severity::Help => write!(f, "help"),
+            Severity::Note => write!(f, "note"),
+            Severity::Warning => write!(f, "warning"),
+            Severity::Error => write!(f, "error"),
+        }
+    }
+}
+
+/// the ANSI color code of the line numbers and arrows, bold blue
+const GUTTER: &str = "1;34";
+
+/// # Color Choice
+///
+/// If rendered diagnostics should be colored. `Auto` colors them when stderr is a terminal,
+/// unless `NO_COLOR` is set, so piped output stays plain.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub enum ColorChoice {
+    #[default]
+    Auto, // when stderr is a terminal and `NO_COLOR` isn't set
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    /// if this means colors, looking at the environment for `Auto`
+    pub fn enabled(&self) -> bool {
+        use std::io::IsTerminal;
+
+        match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => Self::auto(
+                std::env::var_os("NO_COLOR").as_deref(),
+                std::io::stderr().is_terminal(),
+            ),
+        }
+    }
+
+    /// `Auto`, given `NO_COLOR` and if stderr is a terminal. An empty `NO_COLOR` doesn't
+    /// count, see no-color.org.
+    fn auto(no_color: Option<&std::ffi::OsStr>, terminal: bool) -> bool {
+        terminal && no_color.is_none_or(|value| value.is_empty())
+    }
+}
+
+/// # Labels
+///
+/// A secondary span of a diagnostic and what it has to do with it, like where an unclosed
+/// block started. Rendered underlined with `-`, next to the primary span's `^`.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Label {
+    pub span: Span,      // what it points at
+    pub message: String, // e.g. `the block started here`
+}
+
+/// # Children
+///
+/// A `note:` or `help:` line under a diagnostic, with more context or a hint on how to fix
+/// it.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Child {
+    pub severity: Severity, // `Note` or `Help`
+    pub message: String,    // what it says
+}
+
+/// # Applicability
+///
+/// How sure a suggestion is. Only machine-applicable ones are applied without asking, see
+/// [`apply_suggestions`].
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Applicability {
+    MachineApplicable, // certainly right, safe to apply
+    MaybeIncorrect,    // probably right, a person should look
+    HasPlaceholders,   // needs filling in, like `new <name> { }`
+    Unspecified,       // nobody knows
+}
+
+impl Applicability {
+    /// a short lowercase name, e.g. for JSON
+    pub fn name(&self) -> &'static str {
+        match self {
+            Applicability::MachineApplicable => "machine-applicable",
+            Applicability::MaybeIncorrect => "maybe-incorrect",
+            Applicability::HasPlaceholders => "has-placeholders",
+            Applicability::Unspecified => "unspecified",
+        }
+    }
+}
+
+impl std::fmt::Display for Applicability {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// # Suggestions
+///
+/// A fix for a diagnostic, as an edit to the source: `span` is replaced with `replacement`.
+/// An empty span inserts, an empty replacement removes.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Suggestion {
+    pub span: Span,                   // what to replace
+    pub replacement: String,          // what to replace it with
+    pub applicability: Applicability, // how sure it is
+}
+
+impl Suggestion {
+    /// what the edit does, in words, e.g. ``insert `"` ``
+    pub fn describe(&self) -> String {
+        match (self.span.is_empty(), self.replacement.is_empty()) {
+            (true, _) => format!("insert `{}`", self.replacement),
+            (false, true) => "remove this".to_owned(),
+            (false, false) => format!("replace with `{}`", self.replacement),
+        }
+    }
+}
+
+/// # Diagnostics
+///
+/// A message about a span of the source. Displays as `error[N0001]: message`, or without
+/// the code if it has none, and without the span, which only means something next to the
+/// source it came from.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    pub severity: Severity,           // how bad it is
+    pub code: Option<String>,         // the error code, see `newton_codes`
+    pub message: String,              // what happened
+    pub span: Span,                   // where it happened
+    pub labels: Vec<Label>,           // other spans involved
+    pub children: Vec<Child>,         // notes and help
+    pub suggestions: Vec<Suggestion>, // fixes
+}
+
+impl Diagnostic {
+    pub fn new(severity: Severity, message: impl Into<String>, span: Span) -> Self {
+        Self {
+            severity,
+            code: None,
+            message: message.into(),
+            span,
+            labels: Vec::new(),
+            children: Vec::new(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// offers a fix, replacing `span` with `replacement`
+    pub fn with_suggestion(
+        mut self,
+        span: Span,
+        replacement: impl Into<String>,
+        applicability: Applicability,
+    ) -> Self {
+        self.suggestions.push(Suggestion {
+            span,
+            replacement: replacement.into(),
+            applicability,
+        });
+        self
+    }
+
+    /// points at another span involved, with a message of its own
+    pub fn with_label(mut self, span: Span, message: impl Into<String>) -> Self {
+        self.labels.push(Label {
+            span,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// adds a `note:` line
+    pub fn with_note(mut self, message: impl Into<String>) -> Self {
+        self.children.push(Child {
+            severity: Severity::Note,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// adds a `help:` line
+    pub fn with_help(mut self, message: impl Into<String>) -> Self {
+        self.children.push(Child {
+            severity: Severity::Help,
+            message: message.into(),
+        });
+        self
+    }
+
+    /// gives the diagnostic an error code
+    pub fn with_code(mut self, code: impl Into<String>) -> Self {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// the severity and code, e.g. `error[N0001]`
+    fn title(&self) -> String {
+        match &self.code {
+            Some(code) => format!("{}[{}]", self.severity, code),
+            None => self.severity.to_string(),
+        }
+    }
+
+    /// an `Error` diagnostic
+    pub fn error(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Error, message, span)
+    }
+
+    /// a `Warning` diagnostic
+    pub fn warning(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Warning, message, span)
+    }
+
+    /// a `Note` diagnostic
+    pub fn note(message: impl Into<String>, span: Span) -> Self {
+        Self::new(Severity::Note, message, span)
+    }
+
+    /// if this stops the program from running
+    pub fn is_error(&self) -> bool {
+        self.severity == Severity::Error
+    }
+
+    /// # Rendering
+    ///
+    /// The diagnostic with the lines it points at, numbered, and the span underlined, the
+    /// way rustc does it.
+    ///
+    /// ```
+    /// # use newton::newton_diag::Diagnostic;
+    /// # use newton::newton_lex::Lexer;
+    /// # use newton::newton_source::SourceMap;
+    /// let mut map = SourceMap::new();
+    /// let file = map.add("main.newton", "new x {\n  ::stdout write \"hi\n}");
+    ///
+    /// let mut lexer = Lexer::new(&map.file(file).text);
+    /// lexer.lexeme();
+    ///
+    /// assert_eq!(
+    ///     Diagnostic::from(&lexer.errors[0]).render(map.file(file)),
+    ///     "error[N0001]: unterminated string
+    ///  --> main.newton:2:18
+    ///   |
+    /// 2 |   ::stdout write \"hi
+    ///   |                  ^
+    /// "
+    /// );
+    /// ```
+    ///
+    /// A span over several lines underlines its part of each. Labels are underlined with `-`
+    /// and their message, and notes and help come last, after a `=`. A span that doesn't fit
+    /// the file only gets the message, the path, and the notes.
+    pub fn render(&self, file: &SourceFile) -> String {
+        self.render_with(file, false)
+    }
+
+    /// `render`, with ANSI colors when `color` is set, see [`ColorChoice`]
+    pub fn render_with(&self, file: &SourceFile, color: bool) -> String {
+        let paint = |code: &str, text: &str| match color {
+            true => format!("\x1b[{}m{}\x1b[0m", code, text),
+            false => text.to_owned(),
+        };
+        let tint = format!("1;{}", self.severity.color());
+
+        let mut out = format!(
+            "{}{}\n",
+            paint(&tint, &self.title()),
+            paint("1", &format!(": {}", self.message))
+        );
+        let Some(start) = file.line_col(self.span.start) else {
+            writeln!(out, " {} {}", paint(GUTTER, "-->"), file.path.display()).unwrap();
+            self.render_children(&mut out, 1, &paint);
+            return out;
+        };
+
+        // every span that fits the file, the primary one first, with the lines it's on
+        let marks: Vec<(Span, Option<&str>, usize, usize)> = std::iter::once((self.span, None))
+            .chain(
+                self.labels
+                    .iter()
+                    .map(|l| (l.span, Some(l.message.as_str()))),
+            )
+            .filter_map(|(span, label)| {
+                let first = file.lines.line(span.start)?;
+                let last = file
+                    .lines
+                    .line(span.start.max(span.end.saturating_sub(1)))?;
+
+                Some((span, label, first, last))
+            })
+            .collect();
+
+        let mut lines: Vec<usize> = marks
+            .iter()
+            .flat_map(|&(_, _, first, last)| first..=last)
+            .collect();
+        lines.sort_unstable();
+        lines.dedup();
+
+        let width = lines.last().map_or(1, |line| line.to_string().len());
+        let gutter = |number: &str| paint(GUTTER, &format!("{:>width$} |", number));
+
+        writeln!(
+            out,
+            "{:width$}{} {}:{}",
+            "",
+            paint(GUTTER, "-->"),
+            file.path.display(),
+            start
+        )
+        .unwrap();
+        writeln!(out, "{}", gutter("")).unwrap();
+
+        for (i, &line) in lines.iter().enumerate() {
+            let Some(range) = file.lines.line_range(line) else {
+                break;
+            };
+
+            if i > 0 && lines[i - 1] + 1 < line {
+                writeln!(out, "{}", paint(GUTTER, "...")).unwrap();
+            }
+
+            let text = range.slice_clamped(&file.text).trim_end();
+
+            match text.is_empty() {
+                true => writeln!(out, "{}", gutter(&line.to_string())).unwrap(),
+                false => writeln!(out, "{} {}", gutter(&line.to_string()), text).unwrap(),
+            }
+
+            for &(span, label, first, last) in &marks {
+                if line < first || line > last {
+                    continue;
+                }
+
+                let from = span.start.max(range.start);
+                let to = span.end.min(range.end);
+                let pad = Span::new(range.start, from).slice_clamped(&file.text);
+                let marked = Span::new(from, to).slice_clamped(&file.text);
+                let width = marked.chars().count().max(1);
+
+                let underline = match label {
+                    None => paint(&tint, &"^".repeat(width)),
+                    Some(message) if line == last => {
+                        paint(GUTTER, &format!("{} {}", "-".repeat(width), message))
+                    }
+                    Some(_) => paint(GUTTER, &"-".repeat(width)),
+                };
+
+                writeln!(
+                    out,
+                    "{} {}{}",
+                    gutter(""),
+                    " ".repeat(pad.chars().count()),
+                    underline
+                )
+                .unwrap();
+            }
+        }
+
+        self.render_children(&mut out, width, &paint);
+        out
+    }
+
+    /// the `= note:` and `= help:` lines, lined up with a gutter `width` digits wide
+    fn render_children(
+        &self,
+        out: &mut String,
+        width: usize,
+        paint: &dyn Fn(&str, &str) -> String,
+    ) {
+        let suggestions = self
+            .suggestions
+            .iter()
+            .map(|s| (Severity::Help, s.describe()));
+        let children = self
+            .children
+            .iter()
+            .map(|c| (c.severity, c.message.clone()))
+            .chain(suggestions);
+
+        for (severity, message) in children {
+            writeln!(
+                out,
+                "{:width$} {} {}: {}",
+                "",
+                paint(GUTTER, "="),
+                paint("1", &severity.to_string()),
+                message
+            )
+            .unwrap();
+        }
+    }
+}
+
+impl Diagnostic {
+    /// # JSON
+    ///
+    /// The diagnostic as one line of JSON, for build systems and editors. `file` is the file
+    /// it's about, and the span also comes as the 1-based line and column it starts at.
+    ///
+    /// ```
+    /// # use newton::newton_diag::Diagnostic;
+    /// # use newton::newton_lex::Span;
+    /// # use newton::newton_source::SourceMap;
+    /// let mut map = SourceMap::new();
+    /// let file = map.add("main.newton", "new x \"open");
+    ///
+    /// assert_eq!(
+    ///     Diagnostic::error("unterminated string", Span::new(6, 7)).to_json(map.file(file)),
+    ///     r#"{"code":null,"severity":"error","file":"main.newton","span":{"start":6,"end":7,"line":1,"column":7},"message":"unterminated string","suggestions":[],"labels":[],"children":[]}"#
+    /// );
+    /// ```
+    ///
+    /// `code` is `null` for a diagnostic without one. Suggestions and labels come with their
+    /// spans like the diagnostic's own, and children with their severity. It only ever grows,
+    /// the same as the [`newton_dump`](crate::newton_dump) format.
+    pub fn to_json(&self, file: &SourceFile) -> String {
+        let mut out = String::from("{\"code\":");
+
+        match &self.code {
+            Some(code) => string(&mut out, code),
+            None => out.push_str("null"),
+        }
+
+        out.push_str(",\"severity\":");
+        string(&mut out, &self.severity.to_string());
+        out.push_str(",\"file\":");
+        string(&mut out, &file.path.to_string_lossy());
+        out.push_str(",\"span\":");
+        json_span(&mut out, file, self.span);
+        out.push_str(",\"message\":");
+        string(&mut out, &self.message);
+        out.push_str(",\"suggestions\":[");
+
+        for (i, suggestion) in self.suggestions.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            out.push_str("{\"span\":");
+            json_span(&mut out, file, suggestion.span);
+            out.push_str(",\"replacement\":");
+            string(&mut out, &suggestion.replacement);
+            out.push_str(",\"applicability\":");
+            string(&mut out, suggestion.applicability.name());
+            out.push('}');
+        }
+
+        out.push_str("],\"labels\":[");
+
+        for (i, label) in self.labels.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            out.push_str("{\"span\":");
+            json_span(&mut out, file, label.span);
+            out.push_str(",\"message\":");
+            string(&mut out, &label.message);
+            out.push('}');
+        }
+
+        out.push_str("],\"children\":[");
+
+        for (i, child) in self.children.iter().enumerate() {
+            if i > 0 {
+                out.push(',');
+            }
+
+            out.push_str("{\"severity\":");
+            string(&mut out, &child.severity.to_string());
+            out.push_str(",\"message\":");
+            string(&mut out, &child.message);
+            out.push('}');
+        }
+
+        out.push_str("]}");
+        out
+    }
+}
+
+/// a span as JSON, with the 1-based line and column it starts at, `null` if it doesn't fit
+fn json_span(out: &mut String, file: &SourceFile, span: Span) {
+    write!(out, "{{\"start\":{},\"end\":{}", span.start, span.end).unwrap();
+
+    match file.line_col(span.start) {
+        Some(at) => write!(out, ",\"line\":{},\"column\":{}}}", at.line, at.column).unwrap(),
+        None => out.push_str(",\"line\":null,\"column\":null}"),
+    }
+}
+
+/// # Applying Suggestions
+///
+/// `source` with every machine-applicable suggestion of `diagnostics` applied, the rest are
+/// left for a person. Edits are made in the order of their spans, and one that overlaps an
+/// edit before it, or doesn't fit the source, is skipped.
+///
+/// ```
+/// # use newton::newton_diag::{apply_suggestions, Applicability, Diagnostic};
+/// # use newton::newton_lex::Span;
+/// let source = "::stdout write \"hi";
+/// let diagnostic = Diagnostic::error("unterminated string", Span::new(15, 16))
+///     .with_suggestion(Span::new(18, 18), "\"", Applicability::MachineApplicable);
+///
+/// assert_eq!(apply_suggestions(source, &[diagnostic]), "::stdout write \"hi\"");
+/// ```
+pub fn apply_suggestions(source: &str, diagnostics: &[Diagnostic]) -> String {
+    let mut edits: Vec<&Suggestion> = diagnostics
+        .iter()
+        .flat_map(|d| &d.suggestions)
+        .filter(|s| s.applicability == Applicability::MachineApplicable)
+        .collect();
+    edits.sort_by_key(|s| (s.span.start, s.span.end));
+
+    let mut out = String::with_capacity(source.len());
+    let mut at = 0;
+
+    for edit in edits {
+        let span = edit.span;
+
+        if span.start < at || source.get(span.start..span.end).is_none() {
+            continue;
+        }
+
+        out.push_str(&source[at..span.start]);
+        out.push_str(&edit.replacement);
+        at = span.end;
+    }
+
+    out.push_str(&source[at..]);
+    out
+}
+
+/// every diagnostic about `file` as line-delimited JSON, one `Diagnostic::to_json` per line
+pub fn json_lines(diagnostics: &[Diagnostic], file: &SourceFile) -> String {
+    let mut out = String::new();
+
+    for diagnostic in diagnostics {
+        out.push_str(&diagnostic.to_json(file));
+        out.push('\n');
+    }
+
+    out
+}
+
+/// # SARIF
+///
+/// Diagnostics as a SARIF 2.1.0 log, the format code-scanning dashboards read. Each
+/// diagnostic comes with the file it's about, like the files from `lex_many`.
+///
+/// Help is logged as a note, SARIF has nothing closer, and columns count characters. Labels
+/// become related locations.
+///
+/// ```
+/// # use newton::newton_diag::{sarif, Diagnostic};
+/// # use newton::newton_lex::Span;
+/// # use newton::newton_source::SourceMap;
+/// let mut map = SourceMap::new();
+/// let file = map.add("main.newton", "new x `");
+///
+/// let log = sarif(&map, &[(file, Diagnostic::error("unexpected character", Span::new(6, 7)))]);
+///
+/// assert!(log.starts_with(r#"{"version":"2.1.0","#));
+/// assert!(log.contains(r#""region":{"startLine":1,"startColumn":7,"endLine":1,"endColumn":8"#));
+/// ```
+pub fn sarif(map: &SourceMap, diagnostics: &[(FileId, Diagnostic)]) -> String {
+    let mut out = String::from(
+        "{\"version\":\"2.1.0\",\"$schema\":\"https://json.schemastore.org/sarif-2.1.0.json\",\"runs\":[{",
+    );
+
+    write!(
+        out,
+        "\"tool\":{{\"driver\":{{\"name\":\"newton\",\"version\":\"{}\"}}}},",
+        env!("CARGO_PKG_VERSION")
+    )
+    .unwrap();
+    out.push_str("\"columnKind\":\"unicodeCodePoints\",\"results\":[");
+
+    for (i, (file, diagnostic)) in diagnostics.iter().enumerate() {
+        let file = map.file(*file);
+
+        if i > 0 {
+            out.push(',');
+        }
+
+        out.push('{');
+
+        if let Some(code) = &diagnostic.code {
+            out.push_str("\"ruleId\":");
+            string(&mut out, code);
+            out.push(',');
+        }
+
+        out.push_str("\"level\":");
+        string(
+            &mut out,
+            match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+                Severity::Note | Severity::Help => "note",
+            },
+        );
+        out.push_str(",\"message\":{\"text\":");
+        string(&mut out, &diagnostic.message);
+        out.push_str("},\"locations\":[{");
+        sarif_location(&mut out, file, diagnostic.span);
+        out.push_str("}]");
+
+        if !diagnostic.labels.is_empty() {
+            out.push_str(",\"relatedLocations\":[");
+
+            for (i, label) in diagnostic.labels.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+
+                out.push('{');
+                sarif_location(&mut out, file, label.span);
+                out.push_str(",\"message\":{\"text\":");
+                string(&mut out, &label.message);
+                out.push_str("}}");
+            }
+
+            out.push(']');
+        }
+
+        out.push('}');
+    }
+
+    out.push_str("]}]}");
+    out
+}
+
+/// a SARIF `physicalLocation`, without a region if the span doesn't fit the file
+fn sarif_location(out: &mut String, file: &SourceFile, span: Span) {
+    out.push_str("\"physicalLocation\":{\"artifactLocation\":{\"uri\":");
+    string(out, &file.path.to_string_lossy().replace('\\', "/"));
+    out.push('}');
+
+    if let (Some(start), Some(end)) = (file.line_col(span.start), file.line_col(span.end)) {
+        write!(
+            out,
+            ",\"region\":{{\"startLine\":{},\"startColumn\":{},\"endLine\":{},\"endColumn\":{}}}",
+            start.line, start.column, end.line, end.column
+        )
+        .unwrap();
+    }
+
+    out.push('}');
+}
+
+/// # Diagnostic Sinks
+///
+/// Somewhere diagnostics go as they're found, like `Lexer::lexeme_into`'s, so a language
+/// server or a test can act on each one without waiting for the whole run.
+///
+/// A `Vec<Diagnostic>` collects them, and any `FnMut(Diagnostic)` is a sink too.
+///
+/// ```
+/// # use newton::newton_diag::{Diagnostic, DiagnosticSink};
+/// # use newton::newton_lex::Span;
+/// let mut errors = 0;
+/// let mut count = |d: Diagnostic| errors += d.is_error() as usize;
+///
+/// count.report(Diagnostic::error("oops", Span::new(0, 1)));
+/// count.report(Diagnostic::warning("hmm", Span::new(0, 1)));
+///
+/// assert_eq!(errors, 1);
+/// ```
+pub trait DiagnosticSink {
+    fn report(&mut self, diagnostic: Diagnostic);
+}
+
+impl DiagnosticSink for Vec<Diagnostic> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self.push(diagnostic);
+    }
+}
+
+impl<F: FnMut(Diagnostic)> DiagnosticSink for F {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        self(diagnostic)
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}: {}", self.title(), self.message)
+    }
+}
+
+impl From<&LexError> for Diagnostic {
+    fn from(error: &LexError) -> Self {
+        Diagnostic::error(error.kind.to_string(), error.span).with_code(error.kind.code())
+    }
+}
+
+impl From<LexError> for Diagnostic {
+    fn from(error: LexError) -> Self {
+        Diagnostic::from(&error)
+    }
+}
+
+impl From<&ParseError> for Diagnostic {
+    fn from(error: &ParseError) -> Self {
+        Diagnostic::error(error.kind.to_string(), error.span).with_code(error.kind.code())
+    }
+}
+
+impl From<ParseError> for Diagnostic {
+    fn from(error: ParseError) -> Self {
+        Diagnostic::from(&error)
+    }
+}
+
+impl From<&ResolveError> for Diagnostic {
+    fn from(error: &ResolveError) -> Self {
+        Diagnostic::error(error.kind.to_string(), error.span).with_code(error.kind.code())
+    }
+}
+
+impl From<ResolveError> for Diagnostic {
+    fn from(error: ResolveError) -> Self {
+        Diagnostic::from(&error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_lex::{Lexer, Type};
+    use crate::newton_parse::TokenStream;
+    use crate::newton_source::SourceMap;
+
+    #[test]
+    pub fn test_diag_from_errors() {
+        let mut lexer = Lexer::new("new ` {");
+        let mut stream = TokenStream::new(lexer.lexeme());
+
+        stream.bump();
+
+        let lexed = Diagnostic::from(&lexer.errors[0]);
+        let parsed = Diagnostic::from(stream.expect(Type::Ident).unwrap_err());
+
+        assert_eq!(lexed.span, Span::new(4, 5));
+        assert_eq!(lexed.message, "unexpected character ```");
+        assert_eq!(
+            parsed.to_string(),
+            "error[N0100]: expected Ident, found OpenBrace"
+        );
+        assert_eq!(lexed.code.as_deref(), Some("N0003"));
+        assert!(parsed.is_error());
+    }
+
+    #[test]
+    pub fn test_diag_render_many_lines() {
+        let mut map = SourceMap::new();
+        let file = map.add("a.newton", "x ;* é\n\n  y *; z");
+
+        let span = Span::new(2, 15);
+        let rendered = Diagnostic::warning("a comment", span).render(map.file(file));
+
+        assert_eq!(
+            rendered,
+            "warning: a comment
+ --> a.newton:1:3
+  |
+1 | x ;* é
+  |   ^^^^
+2 |
+  | ^
+3 |   y *; z
+  | ^^^^^^
+"
+        );
+    }
+
+    #[test]
+    pub fn test_diag_render_ends() {
+        let mut map = SourceMap::new();
+        let file = map.add("b.newton", "new");
+
+        assert_eq!(
+            Diagnostic::error("expected a name", Span::new(3, 3)).render(map.file(file)),
+            "error: expected a name\n --> b.newton:1:4\n  |\n1 | new\n  |    ^\n"
+        );
+        assert_eq!(
+            Diagnostic::error("nowhere", Span::new(9, 12)).render(map.file(file)),
+            "error: nowhere\n --> b.newton\n"
+        );
+    }
+
+    #[test]
+    pub fn test_diag_render_labels() {
+        let mut map = SourceMap::new();
+        let file = map.add("d.newton", "new x {\n  a\n  b\n  c\n");
+
+        let diagnostic = Diagnostic::error("expected `}`, found the end", Span::new(20, 20))
+            .with_label(Span::new(6, 7), "the block started here")
+            .with_note("blocks are closed with `}`")
+            .with_help("add a `}` after `c`");
+
+        assert_eq!(
+            diagnostic.render(map.file(file)),
+            "error: expected `}`, found the end
+ --> d.newton:5:1
+  |
+1 | new x {
+  |       - the block started here
+...
+5 |
+  | ^
+  = note: blocks are closed with `}`
+  = help: add a `}` after `c`
+"
+        );
+        assert_eq!(
+            Diagnostic::error("nowhere", Span::new(99, 99))
+                .with_help("somewhere")
+                .render(map.file(file)),
+            "error: nowhere\n --> d.newton\n  = help: somewhere\n"
+        );
+    }
+
+    #[test]
+    pub fn test_diag_render_label_same_line() {
+        let mut map = SourceMap::new();
+        let file = map.add("e.newton", "x = y + z");
+
+        let diagnostic = Diagnostic::warning("odd sum", Span::new(4, 9))
+            .with_label(Span::new(0, 1), "assigned here")
+            .with_label(Span::new(50, 51), "dropped, it doesn't fit");
+
+        assert_eq!(
+            diagnostic.render(map.file(file)),
+            "warning: odd sum\n --> e.newton:1:5\n  |\n1 | x = y + z\n  |     ^^^^^\n  | - assigned here\n"
+        );
+    }
+
+    #[test]
+    pub fn test_diag_suggestions() {
+        let mut map = SourceMap::new();
+        let file = map.add("g.newton", "new x { ; }");
+
+        let diagnostic = Diagnostic::error("a comment hides the `}`", Span::new(8, 9))
+            .with_suggestion(Span::new(8, 11), "}", Applicability::MachineApplicable)
+            .with_suggestion(Span::new(8, 9), "", Applicability::MaybeIncorrect);
+
+        assert!(diagnostic
+            .render(map.file(file))
+            .ends_with("  = help: replace with `}`\n  = help: remove this\n"));
+        assert_eq!(
+            apply_suggestions(&map.file(file).text, std::slice::from_ref(&diagnostic)),
+            "new x { }"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&diagnostic.to_json(map.file(file))).unwrap();
+
+        assert_eq!(json["suggestions"][0]["replacement"], "}");
+        assert_eq!(json["suggestions"][0]["span"]["end"], 11);
+        assert_eq!(json["suggestions"][1]["applicability"], "maybe-incorrect");
+    }
+
+    #[test]
+    pub fn test_diag_apply_overlapping() {
+        let source = "a b c";
+        let fix = |start, end, text: &str| {
+            Diagnostic::warning("fix", Span::new(start, end)).with_suggestion(
+                Span::new(start, end),
+                text,
+                Applicability::MachineApplicable,
+            )
+        };
+
+        let diagnostics = [
+            fix(4, 5, "z"),
+            fix(0, 3, "x"),
+            fix(2, 3, "skipped, overlaps"),
+            fix(9, 9, "skipped, past the end"),
+            fix(5, 5, "!"),
+        ];
+
+        assert_eq!(apply_suggestions(source, &diagnostics), "x z!");
+        assert_eq!(apply_suggestions(source, &[]), source);
+    }
+
+    #[test]
+    pub fn test_diag_render_colored() {
+        let mut map = SourceMap::new();
+        let file = map.add("c.newton", "x");
+
+        let plain = Diagnostic::warning("careful", Span::new(0, 1));
+        let colored = plain.render_with(map.file(file), true);
+
+        assert!(colored.starts_with("\x1b[1;33mwarning\x1b[0m\x1b[1m: careful\x1b[0m\n"));
+        assert!(colored.contains("\x1b[1;34m1 |\x1b[0m x\n"));
+        assert!(colored.contains("\x1b[1;33m^\x1b[0m"));
+        assert_eq!(
+            plain.render_with(map.file(file), false),
+            plain.render(map.file(file))
+        );
+    }
+
+    #[test]
+    pub fn test_diag_color_choice() {
+        let set = Some(std::ffi::OsStr::new("1"));
+        let empty = Some(std::ffi::OsStr::new(""));
+
+        assert!(ColorChoice::auto(None, true));
+        assert!(ColorChoice::auto(empty, true));
+        assert!(!ColorChoice::auto(set, true));
+        assert!(!ColorChoice::auto(None, false));
+        assert!(ColorChoice::Always.enabled());
+        assert!(!ColorChoice::Never.enabled());
+    }
+
+    #[test]
+    pub fn test_diag_json_lines() {
+        let mut map = SourceMap::new();
+        let file = map.add("dir\\\"q\".newton", "a\n\"b\\q\"");
+
+        let mut lexer = Lexer::new(&map.file(file).text);
+        lexer.lexeme();
+
+        let mut diagnostics: Vec<Diagnostic> = lexer.errors.iter().map(Diagnostic::from).collect();
+        diagnostics.push(Diagnostic::note("past the end", Span::new(40, 41)));
+
+        let lines = json_lines(&diagnostics, map.file(file));
+        let parsed: Vec<serde_json::Value> = lines
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0]["file"], "dir\\\"q\".newton");
+        assert_eq!(parsed[0]["message"], "unknown escape sequence `\\q`");
+        assert_eq!(parsed[0]["span"]["line"], 2);
+        assert_eq!(parsed[0]["span"]["column"], 3);
+        assert_eq!(parsed[1]["severity"], "note");
+        assert!(parsed[1]["span"]["line"].is_null());
+        assert_eq!(parsed[0]["code"], "N0005");
+        assert!(parsed[1]["code"].is_null());
+        assert_eq!(parsed[1]["labels"], serde_json::json!([]));
+    }
+
+    #[test]
+    pub fn test_diag_json_labels() {
+        let mut map = SourceMap::new();
+        let file = map.add("f.newton", "new x {");
+
+        let json = Diagnostic::error("unclosed", Span::new(7, 7))
+            .with_label(Span::new(6, 7), "opened here")
+            .with_note("a note")
+            .to_json(map.file(file));
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed["labels"][0]["span"]["column"], 7);
+        assert_eq!(parsed["labels"][0]["message"], "opened here");
+        assert_eq!(parsed["children"][0]["severity"], "note");
+        assert_eq!(parsed["children"][0]["message"], "a note");
+    }
+
+    #[test]
+    pub fn test_diag_sarif() {
+        let mut map = SourceMap::new();
+        let a = map.add("src\\a.newton", "é `");
+        let b = map.add("b.newton", "x");
+
+        let log = sarif(
+            &map,
+            &[
+                (
+                    a,
+                    Diagnostic::error("unexpected", Span::new(3, 4))
+                        .with_code("N0003")
+                        .with_label(Span::new(0, 2), "after this"),
+                ),
+                (
+                    b,
+                    Diagnostic::new(Severity::Help, "try this", Span::new(0, 1)),
+                ),
+                (b, Diagnostic::warning("nowhere", Span::new(8, 9))),
+            ],
+        );
+        let log: serde_json::Value = serde_json::from_str(&log).unwrap();
+        let results = &log["runs"][0]["results"];
+
+        assert_eq!(log["version"], "2.1.0");
+        assert_eq!(log["runs"][0]["tool"]["driver"]["name"], "newton");
+        assert_eq!(results[0]["level"], "error");
+        assert_eq!(results[0]["ruleId"], "N0003");
+        assert!(results[1]["ruleId"].is_null());
+        assert_eq!(
+            results[0]["relatedLocations"][0]["message"]["text"],
+            "after this"
+        );
+        assert!(results[1]["relatedLocations"].is_null());
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"]["artifactLocation"]["uri"],
+            "src/a.newton"
+        );
+        assert_eq!(
+            results[0]["locations"][0]["physicalLocation"]["region"]["startColumn"],
+            3
+        );
+        assert_eq!(results[1]["level"], "note");
+        assert_eq!(results[1]["message"]["text"], "try this");
+        assert!(results[2]["locations"][0]["physicalLocation"]["region"].is_null());
+    }
+
+    #[test]
+    pub fn test_diag_severity() {
+        let warning = Diagnostic::warning("unused variable", Span::new(0, 3));
+
+        assert_eq!(warning.to_string(), "warning: unused variable");
+        assert!(!warning.is_error());
+        assert!(Severity::Error > Severity::Warning);
+        assert!(Severity::Warning > Severity::Note);
+    }
+}
@@ -0,0 +1,559 @@
+//! # Newton Dump
+//!
+//! A stable JSON form of a token stream, for editors, highlighters, and test harnesses that
+//! don't want to link against the lexer. `newton tokens --json` prints it.
+//!
+//! ```json
+//! {
+//!   "version": 1,
+//!   "tokens": [
+//!     { "kind": "ReservedKeyword", "text": "new", "start": 0, "end": 3, "value": null },
+//!     { "kind": "Number", "text": "0x1F", "start": 4, "end": 8, "value": 31 }
+//!   ],
+//!   "errors": [
+//!     { "message": "unexpected character `!`", "start": 9, "end": 10 }
+//!   ]
+//! }
+//! ```
+//!
+//! `start` and `end` are byte offsets, end exclusive, and `text` is the source they cover,
+//! quotes included. `value` is a number, a string, or `null`, and an interned identifier's
+//! value is its symbol's index. The format only ever grows, and `version` goes up if it has
+//! to change.
+//!
+//! `program_sexp` writes a parsed program as S-expressions, a line to an item, for snapshot
+//! tests of the parser, where a change in the tree shows up as a change in a line:
+//!
+//! ```text
+//! (new hello_world (conditions any) (logic (::stdout write "hi")))
+//! ```
+
+use std::fmt::Write;
+
+use crate::newton_ast::{
+    Call, ConditionKind, Else, Expr, ExprKind, Item, Pattern, PatternKind, Program, Section, Stmt,
+    StmtKind, StringPart,
+};
+use crate::newton_lex::{LexError, Lexer, Token, TokenValue};
+
+/// the version of the format written by `tokens_json`
+pub const VERSION: u32 = 1;
+
+/// writes tokens and errors from `source` as JSON, on one line
+///
+/// ```
+/// # use newton::newton_dump::tokens_json;
+/// # use newton::newton_lex::Lexer;
+/// let mut lexer = Lexer::new("x");
+/// let tokens = lexer.lexeme();
+///
+/// assert_eq!(
+///     tokens_json("x", &tokens, &lexer.errors),
+///     r#"{"version":1,"tokens":[{"kind":"Ident","text":"x","start":0,"end":1,"value":null},{"kind":"Eof","text":"","start":1,"end":1,"value":null}],"errors":[]}"#
+/// );
+/// ```
+pub fn tokens_json(source: &str, tokens: &[Token], errors: &[LexError]) -> String {
+    let mut out = String::new();
+
+    write!(out, "{{\"version\":{},\"tokens\":[", VERSION).unwrap();
+
+    for (i, token) in tokens.iter().enumerate() {
+        let span = token.span;
+
+        if i > 0 {
+            out.push(',');
+        }
+
+        out.push_str("{\"kind\":");
+        string(&mut out, &token.ty.to_string());
+        out.push_str(",\"text\":");
+        string(&mut out, span.slice_clamped(source));
+        write!(
+            out,
+            ",\"start\":{},\"end\":{},\"value\":",
+            span.start, span.end
+        )
+        .unwrap();
+        value(&mut out, token.value.as_ref());
+        out.push('}');
+    }
+
+    out.push_str("],\"errors\":[");
+
+    for (i, error) in errors.iter().enumerate() {
+        if i > 0 {
+            out.push(',');
+        }
+
+        out.push_str("{\"message\":");
+        string(&mut out, &error.kind.to_string());
+        write!(
+            out,
+            ",\"start\":{},\"end\":{}}}",
+            error.span.start, error.span.end
+        )
+        .unwrap();
+    }
+
+    out.push_str("]}");
+    out
+}
+
+impl Lexer<'_> {
+    /// lexes the whole buffer and writes it out with `tokens_json`
+    pub fn lexeme_json(&mut self) -> String {
+        let tokens = self.lexeme();
+
+        tokens_json(self.buffer, &tokens, &self.errors)
+    }
+}
+
+fn value(out: &mut String, value: Option<&TokenValue>) {
+    match value {
+        Some(TokenValue::Number(n)) if n.is_finite() => write!(out, "{}", n).unwrap(),
+        Some(TokenValue::Str(s)) => string(out, s),
+        Some(TokenValue::Char(c)) => string(out, c.encode_utf8(&mut [0; 4])),
+        Some(TokenValue::Symbol(symbol)) => write!(out, "{}", symbol.index()).unwrap(),
+        _ => out.push_str("null"),
+    }
+}
+
+/// a JSON string, escaped
+pub(crate) fn string(out: &mut String, s: &str) {
+    out.push('"');
+
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+}
+
+/// writes `program` as S-expressions, one line for each item
+///
+/// Spans are left out, so moving code around without changing it changes nothing. Each node
+/// is its name and its parts, `(+ 1 2)`, `(let x 5)`, and names and literals are written as
+/// they are, strings quoted like in `tokens_json`.
+///
+/// ```
+/// # use newton::newton_dump::program_sexp;
+/// # use newton::newton_parse::parse_source;
+/// let (program, _) = parse_source("new x { logic { let a = 1 + 2 } }");
+///
+/// assert_eq!(program_sexp(&program), "(new x (logic (let a (+ 1 2))))");
+/// ```
+pub fn program_sexp(program: &Program) -> String {
+    let items: Vec<String> = program
+        .items
+        .iter()
+        .map(|each| {
+            let mut out = String::new();
+
+            item(&mut out, each);
+            out
+        })
+        .collect();
+
+    items.join("\n")
+}
+
+fn item(out: &mut String, item: &Item) {
+    match item {
+        Item::Include(include) => {
+            out.push_str("(include ");
+            string(out, &include.path);
+            out.push(')');
+        }
+        Item::Use(import) => {
+            out.push_str("(use");
+
+            for name in &import.path {
+                write!(out, " {}", name.name).unwrap();
+            }
+
+            out.push(')');
+        }
+        Item::Fn(function) => {
+            write!(out, "(fn {} (", function.name.name).unwrap();
+            names(out, function.params.iter().map(|param| param.name.as_str()));
+            out.push(')');
+            stmts(out, &function.body);
+            out.push(')');
+        }
+        Item::Struct(record) => {
+            write!(out, "(struct {}", record.name.name).unwrap();
+
+            for field in &record.fields {
+                write!(out, " {}", field.name).unwrap();
+            }
+
+            out.push(')');
+        }
+        Item::Enum(choice) => {
+            write!(out, "(enum {}", choice.name.name).unwrap();
+
+            for variant in &choice.variants {
+                write!(out, " {}", variant.name).unwrap();
+            }
+
+            out.push(')');
+        }
+        Item::Block(block) => {
+            write!(out, "(new {}", block.name.name).unwrap();
+
+            for section in &block.sections {
+                out.push(' ');
+                self::section(out, section);
+            }
+
+            out.push(')');
+        }
+    }
+}
+
+/// names split up by spaces
+fn names<'a>(out: &mut String, names: impl Iterator<Item = &'a str>) {
+    let names: Vec<&str> = names.collect();
+
+    out.push_str(&names.join(" "));
+}
+
+fn section(out: &mut String, section: &Section) {
+    match section {
+        Section::Conditions(conditions) => {
+            out.push_str("(conditions");
+
+            for condition in &conditions.conditions {
+                out.push(' ');
+
+                match &condition.kind {
+                    ConditionKind::Predicate { name, args } if args.is_empty() => {
+                        out.push_str(&name.name)
+                    }
+                    ConditionKind::Predicate { name, args } => {
+                        write!(out, "({}", name.name).unwrap();
+                        exprs(out, args);
+                        out.push(')');
+                    }
+                    ConditionKind::Directive(name) => write!(out, "%{}", name.name).unwrap(),
+                }
+            }
+
+            out.push(')');
+        }
+        Section::Logic(logic) => {
+            out.push_str("(logic");
+            stmts(out, &logic.body);
+            out.push(')');
+        }
+    }
+}
+
+/// statements, each after a space
+fn stmts(out: &mut String, stmts: &[Stmt]) {
+    for each in stmts {
+        out.push(' ');
+        stmt(out, each);
+    }
+}
+
+fn stmt(out: &mut String, stmt: &Stmt) {
+    match &stmt.kind {
+        StmtKind::Expr(value) => expr(out, value),
+        StmtKind::Collect(collect) => write!(out, "(collect ${})", collect.target.name).unwrap(),
+        StmtKind::For(for_stmt) => {
+            out.push_str("(for ");
+            expr(out, &for_stmt.source);
+            out.push(' ');
+            pattern(out, &for_stmt.binding);
+            stmts(out, &for_stmt.body);
+            out.push(')');
+        }
+        StmtKind::Call(call) => self::call(out, call),
+        StmtKind::Let(let_stmt) => {
+            out.push_str("(let ");
+            pattern(out, &let_stmt.pattern);
+
+            if let Some(value) = &let_stmt.value {
+                out.push(' ');
+                expr(out, value);
+            }
+
+            out.push(')');
+        }
+        StmtKind::Assign(assign) => {
+            let op = assign.op.map(|op| op.symbol()).unwrap_or_default();
+
+            write!(out, "({}= ", op).unwrap();
+            expr(out, &assign.target);
+            out.push(' ');
+            expr(out, &assign.value);
+            out.push(')');
+        }
+        StmtKind::If(if_stmt) => {
+            out.push_str("(if ");
+            expr(out, &if_stmt.condition);
+            out.push_str(" (then");
+            stmts(out, &if_stmt.then);
+            out.push(')');
+
+            match &if_stmt.otherwise {
+                Some(Else::If(nested)) => {
+                    out.push_str(" (else ");
+                    self::stmt(out, nested);
+                    out.push(')');
+                }
+                Some(Else::Block(body)) => {
+                    out.push_str(" (else");
+                    stmts(out, body);
+                    out.push(')');
+                }
+                None => {}
+            }
+
+            out.push(')');
+        }
+        StmtKind::While(while_stmt) => {
+            out.push_str("(while ");
+            expr(out, &while_stmt.condition);
+            stmts(out, &while_stmt.body);
+            out.push(')');
+        }
+        StmtKind::Return(None) => out.push_str("(return)"),
+        StmtKind::Return(Some(value)) => {
+            out.push_str("(return ");
+            expr(out, value);
+            out.push(')');
+        }
+        StmtKind::Break => out.push_str("break"),
+        StmtKind::Continue => out.push_str("continue"),
+    }
+}
+
+/// `(::stdout write x)`
+fn call(out: &mut String, call: &Call) {
+    write!(out, "(::{} {}", call.module.name, call.function.name).unwrap();
+    exprs(out, &call.args);
+    out.push(')');
+}
+
+/// expressions, each after a space
+fn exprs(out: &mut String, exprs: &[Expr]) {
+    for each in exprs {
+        out.push(' ');
+        expr(out, each);
+    }
+}
+
+fn expr(out: &mut String, value: &Expr) {
+    match &value.kind {
+        ExprKind::Number(n) => write!(out, "{}", n).unwrap(),
+        ExprKind::String(s) => string(out, s),
+        ExprKind::Interpolated(parts) => {
+            out.push_str("(str");
+
+            for part in parts {
+                out.push(' ');
+
+                match part {
+                    StringPart::Text(text) => string(out, text),
+                    StringPart::Expr(value) => expr(out, value),
+                }
+            }
+
+            out.push(')');
+        }
+        ExprKind::Char(c) => write!(out, "{:?}", c).unwrap(),
+        ExprKind::Ident(name) => out.push_str(name),
+        ExprKind::Sigil(name) => write!(out, "${}", name).unwrap(),
+        ExprKind::Member { base, member } => {
+            out.push_str("(:: ");
+            expr(out, base);
+            out.push(' ');
+            expr(out, member);
+            out.push(')');
+        }
+        ExprKind::Unary { op, operand } => {
+            write!(out, "({} ", op.symbol()).unwrap();
+            expr(out, operand);
+            out.push(')');
+        }
+        ExprKind::Binary { op, lhs, rhs } => {
+            write!(out, "({} ", op.symbol()).unwrap();
+            expr(out, lhs);
+            out.push(' ');
+            expr(out, rhs);
+            out.push(')');
+        }
+        ExprKind::Call(call) => self::call(out, call),
+        ExprKind::Match(matched) => {
+            out.push_str("(match ");
+            expr(out, &matched.scrutinee);
+
+            for arm in &matched.arms {
+                out.push_str(" (");
+                pattern(out, &arm.pattern);
+                stmts(out, &arm.body);
+                out.push(')');
+            }
+
+            out.push(')');
+        }
+        ExprKind::FnCall { callee, args } => {
+            out.push_str("(call ");
+            expr(out, callee);
+            exprs(out, args);
+            out.push(')');
+        }
+        ExprKind::ListLit(items) => {
+            out.push_str("(list");
+            exprs(out, items);
+            out.push(')');
+        }
+        ExprKind::MapLit(entries) => {
+            out.push_str("(map");
+
+            for entry in entries {
+                out.push_str(" (");
+                expr(out, &entry.key);
+                out.push(' ');
+                expr(out, &entry.value);
+                out.push(')');
+            }
+
+            out.push(')');
+        }
+        ExprKind::Lambda(lambda) => {
+            out.push_str("(fn (");
+            names(out, lambda.params.iter().map(|param| param.name.as_str()));
+            out.push(')');
+            stmts(out, &lambda.body);
+            out.push(')');
+        }
+        ExprKind::Conditional {
+            condition,
+            then,
+            otherwise,
+        } => {
+            out.push_str("(? ");
+            expr(out, condition);
+            out.push(' ');
+            expr(out, then);
+            out.push(' ');
+            expr(out, otherwise);
+            out.push(')');
+        }
+        ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } => {
+            out.push_str(if *inclusive { "(..= " } else { "(.. " });
+            expr(out, start);
+            out.push(' ');
+            expr(out, end);
+            out.push(')');
+        }
+        ExprKind::Field { base, field } => {
+            out.push_str("(. ");
+            expr(out, base);
+            write!(out, " {})", field.name).unwrap();
+        }
+        ExprKind::Index { base, index } => {
+            out.push_str("(index ");
+            expr(out, base);
+            out.push(' ');
+            expr(out, index);
+            out.push(')');
+        }
+    }
+}
+
+fn pattern(out: &mut String, pattern: &Pattern) {
+    match &pattern.kind {
+        PatternKind::Number(n) => write!(out, "{}", n).unwrap(),
+        PatternKind::String(s) => string(out, s),
+        PatternKind::Char(c) => write!(out, "{:?}", c).unwrap(),
+        PatternKind::Wildcard => out.push('_'),
+        PatternKind::Ident(name) => out.push_str(name),
+        PatternKind::Tuple(items) => {
+            out.push_str("(tuple");
+
+            for item in items {
+                out.push(' ');
+                self::pattern(out, item);
+            }
+
+            out.push(')');
+        }
+        PatternKind::Variant { name, variant } => {
+            write!(out, "{}::{}", name.name, variant.name).unwrap()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_dump_values() {
+        let json = Lexer::new("0x1F 2.5 'c' \"a\\tb\" x").lexeme_json();
+
+        assert!(json.contains(r#"{"kind":"Number","text":"0x1F","start":0,"end":4,"value":31}"#));
+        assert!(json.contains(r#""text":"2.5","start":5,"end":8,"value":2.5}"#));
+        assert!(json.contains(r#""text":"'c'","start":9,"end":12,"value":"c"}"#));
+        assert!(json.contains(r#""text":"\"a\\tb\"","start":13,"end":19,"value":"a\tb"}"#));
+    }
+
+    #[test]
+    pub fn test_dump_errors() {
+        let json = Lexer::new("a\u{7}").lexeme_json();
+
+        assert!(json.ends_with(
+            r#""errors":[{"message":"unexpected character `\u0007`","start":1,"end":2}]}"#
+        ));
+    }
+
+    #[test]
+    pub fn test_dump_is_json() {
+        let json = Lexer::new("new x { ::stdout write \"hi\\n\" } `").lexeme_json();
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed["version"], 1);
+        assert_eq!(parsed["tokens"][3]["kind"], "MemberAccess");
+        assert_eq!(parsed["tokens"][6]["value"], "hi\n");
+        assert_eq!(parsed["errors"][0]["start"], 32);
+    }
+
+    #[test]
+    pub fn test_dump_sexp() {
+        let source = "include \"core\"\nuse core::str\nnew struct P { x, y }\nnew enum S { A }\nfn f(a, b) {\n  if a { return } else if b { break } else { continue }\n  while a { a -= 1 }\n  return match a { (x, 1) => x, S::A => { }, _ => 'c' }\n}\nnew hello_world {\n  conditions { any expect ident \"print\" %override }\n  logic {\n    collect as $args\n    for 1..=3 as i { ::stdout write $::1 -i }\n    let p = #{ \"k\": [1, 2.5] }\n    ::stdout write \"hi {p}!\"\n    p.x = c ? g(1)(2)[0] : fn (x) { x }\n  }\n}";
+        let (program, diagnostics) = crate::newton_parse::parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let sexp = program_sexp(&program);
+        let lines: Vec<&str> = sexp.lines().collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "(include \"core\")",
+                "(use core str)",
+                "(struct P x y)",
+                "(enum S A)",
+                "(fn f (a b) (if a (then (return)) (else (if b (then break) (else continue)))) (while a (-= a 1)) (return (match a ((tuple x 1) x) (S::A) (_ 'c'))))",
+                "(new hello_world (conditions any (expect ident \"print\") %override) (logic (collect $args) (for (..= 1 3) i (::stdout write (- (:: $ 1) i))) (let p (map (\"k\" (list 1 2.5)))) (::stdout write (str \"hi \" p \"!\")) (= (. p x) (? c (index (call (call g 1) 2) 0) (fn (x) x)))))",
+            ]
+        );
+    }
+}
@@ -0,0 +1,260 @@
+//! # Newton Highlighting
+//!
+//! Coarse classes for syntax highlighting, worked out from the tokens alone, so an editor or
+//! an HTML page can color a `.newton` file without parsing it.
+//!
+//! ```
+//! # use newton::newton_highlight::{highlight, Class};
+//! # use newton::newton_lex::{Lexer, Span};
+//! let tokens = Lexer::new("new x { ::stdout write 1 }").lexeme();
+//! let highlights = highlight(&tokens);
+//!
+//! assert_eq!(highlights[0].class, Class::Keyword);
+//! assert_eq!(highlights[1].class, Class::Member); // ::
+//! assert_eq!(highlights[2].class, Class::Member); // stdout
+//! assert_eq!(highlights[3].span, Span::new(23, 24)); // 1
+//! ```
+//!
+//! Lex with `LexerOptions::trivia` on to get comments highlighted too, they're dropped
+//! otherwise.
+
+use std::fmt::Write;
+
+use crate::newton_lex::{Span, Token, Type, CONTEXTUAL_KEYWORDS};
+
+/// # Highlight Classes
+///
+/// What a piece of source is, as far as coloring it goes.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Class {
+    Keyword,  // new, logic, %override, `as` in `collect as $`
+    String,   // "abc", r"abc", 'a'
+    Number,   // 123, 0x1F
+    Comment,  // ; comment, ;;; doc comment
+    Operator, // +, ==, ->, ...
+    Member,   // ::stdout, both the `::` and the name
+}
+
+impl Class {
+    /// a short lowercase name, e.g. for a CSS class
+    pub fn name(&self) -> &'static str {
+        match self {
+            Class::Keyword => "keyword",
+            Class::String => "string",
+            Class::Number => "number",
+            Class::Comment => "comment",
+            Class::Operator => "operator",
+            Class::Member => "member",
+        }
+    }
+}
+
+impl std::fmt::Display for Class {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// # Highlights
+///
+/// A span of source and its class.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Highlight {
+    pub class: Class, // what it is
+    pub span: Span,   // where it is
+}
+
+/// classifies a token stream, in order
+///
+/// Tokens with nothing to color, like names, brackets, and whitespace, are left out. A
+/// contextual keyword is only a `Keyword` next to a sigil, as in `collect as $` and
+/// `for $ as var`, or right after `new`, as in `new struct Point`, and a plain name anywhere
+/// else.
+pub fn highlight(tokens: &[Token]) -> Vec<Highlight> {
+    let mut highlights = Vec::new();
+    let mut last: Option<&Token> = None; // the last token that wasn't trivia
+
+    for (i, token) in tokens.iter().enumerate() {
+        let prev = last;
+        let next = tokens[i + 1..].iter().find(|t| !t.ty.is_trivia());
+
+        if !token.ty.is_trivia() {
+            last = Some(token);
+        }
+
+        let class = match token.ty {
+            Type::ReservedKeyword | Type::Directive => Class::Keyword,
+            Type::String | Type::RawString | Type::InterpolatedString | Type::Char => Class::String,
+            Type::Number => Class::Number,
+            Type::Comment | Type::DocComment => Class::Comment,
+            Type::MemberAccess => Class::Member,
+
+            Type::Ident if prev.is_some_and(|t| t.ty == Type::MemberAccess) => Class::Member,
+            Type::Ident
+                if CONTEXTUAL_KEYWORDS.contains(&token.body)
+                    && [prev, next]
+                        .iter()
+                        .any(|t| t.is_some_and(|t| t.ty == Type::Sigil)) =>
+            {
+                Class::Keyword
+            }
+            Type::Ident
+                if CONTEXTUAL_KEYWORDS.contains(&token.body)
+                    && prev.is_some_and(|t| t.is_keyword("new")) =>
+            {
+                Class::Keyword
+            }
+
+            Type::Equal
+            | Type::Greater
+            | Type::Less
+            | Type::Plus
+            | Type::Minus
+            | Type::Multiply
+            | Type::Divide
+            | Type::Modulo
+            | Type::EqualEqual
+            | Type::NotEqual
+            | Type::GreaterEqual
+            | Type::LessEqual
+            | Type::PlusEqual
+            | Type::MinusEqual
+            | Type::MultiplyEqual
+            | Type::DivideEqual
+            | Type::ModuloEqual
+            | Type::And
+            | Type::Or
+            | Type::Arrow
+            | Type::FatArrow
+            | Type::DotDot
+            | Type::DotDotEqual
+            | Type::Question => Class::Operator,
+
+            _ => continue,
+        };
+
+        highlights.push(Highlight {
+            class,
+            span: token.span,
+        });
+    }
+
+    highlights
+}
+
+/// `source` as HTML, with each highlight wrapped in a `<span class="...">`
+///
+/// Highlights have to be in order and not overlap, like the ones from `highlight`.
+///
+/// ```
+/// # use newton::newton_highlight::{highlight, html};
+/// # use newton::newton_lex::Lexer;
+/// let source = "x = 1 < 2";
+/// let tokens = Lexer::new(source).lexeme();
+///
+/// assert_eq!(
+///     html(source, &highlight(&tokens)),
+///     r#"x <span class="operator">=</span> <span class="number">1</span> <span class="operator">&lt;</span> <span class="number">2</span>"#
+/// );
+/// ```
+pub fn html(source: &str, highlights: &[Highlight]) -> String {
+    let mut out = String::new();
+    let mut at = 0;
+
+    for highlight in highlights {
+        let span = highlight.span;
+
+        if span.start < at {
+            continue; // overlaps the one before
+        }
+
+        escape(&mut out, Span::new(at, span.start).slice_clamped(source));
+        write!(out, "<span class=\"{}\">", highlight.class).unwrap();
+        escape(&mut out, span.slice_clamped(source));
+        out.push_str("</span>");
+
+        at = span.end;
+    }
+
+    escape(&mut out, source.get(at..).unwrap_or(""));
+    out
+}
+
+/// HTML-escaped text
+fn escape(out: &mut String, text: &str) {
+    for ch in text.chars() {
+        match ch {
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '&' => out.push_str("&amp;"),
+            '"' => out.push_str("&quot;"),
+            c => out.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_lex::{Lexer, LexerOptions};
+
+    fn classes(source: &str) -> Vec<(Class, &str)> {
+        let tokens = Lexer::new_with(source, LexerOptions::new().trivia(true)).lexeme();
+
+        highlight(&tokens)
+            .into_iter()
+            .map(|h| (h.class, &source[h.span.start..h.span.end]))
+            .collect()
+    }
+
+    #[test]
+    pub fn test_highlight_classes() {
+        assert_eq!(
+            classes("; hi\nlogic { ::stdout write \"a\" 'b' 0x1F -> x }"),
+            vec![
+                (Class::Comment, "; hi"),
+                (Class::Keyword, "logic"),
+                (Class::Member, "::"),
+                (Class::Member, "stdout"),
+                (Class::String, "\"a\""),
+                (Class::String, "'b'"),
+                (Class::Number, "0x1F"),
+                (Class::Operator, "->"),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_highlight_contextual_keywords() {
+        assert_eq!(
+            classes(
+                "collect as $\nfor $ as var { }\nas = 1\nnew struct P { struct }\nnew enum E { }"
+            ),
+            vec![
+                (Class::Keyword, "collect"),
+                (Class::Keyword, "as"),
+                (Class::Keyword, "for"),
+                (Class::Keyword, "as"),
+                (Class::Operator, "="), // the last `as` is just a name
+                (Class::Number, "1"),
+                (Class::Keyword, "new"),
+                (Class::Keyword, "struct"), // and so is the last `struct`
+                (Class::Keyword, "new"),
+                (Class::Keyword, "enum"),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_highlight_html_escapes() {
+        let source = "\"<b>\" && x";
+        let tokens = Lexer::new(source).lexeme();
+
+        assert_eq!(
+            html(source, &highlight(&tokens)),
+            "<span class=\"string\">&quot;&lt;b&gt;&quot;</span> <span class=\"operator\">&amp;&amp;</span> x"
+        );
+    }
+}
@@ -0,0 +1,113 @@
+//! # Newton Interner
+//!
+//! Names like `var`, `stdout`, and `write_newline` come up over and over in a `.newton`
+//! file. The interner keeps one copy of each, and hands out a `Symbol` for it, so later
+//! stages compare names as integers.
+//!
+//! ```
+//! # use newton::newton_intern::Interner;
+//! let mut interner = Interner::new();
+//!
+//! let a = interner.intern("stdout");
+//! let b = interner.intern("stdout");
+//!
+//! assert_eq!(a, b);
+//! assert_eq!(interner.resolve(a), "stdout");
+//! ```
+
+use std::collections::HashMap;
+
+/// # Symbol
+///
+/// An interned name. Only meaningful together with the `Interner` that made it.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Symbol(u32);
+
+impl Symbol {
+    /// the symbol's index in its interner, they count up from 0
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// # Interner
+///
+/// Maps names to `Symbol`s and back. Each distinct name is stored once, however often it
+/// appears.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Interner {
+    symbols: HashMap<Box<str>, Symbol>, // name to symbol
+    names: Vec<Box<str>>,               // symbol to name
+}
+
+impl Interner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// the symbol for `name`, making one if it's new
+    pub fn intern(&mut self, name: &str) -> Symbol {
+        if let Some(symbol) = self.symbols.get(name) {
+            return *symbol;
+        }
+
+        let symbol = Symbol(self.names.len() as u32);
+
+        self.names.push(name.into());
+        self.symbols.insert(name.into(), symbol);
+
+        symbol
+    }
+
+    /// the symbol for `name`, if it was interned
+    pub fn get(&self, name: &str) -> Option<Symbol> {
+        self.symbols.get(name).copied()
+    }
+
+    /// the name behind a symbol
+    ///
+    /// Panics if the symbol came from another interner.
+    pub fn resolve(&self, symbol: Symbol) -> &str {
+        &self.names[symbol.index()]
+    }
+
+    /// how many distinct names there are
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    /// if nothing was interned yet
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_intern_once() {
+        let mut interner = Interner::new();
+
+        let var = interner.intern("var");
+        let stdout = interner.intern("stdout");
+
+        assert_ne!(var, stdout);
+        assert_eq!(interner.intern("var"), var);
+        assert_eq!(interner.len(), 2);
+        assert_eq!(var.index(), 0);
+        assert_eq!(stdout.index(), 1);
+    }
+
+    #[test]
+    pub fn test_intern_resolve() {
+        let mut interner = Interner::new();
+        let symbol = interner.intern("write_newline");
+
+        assert_eq!(interner.resolve(symbol), "write_newline");
+        assert_eq!(interner.get("write_newline"), Some(symbol));
+        assert_eq!(interner.get("missing"), None);
+    }
+}
@@ -22,18 +22,24 @@
 //! ```
 //!
 
+use crate::newton_diag::{Diagnostic, DiagnosticSink};
+use crate::newton_intern::{Interner, Symbol};
+use crate::newton_source::{FileId, SourceFile, SourceMap};
+
 /// # Span
 ///
 /// A span of code. These are attached to tokens for error reporting
 ///
 /// ```
-/// let span = Span::new(5, 10);
+/// # use newton::newton_lex::Span;
+/// let span = Span::new(6, 11);
 /// let str = "hello world";
 ///
-/// assert_eq!(span.slice_and_dice(&str), "world");
+/// assert_eq!(span.slice_and_dice(str), Some("world"));
 /// ```
 ///
-#[derive(Debug, PartialEq)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Span {
     pub start: usize,
     pub end: usize,
@@ -50,39 +56,68 @@ impl Span {
         self.end - self.start
     }
 
-    /// check if the span is empty, i.e. if the length is 0
-    /// 
-    /// this can ALSO mean that the span is a single character,
-    /// so `is_empty` is a bit of a misnomer
+    /// check if the span is empty, i.e. if `start == end`
+    ///
+    /// An empty span covers no bytes. It's a position between two characters, like where
+    /// something missing should go, or the `Eof` token at the end of the input.
     pub fn is_empty(&self) -> bool {
         self.len() == 0
     }
 
-    /// Peeks into a string and returns a separate string,
-    /// with the content of the span
+    /// Peeks into a string and borrows the content of the span,
+    /// or `None` if the span doesn't fit in the string
     ///
     /// ```
-    /// let span = Span::new(5, 10);
+    /// # use newton::newton_lex::Span;
+    /// let span = Span::new(6, 11);
     /// let str = "hello world";
-    /// let slice = span.slice_and_dice(&str);
     ///
-    /// assert_eq!(slice, "world");
+    /// assert_eq!(span.slice_and_dice(str), Some("world"));
+    /// assert_eq!(Span::new(6, 20).slice_and_dice(str), None);
+    /// assert_eq!(Span::new(1, 2).slice_and_dice("é"), None); // inside the `é`
+    /// ```
+    pub fn slice_and_dice<'a>(&self, string: &'a str) -> Option<&'a str> {
+        string.get(self.start..self.end)
+    }
+
+    /// like `slice_and_dice`, but never fails
+    ///
+    /// The span is cut down to the string, and widened to whole characters, so a span from an
+    /// older version of the source still shows something sensible.
+    ///
+    /// ```
+    /// # use newton::newton_lex::Span;
+    /// assert_eq!(Span::new(6, 20).slice_clamped("hello world"), "world");
+    /// assert_eq!(Span::new(2, 3).slice_clamped("aé"), "é");
+    /// assert_eq!(Span::new(5, 2).slice_clamped("hello"), "");
     /// ```
-    pub fn slice_and_dice(&self, string: &String) -> String {
-        string[self.start..self.end].to_owned()
+    pub fn slice_clamped<'a>(&self, string: &'a str) -> &'a str {
+        let mut end = self.end.min(string.len());
+        let mut start = self.start.min(end);
+
+        while !string.is_char_boundary(start) {
+            start -= 1;
+        }
+
+        while !string.is_char_boundary(end) {
+            end += 1;
+        }
+
+        &string[start..end]
     }
 
     /// check if the span is erroneous
     ///
     /// ```
-    /// let span = Span::new(5, 10);
+    /// # use newton::newton_lex::Span;
+    /// let mut span = Span::new(5, 10);
     ///
-    /// assert_eq!(span.perfect(), true);
+    /// assert!(span.perfect());
     ///
-    /// span.start = 10;
-    /// span.end = 51;
+    /// span.start = 51;
+    /// span.end = 10;
     ///
-    /// assert_eq!(span.perfect(), false);
+    /// assert!(!span.perfect());
     /// ```
     pub fn perfect(&self) -> bool {
         self.start <= self.end
@@ -99,6 +134,55 @@ impl Span {
     pub fn forward(&self) -> bool {
         self.start < self.end
     }
+
+    /// the smallest span covering both, e.g. a node's span from its first and last token
+    ///
+    /// ```
+    /// # use newton::newton_lex::Span;
+    /// assert_eq!(Span::new(4, 7).join(Span::new(10, 12)), Span::new(4, 12));
+    /// ```
+    pub fn join(&self, other: Span) -> Span {
+        Span::new(self.start.min(other.start), self.end.max(other.end))
+    }
+
+    /// if `offset` falls inside the span, the end being exclusive
+    ///
+    /// ```
+    /// # use newton::newton_lex::Span;
+    /// let span = Span::new(4, 7);
+    ///
+    /// assert!(span.contains(4));
+    /// assert!(!span.contains(7));
+    /// ```
+    pub fn contains(&self, offset: usize) -> bool {
+        self.start <= offset && offset < self.end
+    }
+
+    /// if the two spans share at least one offset
+    ///
+    /// ```
+    /// # use newton::newton_lex::Span;
+    /// assert!(Span::new(0, 5).intersects(Span::new(4, 8)));
+    /// assert!(!Span::new(0, 5).intersects(Span::new(5, 8))); // only touching
+    /// ```
+    pub fn intersects(&self, other: Span) -> bool {
+        self.forward() && other.forward() && self.start < other.end && other.start < self.end
+    }
+
+    /// the span moved by `delta` bytes, e.g. after an edit earlier in the source
+    ///
+    /// Saturates at 0 rather than wrapping.
+    ///
+    /// ```
+    /// # use newton::newton_lex::Span;
+    /// assert_eq!(Span::new(4, 7).shift(3), Span::new(7, 10));
+    /// assert_eq!(Span::new(4, 7).shift(-5), Span::new(0, 2));
+    /// ```
+    pub fn shift(&self, delta: isize) -> Span {
+        let by = |offset: usize| offset.saturating_add_signed(delta);
+
+        Span::new(by(self.start), by(self.end))
+    }
 }
 
 impl std::fmt::Display for Span {
@@ -107,29 +191,95 @@ impl std::fmt::Display for Span {
     }
 }
 
+/// # Keywords
+///
+/// Every word the language reserves for itself. These lex as `ReservedKeyword` instead of
+/// `Ident`.
+pub const KEYWORDS: &[&str] = &[
+    "new",        // new hello_world { ... }
+    "conditions", // conditions { ... }
+    "logic",      // logic { ... }
+    "collect",    // collect as $
+    "for",        // for $ as var { ... }
+    "any",        // matches any statement
+    "expect",     // expect ident 'print'
+    "include",    // include! "core/internal"
+    "let",        // let x = 5
+    "if",         // if x > 1 { ... }
+    "else",       // else { ... }
+    "while",      // while x > 1 { ... }
+    "match",      // match x { 1 => ..., _ => ... }
+    "fn",         // fn greet(name) { ... }
+    "return",     // return x
+    "break",      // break
+    "continue",   // continue
+    "use",        // use core::str
+];
+
+/// # Contextual Keywords
+///
+/// Words that are only keywords in some places, like `as` in `collect as $`. They lex as a
+/// plain `Ident`, so programs that already use them as names keep working, and the parser
+/// picks them out where they fit with `Token::is_contextual`.
+pub const CONTEXTUAL_KEYWORDS: &[&str] = &[
+    "as",     // collect as $, for $ as var
+    "struct", // new struct Point { x, y }
+    "enum",   // new enum State { Idle, Running }
+];
+
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Type {
-    Ident,           // abc
-    ReservedKeyword, // new, conditions, logic
-    String,          // "abc"
-    Number,          // 123
-    OpenParen,       // '('
-    CloseParen,      // ')'
-    OpenBrace,       // '{'
-    CloseBrace,      // '}'
-    MemberAccess,    // '::'
-    Colon,           // ':'
-    SemiColon,       // ';'
-    Comma,           // ','
-    Dot,             // '.'
-    Equal,           // '='
-    Greater,         // '>'
-    Less,            // '<'
-    Plus,            // '+'
-    Minus,           // '-'
-    Multiply,        // '*'
-    Divide,          // '/'
-    Modulo,          // '%'
+    Ident,              // abc, and contextual keywords, see `CONTEXTUAL_KEYWORDS`
+    ReservedKeyword,    // new, conditions, logic, see `KEYWORDS`
+    String,             // "abc"
+    Number,             // 123
+    OpenParen,          // '('
+    CloseParen,         // ')'
+    OpenBrace,          // '{'
+    OpenMap,            // '#{', a map literal
+    CloseBrace,         // '}'
+    OpenBracket,        // '['
+    CloseBracket,       // ']'
+    MemberAccess,       // '::'
+    Colon,              // ':'
+    SemiColon,          // ';'
+    Comma,              // ','
+    Question,           // '?', in `cond ? a : b`
+    Dot,                // '.'
+    DotDot,             // '..', a range
+    DotDotEqual,        // '..=', a range with its end
+    Equal,              // '='
+    Greater,            // '>'
+    Less,               // '<'
+    Plus,               // '+'
+    Minus,              // '-', binary or unary, see `Token::is_unary`
+    Multiply,           // '*'
+    Divide,             // '/'
+    Modulo,             // '%'
+    EqualEqual,         // '=='
+    NotEqual,           // '!='
+    GreaterEqual,       // '>='
+    LessEqual,          // '<='
+    PlusEqual,          // '+='
+    MinusEqual,         // '-='
+    MultiplyEqual,      // '*='
+    DivideEqual,        // '/='
+    ModuloEqual,        // '%='
+    And,                // '&&'
+    Or,                 // '||'
+    Arrow,              // '->'
+    FatArrow,           // '=>'
+    Directive,          // %override
+    Sigil,              // $, $name
+    DocComment,         // ;;; documentation
+    RawString,          // r"C:\no\escapes"
+    InterpolatedString, // "hello {name}"
+    Char,               // 'a'
+    Eof,                // the end of the input
+    Error,              // something unexpected, only in recovery mode
+    Whitespace,         // spaces and line breaks, only in trivia mode
+    Comment,            // ; comment, ;* block *;, only in trivia mode
 }
 
 /// # Token
@@ -160,11 +310,68 @@ pub enum Type {
 /// write_newline :    Ident
 /// var           :    Ident
 /// ```
+///
+/// Tokens borrow their body straight from the source, so lexing never allocates per token.
+/// Literals also carry their `value`, so later stages don't have to parse the body again.
+///
+/// With the `serde` feature tokens can be serialized, and deserialized again by borrowing
+/// their bodies from the serialized text, so a body that had to be escaped (a `\` or `"`
+/// in it) can't be read back from JSON.
 #[derive(Debug, PartialEq /* Clone */)]
-pub struct Token {
-    pub ty: Type,     // the token type
-    pub body: String, // the embodiment of the token
-    pub span: Span,   // the span of the token
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Token<'src> {
+    pub ty: Type,                  // the token type
+    pub body: &'src str,           // the embodiment of the token
+    pub span: Span,                // the span of the token
+    pub value: Option<TokenValue>, // the value of a literal
+}
+
+/// # Token Values
+///
+/// What a literal evaluates to. Strings have their escapes resolved, and numbers are read in
+/// their radix.
+///
+/// | token               | value             |
+/// |---------------------|-------------------|
+/// | `"a\tb"`            | `Str("a<tab>b")`  |
+/// | `"""a"""`           | `Str("a")`        |
+/// | `r"a\tb"`           | `Str("a\\tb")`    |
+/// | `'\n'`              | `Char('\n')`      |
+/// | `2.5e3`             | `Number(2500.0)`  |
+/// | `0xF`               | `Number(15.0)`    |
+///
+/// Interpolated strings have no value, their segments are only known when they run.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum TokenValue {
+    Number(f64),
+    Str(String),
+    Char(char),
+    Symbol(Symbol), // an identifier, when the lexer has an interner
+}
+
+impl Type {
+    /// if the token is only there in trivia mode, whitespace or a comment
+    pub fn is_trivia(&self) -> bool {
+        matches!(self, Type::Whitespace | Type::Comment)
+    }
+
+    /// if a token of this type can be the last token of an operand, e.g. `x`, `1`, or `)`, so
+    /// a `-` right after it is a subtraction
+    pub fn ends_operand(&self) -> bool {
+        matches!(
+            self,
+            Type::Ident
+                | Type::String
+                | Type::RawString
+                | Type::InterpolatedString
+                | Type::Number
+                | Type::Char
+                | Type::Sigil
+                | Type::CloseParen
+                | Type::CloseBracket
+        )
+    }
 }
 
 impl std::fmt::Display for Type {
@@ -176,11 +383,17 @@ impl std::fmt::Display for Type {
             Type::OpenParen => write!(f, "OpenParen"),
             Type::CloseParen => write!(f, "CloseParen"),
             Type::OpenBrace => write!(f, "OpenBrace"),
+            Type::OpenMap => write!(f, "OpenMap"),
             Type::CloseBrace => write!(f, "CloseBrace"),
+            Type::OpenBracket => write!(f, "OpenBracket"),
+            Type::CloseBracket => write!(f, "CloseBracket"),
             Type::Colon => write!(f, "Colon"),
             Type::SemiColon => write!(f, "SemiColon"),
             Type::Comma => write!(f, "Comma"),
+            Type::Question => write!(f, "Question"),
             Type::Dot => write!(f, "Dot"),
+            Type::DotDot => write!(f, "DotDot"),
+            Type::DotDotEqual => write!(f, "DotDotEqual"),
             Type::Equal => write!(f, "Equal"),
             Type::Greater => write!(f, "Greater"),
             Type::Less => write!(f, "Less"),
@@ -189,349 +402,3911 @@ impl std::fmt::Display for Type {
             Type::Multiply => write!(f, "Multiply"),
             Type::Divide => write!(f, "Divide"),
             Type::Modulo => write!(f, "Modulo"),
+            Type::EqualEqual => write!(f, "EqualEqual"),
+            Type::NotEqual => write!(f, "NotEqual"),
+            Type::GreaterEqual => write!(f, "GreaterEqual"),
+            Type::LessEqual => write!(f, "LessEqual"),
+            Type::PlusEqual => write!(f, "PlusEqual"),
+            Type::MinusEqual => write!(f, "MinusEqual"),
+            Type::MultiplyEqual => write!(f, "MultiplyEqual"),
+            Type::DivideEqual => write!(f, "DivideEqual"),
+            Type::ModuloEqual => write!(f, "ModuloEqual"),
+            Type::And => write!(f, "And"),
+            Type::Or => write!(f, "Or"),
+            Type::Arrow => write!(f, "Arrow"),
+            Type::FatArrow => write!(f, "FatArrow"),
+            Type::Directive => write!(f, "Directive"),
+            Type::Sigil => write!(f, "Sigil"),
+            Type::DocComment => write!(f, "DocComment"),
+            Type::RawString => write!(f, "RawString"),
+            Type::InterpolatedString => write!(f, "InterpolatedString"),
+            Type::Char => write!(f, "Char"),
+            Type::Eof => write!(f, "Eof"),
+            Type::Error => write!(f, "Error"),
+            Type::Whitespace => write!(f, "Whitespace"),
+            Type::Comment => write!(f, "Comment"),
             Type::MemberAccess => write!(f, "MemberAccess"),
             Type::ReservedKeyword => write!(f, "ReservedKeyword"),
         }
     }
 }
 
-impl std::fmt::Display for Token {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "Token: ({}, {})", self.ty, self.body)
-    }
+/// # Segments
+///
+/// A piece of an interpolated string. `"hello {name}!"` is made of `Text("hello ")`,
+/// `Expr("name")`, and `Text("!")`. Both borrow from the source, and text is left exactly as
+/// written, escapes and all.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Segment<'src> {
+    Text(&'src str, Span), // plain text
+    Expr(&'src str, Span), // the source between `{` and `}`
 }
 
-/// # Lexer
+/// # Radix
 ///
-/// This handles the large bit of the compiling process.
-#[derive(Debug, PartialEq, Clone)]
-pub struct Lexer {
-    pub buffer: String, // the source code
-    pub pos: isize,     // the current position in the source code
+/// The base a number literal was written in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Radix {
+    Binary,      // 0b1010
+    Octal,       // 0o17
+    Decimal,     // 123
+    Hexadecimal, // 0x1F
 }
 
-impl Lexer {
-    pub fn new(buffer: String) -> Self {
-        Self { buffer, pos: -1 }
+impl Radix {
+    /// the numeric base, e.g. 16 for hexadecimal
+    pub fn base(&self) -> u32 {
+        match self {
+            Radix::Binary => 2,
+            Radix::Octal => 8,
+            Radix::Decimal => 10,
+            Radix::Hexadecimal => 16,
+        }
     }
 
-    pub fn cur(&self) -> Option<char> {
-        self.buffer.chars().nth(self.pos as usize)
+    /// the prefix a literal in this radix starts with
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            Radix::Binary => "0b",
+            Radix::Octal => "0o",
+            Radix::Decimal => "",
+            Radix::Hexadecimal => "0x",
+        }
     }
+}
 
-    pub fn next(&mut self) -> Option<char> {
-        self.pos += 1;
-        self.cur()
+impl std::fmt::Display for Radix {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Radix::Binary => write!(f, "binary"),
+            Radix::Octal => write!(f, "octal"),
+            Radix::Decimal => write!(f, "decimal"),
+            Radix::Hexadecimal => write!(f, "hexadecimal"),
+        }
     }
+}
 
-    pub fn peek(&self) -> Option<char> {
-        self.buffer.chars().nth((self.pos + 1) as usize)
-    }
+impl<'src> Token<'src> {
+    /// the radix of a `Number` token, read from its prefix
+    ///
+    /// The digits after the prefix are in that base, so `0x1F` is `1F` in hexadecimal.
+    pub fn radix(&self) -> Option<Radix> {
+        if self.ty != Type::Number {
+            return None;
+        }
 
-    pub fn advance(&mut self) {
-        self.pos += 1;
+        [Radix::Binary, Radix::Octal, Radix::Hexadecimal]
+            .into_iter()
+            .find(|radix| self.body.starts_with(radix.prefix()))
+            .or(Some(Radix::Decimal))
     }
 
-    /// turns the lexer's input stream into a list of tokens
-    /// 
-    /// Each token contains location information, specially for the parser to be able to
-    /// find and report errors in the source code.
-    /// 
-    /// Still unfinished, as there are plans to include diagnostics in the error reporting,
-    /// instead of panicking.
-    pub fn lexeme(&mut self) -> Vec<Option<Token>> {
-        let mut tokens = Vec::new();
-
-        while let Some(ch) = self.next() {
-            if ch.is_whitespace() {
-                continue;
-            }
-
-            match ch {
-                'a'..='z' | 'A'..='Z' | '_' => {
-                    let identifier = self.digest_ident();
-
-                    tokens.push(identifier);
-                }
+    /// splits a string token into its text and `{...}` segments
+    ///
+    /// Only `String` and `InterpolatedString` tokens have segments.
+    pub fn segments(&self) -> Vec<Segment<'src>> {
+        if !matches!(self.ty, Type::String | Type::InterpolatedString) {
+            return Vec::new();
+        }
 
-                '\"' => {
-                    let literal_sub = self.digest_literal();
+        let inner: &'src str = self.body;
 
-                    tokens.push(literal_sub);
-                }
+        // the offset of the body in the source, after one `"`, or three for a text block
+        let quotes = (self.span.len() - inner.len()) / 2;
+        let base = self.span.start + quotes;
 
-                _ if (ch.is_numeric()) => {
-                    let number = self.digest_number();
+        let mut segments = Vec::new();
+        let mut text_start = 0;
+        let mut chars = inner.char_indices();
 
-                    if number.is_none() {
-                        panic!("weird token in number"); /* again, need diagnostics tf is this */
+        while let Some((i, ch)) = chars.next() {
+            match ch {
+                '\\' => {
+                    // \u{...} has its own braces, which aren't an interpolation
+                    if let Some((_, 'u')) = chars.next() {
+                        if inner[i + 2..].starts_with('{') {
+                            chars.by_ref().find(|&(_, c)| c == '}');
+                        }
                     }
-
-                    tokens.push(number);
-                }
-
-                '(' => {
-                    tokens.push(Some(Token {
-                        ty: Type::OpenParen,
-                        body: ch.to_string(),
-                        span: Span::new(self.pos as usize, self.pos as usize),
-                    }));
-                }
-
-                ')' => {
-                    tokens.push(Some(Token {
-                        ty: Type::CloseParen,
-                        body: ch.to_string(),
-                        span: Span::new(self.pos as usize, self.pos as usize),
-                    }));
                 }
 
                 '{' => {
-                    tokens.push(Some(Token {
-                        ty: Type::OpenBrace,
-                        body: ch.to_string(),
-                        span: Span::new(self.pos as usize, self.pos as usize),
-                    }));
-                }
-
-                '}' => {
-                    tokens.push(Some(Token {
-                        ty: Type::CloseBrace,
-                        body: ch.to_string(),
-                        span: Span::new(self.pos as usize, self.pos as usize),
-                    }));
-                }
+                    if text_start < i {
+                        segments.push(Segment::Text(
+                            &inner[text_start..i],
+                            Span::new(base + text_start, base + i),
+                        ));
+                    }
 
-                ':' => {
-                    let is_access = self.digest_access();
-                    let access_id = self.digest_ident();
+                    let close = chars
+                        .by_ref()
+                        .find(|&(_, c)| c == '}')
+                        .map_or(inner.len(), |(j, _)| j);
 
-                    // if we have an access token
-                    // we can now push it to the token array
-                    if is_access.is_none() == false {
-                        tokens.push(is_access);
-                        tokens.push(access_id);
-                    }
-                }
+                    segments.push(Segment::Expr(
+                        &inner[i + 1..close],
+                        Span::new(base + i + 1, base + close),
+                    ));
 
-                ';' => {
-                    self.digest_comment();
+                    text_start = (close + 1).min(inner.len());
                 }
 
-                /* ignore it otherwise */
-                _ => {
-                    panic!("weird token");
-                }
+                _ => {}
             }
         }
 
-        tokens
+        if text_start < inner.len() {
+            segments.push(Segment::Text(
+                &inner[text_start..],
+                Span::new(base + text_start, base + inner.len()),
+            ));
+        }
+
+        segments
     }
+}
 
-    pub fn digest_comment(&mut self) {
-        while let Some(ch) = self.cur() {
-            if ch == '\n' {
-                break;
-            }
+impl Token<'_> {
+    /// a one-line description for debugging, with the token's position and the source it
+    /// came from, e.g. `2:3     Ident             "stdout"`
+    ///
+    /// ```
+    /// # use newton::newton_lex::Lexer;
+    /// # use newton::newton_source::SourceMap;
+    /// let mut map = SourceMap::new();
+    /// let file = map.add("main.newton", "new x");
+    /// let source = &map.file(file).text;
+    ///
+    /// let tokens = Lexer::new(source).lexeme();
+    ///
+    /// assert_eq!(tokens[1].render(map.file(file)), r#"1:5     Ident             "x""#);
+    /// ```
+    pub fn render(&self, file: &SourceFile) -> String {
+        let span = self.span;
+        let position = match file.line_col(span.start) {
+            Some(position) => position.to_string(),
+            None => "?:?".to_owned(),
+        };
 
-            self.advance();
-        }
+        format!(
+            "{:<7} {:<17} {:?}",
+            position,
+            self.ty.to_string(),
+            span.slice_clamped(&file.text)
+        )
     }
 
-    pub fn digest_ident(&mut self) -> Option<Token> {
-        let mut ident = String::new();
-        let start = self.pos;
-
-        while let Some(ch) = self.cur() {
-            if ch.is_alphanumeric() || ch == '_' {
-                ident.push(ch);
-            } else {
-                break;
-            }
+    /// # Unary Minus
+    ///
+    /// The lexer never folds a `-` into a number, `a -1` is `a`, `-`, `1` just like `a - 1`.
+    /// Telling the two apart is the parser's job, by this rule: a `Minus` is unary when it
+    /// starts the input or follows something that [can't end an operand](Type::ends_operand).
+    /// A unary `-` that [touches](Token::touches) a `Number` makes a negative literal.
+    ///
+    /// ```
+    /// # use newton::newton_lex::Lexer;
+    /// let tokens = Lexer::new("x = a - 1 - -2").lexeme();
+    ///
+    /// assert!(!tokens[3].is_unary(Some(&tokens[2]))); // a - 1
+    /// assert!(!tokens[5].is_unary(Some(&tokens[4]))); // 1 - ...
+    /// assert!(tokens[6].is_unary(Some(&tokens[5]))); // ... -2
+    /// assert!(tokens[6].touches(&tokens[7]));
+    /// ```
+    pub fn is_unary(&self, prev: Option<&Token>) -> bool {
+        self.ty == Type::Minus && !prev.is_some_and(|prev| prev.ty.ends_operand())
+    }
 
-            self.advance(); // advances without returning
-        }
+    /// if `next` starts right where this token ends, with nothing between them
+    pub fn touches(&self, next: &Token) -> bool {
+        self.span.end == next.span.start
+    }
 
-        Some(Token {
-            // see if it's a reserved keyword
-            ty: match ident.as_str() {
-                "new" => Type::ReservedKeyword,
-                "conditions" => Type::ReservedKeyword,
-                "logic" => Type::ReservedKeyword,
-                _ => Type::Ident,
-            },
-            body: ident,
-            span: Span::new(start as usize, self.pos as usize),
-        })
+    /// if this is the contextual keyword `word`, meaning an `Ident` spelled like it
+    ///
+    /// ```
+    /// # use newton::newton_lex::Lexer;
+    /// let tokens = Lexer::new("collect as $\nas = 1").lexeme();
+    ///
+    /// assert!(tokens[1].is_contextual("as")); // collect as $
+    /// assert!(tokens[3].is_contextual("as")); // just a name here, the parser decides
+    /// assert!(!tokens[0].is_contextual("collect")); // always reserved
+    /// ```
+    pub fn is_contextual(&self, word: &str) -> bool {
+        self.ty == Type::Ident && self.body == word
     }
 
-    /// Digests "abc"
-    /// Tries to find the end quote,
-    pub fn digest_literal(&mut self) -> Option<Token> {
-        let mut literal = String::new();
-        let start = self.pos;
+    /// if the token is the reserved keyword `word`
+    pub fn is_keyword(&self, word: &str) -> bool {
+        self.ty == Type::ReservedKeyword && self.body == word
+    }
+}
 
-        let mut escaped = false;
+impl std::fmt::Display for Token<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Token: ({}, {})", self.ty, self.body)
+    }
+}
 
-        literal.push('\"');
+/// # Lex Errors
+///
+/// Something the lexer couldn't make sense of, and where. Errors are collected in
+/// `Lexer::errors` while lexing carries on.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LexError {
+    pub kind: LexErrorKind, // what went wrong
+    pub span: Span,         // where it went wrong
+}
 
-        // this revising is the result
-        // of some very overestimated effort.
-        //
-        // from author ~ fixed now :)
-        while let Some(ch) = self.next() {
-            if ch == '\"' && escaped == false {
-                // if char is the end quote
-                self.pos += 1; // move past the end quote
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum LexErrorKind {
+    UnterminatedBlockComment,  // ;* with no *;
+    UnknownEscape(char),       // "\q"
+    InvalidHexEscape,          // "\xZZ", "\xFF"
+    InvalidUnicodeEscape,      // "\u{D800}", "\u1234"
+    UnterminatedString,        // "abc
+    UnterminatedInterpolation, // "hello {name"
+    EmptyChar,                 // ''
+    CharTooLong,               // 'ab'
+    UnterminatedChar,          // 'a
+    InvalidDigit(char, Radix), // 0b102
+    MissingDigits(Radix),      // 0x
+    NumberTooLarge(Radix),     // 0x1_0000_0000_0000_0000, past 64 bits
+    MissingExponent,           // 1e
+    MisplacedDecimalPoint,     // 1..2, 1.2.3
+    MisplacedUnderscore,       // 1_, 1__0
+    UnexpectedChar(char),      // a character that doesn't start any token
+    TokenTooLong(usize),       // longer than `LexerOptions::max_token_len`
+}
 
-                literal.push('\"');
+impl LexErrorKind {
+    /// the stable error code, see `newton_codes`
+    pub fn code(&self) -> &'static str {
+        match self {
+            LexErrorKind::UnterminatedString => "N0001",
+            LexErrorKind::InvalidDigit(..)
+            | LexErrorKind::MissingDigits(_)
+            | LexErrorKind::NumberTooLarge(_)
+            | LexErrorKind::MissingExponent
+            | LexErrorKind::MisplacedDecimalPoint
+            | LexErrorKind::MisplacedUnderscore => "N0002",
+            LexErrorKind::UnexpectedChar(_) => "N0003",
+            LexErrorKind::UnterminatedBlockComment => "N0004",
+            LexErrorKind::UnknownEscape(_)
+            | LexErrorKind::InvalidHexEscape
+            | LexErrorKind::InvalidUnicodeEscape => "N0005",
+            LexErrorKind::UnterminatedInterpolation => "N0006",
+            LexErrorKind::EmptyChar
+            | LexErrorKind::CharTooLong
+            | LexErrorKind::UnterminatedChar => "N0007",
+            LexErrorKind::TokenTooLong(_) => "N0008",
+        }
+    }
+}
 
-                return Some(Token {
-                    ty: Type::String,
-                    body: literal,
-                    span: Span::new(start as usize, self.pos as usize),
-                });
-            } else if ch == '\\' && escaped == false {
-                escaped = true;
-            } else {
-                /* todo: probably add more escape sequencies. this is a toy language so i'm not too stressed about them lol */
-                match escaped {
-                    true => {
-                        match ch {
-                            'n' => {
-                                literal.push('\n');
-                            }
-                            _ => {
-                                literal.push(ch);
-                            }
-                        }
-                        escaped = false;
-                    }
-                    false => {
-                        literal.push(ch);
-                    }
-                }
+impl std::fmt::Display for LexErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LexErrorKind::UnterminatedBlockComment => write!(f, "unterminated block comment"),
+            LexErrorKind::UnknownEscape(ch) => write!(f, "unknown escape sequence `\\{}`", ch),
+            LexErrorKind::UnexpectedChar(ch) => write!(f, "unexpected character `{}`", ch),
+            LexErrorKind::TokenTooLong(max) => write!(f, "token is longer than {} bytes", max),
+            LexErrorKind::UnterminatedString => write!(f, "unterminated string"),
+            LexErrorKind::EmptyChar => write!(f, "empty character literal"),
+            LexErrorKind::CharTooLong => {
+                write!(f, "character literal may only contain one character")
+            }
+            LexErrorKind::UnterminatedChar => write!(f, "unterminated character literal"),
+            LexErrorKind::InvalidDigit(ch, radix) => {
+                write!(f, "invalid digit `{}` in {} number", ch, radix)
+            }
+            LexErrorKind::MissingExponent => write!(f, "expected digits in the exponent"),
+            LexErrorKind::MisplacedDecimalPoint => {
+                write!(f, "a number has one decimal point, between two digits")
+            }
+            LexErrorKind::MisplacedUnderscore => {
+                write!(f, "`_` separators only go between two digits")
+            }
+            LexErrorKind::MissingDigits(radix) => {
+                write!(f, "expected digits after `{}`", radix.prefix())
+            }
+            LexErrorKind::NumberTooLarge(radix) => {
+                write!(f, "{} number doesn't fit in 64 bits", radix)
+            }
+            LexErrorKind::UnterminatedInterpolation => {
+                write!(f, "unterminated interpolation, expected a `}}`")
+            }
+            LexErrorKind::InvalidHexEscape => {
+                write!(f, "invalid hex escape, expected `\\x00` through `\\x7F`")
+            }
+            LexErrorKind::InvalidUnicodeEscape => {
+                write!(
+                    f,
+                    "invalid unicode escape, expected `\\u{{...}}` with 1-6 hex digits"
+                )
             }
         }
-
-        panic!("string was never found. he never found his buddy");
     }
+}
 
-    /// # Numbers
-    ///
-    /// `.newton` has very simple number support.
-    ///
-    /// All numbers are parsed as floats, but can be generally interpreted as an integer.
-    pub fn digest_number(&mut self) -> Option<Token> {
-        let mut number = String::new();
-        let start = self.pos;
+impl std::fmt::Display for LexError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.kind, self.span)
+    }
+}
 
-        while let Some(ch) = self.cur() {
-            match ch {
-                '0'..='9' | '.' | '_' => {
-                    number.push(ch);
-                }
+/// # Lexer Options
+///
+/// Lets a host tune the lexer without touching its source, which is how `.newton` is meant to
+/// be extended.
+///
+/// ```
+/// # use newton::newton_lex::{Lexer, LexerOptions, Type};
+/// let options = LexerOptions::new().keyword("print");
+/// let mut lexer = Lexer::new_with("print", options);
+///
+/// assert_eq!(lexer.lexeme()[0].ty, Type::ReservedKeyword);
+/// ```
+///
+/// Everything is off by default, and the builders chain.
+///
+/// ```
+/// # use newton::newton_lex::{CommentStyle, LexerOptions};
+/// let options = LexerOptions::new()
+///     .comments(CommentStyle::Hash)
+///     .strict(true)
+///     .trivia(true)
+///     .max_token_len(256);
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LexerOptions {
+    pub keywords: Vec<String>, // extra reserved keywords, on top of `KEYWORDS`
+    pub contextual: Vec<String>, // more contextual keywords, see `CONTEXTUAL_KEYWORDS`
+    pub comments: CommentStyle, // what starts a line comment
+    pub recover: bool,         // if unexpected characters become `Error` tokens
+    pub trivia: bool,          // if whitespace and comments become tokens
+    pub strict: bool,          // if sloppy but readable input is reported too
+    pub max_token_len: Option<usize>, // the longest a token may be, in bytes
+}
 
-                _ => {
-                    panic!("weird token in number"); /* __todo__ implement diagnostics */
-                }
-            }
+/// # Comment Style
+///
+/// What starts a line comment. `;` is the default, but hosts that need `;` as a statement
+/// separator can pick something else, in which case `;` lexes as a `SemiColon`.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CommentStyle {
+    #[default]
+    Semicolon, // ; comment
+    Hash,        // # comment
+    DoubleSlash, // // comment
+}
 
-            self.advance(); // advances without returning
+impl CommentStyle {
+    /// the text that starts a comment
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            CommentStyle::Semicolon => ";",
+            CommentStyle::Hash => "#",
+            CommentStyle::DoubleSlash => "//",
         }
-
-        Some(Token {
-            ty: Type::Number,
-            body: number,
-            span: Span::new(start as usize, self.pos as usize),
-        })
     }
 
-    pub fn digest_access(&mut self) -> Option<Token> {
-        let start = self.pos;
-
-        let should_be = self.next();
-
-        if should_be == Some(':') {
-            // this is a member access
-            if self.peek().is_some() && self.next().unwrap().is_alphabetic() {
-                return Some(Token {
-                    ty: Type::MemberAccess,
-                    body: String::from("::"),
-                    span: Span::new(start as usize, self.pos as usize),
-                });
-            }
-        } else {
-            panic!("weird token, member access expects a second ':'");
+    /// the text that starts a doc comment, which is kept in the token stream
+    pub fn doc_prefix(&self) -> &'static str {
+        match self {
+            CommentStyle::Semicolon => ";;;",
+            CommentStyle::Hash => "###",
+            CommentStyle::DoubleSlash => "///",
         }
+    }
 
-        None
+    /// the text that opens and closes a block comment
+    pub fn block(&self) -> (&'static str, &'static str) {
+        match self {
+            CommentStyle::Semicolon => (";*", "*;"),
+            CommentStyle::Hash => ("#*", "*#"),
+            CommentStyle::DoubleSlash => ("/*", "*/"),
+        }
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    /// test the span length
-    #[test]
-    pub fn test_simple_span() {
-        let span = Span::new(5, 10);
-        assert_eq!(span.len(), 5);
+impl LexerOptions {
+    pub fn new() -> Self {
+        Self::default()
     }
 
-    /// test the span peeking feature
-    #[test]
-    pub fn test_span_peek() {
-        let span = Span::new(6, 11);
-        let str = "hello world";
-        let slice = span.slice_and_dice(&str.to_string());
-
-        assert_eq!(slice, "world");
+    /// registers an extra reserved keyword
+    pub fn keyword(mut self, word: impl Into<String>) -> Self {
+        self.keywords.push(word.into());
+        self
     }
 
-    #[test]
-    pub fn test_span_perfect() {
-        let mut span = Span::new(5, 10);
-
-        assert_eq!(span.forward(), true);
+    /// registers an extra contextual keyword, which still lexes as an `Ident`
+    ///
+    /// Unlike `keyword`, this never breaks a program using the word as a name, so it's the
+    /// way to grow the language.
+    pub fn contextual_keyword(mut self, word: impl Into<String>) -> Self {
+        self.contextual.push(word.into());
+        self
+    }
 
-        span.start = 50;
-        span.end = 1; // backward span?
+    /// sets what starts a line comment
+    pub fn comments(mut self, style: CommentStyle) -> Self {
+        self.comments = style;
+        self
+    }
 
-        assert_eq!(span.forward(), false);
+    /// turns on error recovery, where unexpected characters become `Error` tokens instead of
+    /// being dropped, so an editor can show every bad span in place
+    pub fn recover(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// keeps whitespace and comments as `Whitespace` and `Comment` tokens, for tools like a
+    /// formatter that need to give the source back as written
+    ///
+    /// With `recover` on as well, the tokens cover every byte of the source.
+    pub fn trivia(mut self, trivia: bool) -> Self {
+        self.trivia = trivia;
+        self
+    }
+
+    /// turns on strict mode, which also reports input that lexes fine but is easy to misread,
+    /// like `5abc` (a number running straight into a name)
+    pub fn strict(mut self, strict: bool) -> Self {
+        self.strict = strict;
+        self
+    }
+
+    /// reports tokens longer than `max` bytes, e.g. to guard against generated input
+    pub fn max_token_len(mut self, max: usize) -> Self {
+        self.max_token_len = Some(max);
+        self
+    }
+
+    /// if `word` is reserved, either by the language or by the host
+    pub fn is_keyword(&self, word: &str) -> bool {
+        KEYWORDS.contains(&word) || self.keywords.iter().any(|k| k == word)
+    }
+
+    /// if `word` is a contextual keyword, either by the language or by the host
+    pub fn is_contextual_keyword(&self, word: &str) -> bool {
+        CONTEXTUAL_KEYWORDS.contains(&word) || self.contextual.iter().any(|k| k == word)
+    }
+}
+
+/// # Lexer
+///
+/// This handles the large bit of the compiling process.
+///
+/// The lexer walks the buffer with a byte cursor, so every step is O(1) and a whole file is
+/// lexed in linear time. `pos` is the byte offset of the current character. Before the first
+/// character and past the last there is none, and `cur` is `None`.
+#[derive(Debug, PartialEq, Clone)]
+pub struct Lexer<'src> {
+    pub buffer: &'src str,          // the source code
+    pub pos: usize,                 // the current position (byte offset) in the source code
+    current: Option<char>,          // the character at `pos`
+    pub options: LexerOptions,      // host configuration
+    pub errors: Vec<LexError>,      // errors found so far
+    pub line: usize,                // the current line, starting at 1
+    pub finished: bool,             // if the `Eof` token has been handed out
+    pub interner: Option<Interner>, // interns identifiers, when set
+    continued: bool,                // if the buffer picks up partway into a file
+}
+
+impl<'src> Lexer<'src> {
+    pub fn new(buffer: &'src str) -> Self {
+        Self::new_with(buffer, LexerOptions::default())
+    }
+
+    /// creates a lexer with host-provided options
+    pub fn new_with(buffer: &'src str, options: LexerOptions) -> Self {
+        Self {
+            buffer,
+            pos: 0,
+            current: None,
+            options,
+            errors: Vec::new(),
+            line: 1,
+            finished: false,
+            interner: None,
+            continued: false,
+        }
+    }
+
+    /// lexes from byte `offset` of the buffer rather than its start, for lexing part of it
+    /// again, the spans still count from the start of the buffer
+    ///
+    /// ```
+    /// # use newton::newton_lex::{Lexer, Span};
+    /// let tokens = Lexer::new("new x { }").starting_at(4).lexeme();
+    ///
+    /// assert_eq!(tokens[0].body, "x");
+    /// assert_eq!(tokens[0].span, Span::new(4, 5));
+    /// ```
+    ///
+    /// `offset` has to be where a token starts, lexing from inside one lexes what's left of
+    /// it as a token of its own.
+    pub fn starting_at(mut self, offset: usize) -> Self {
+        self.seek(offset);
+        self
+    }
+
+    /// interns identifiers as they're lexed, so they carry a `Symbol` value
+    ///
+    /// Take the interner back out of `interner` afterwards to share it with later stages.
+    ///
+    /// ```
+    /// # use newton::newton_intern::Interner;
+    /// # use newton::newton_lex::{Lexer, TokenValue};
+    /// let mut lexer = Lexer::new("var var").with_interner(Interner::new());
+    /// let tokens = lexer.lexeme();
+    ///
+    /// assert_eq!(tokens[0].value, tokens[1].value);
+    /// assert_eq!(lexer.interner.unwrap().len(), 1);
+    /// ```
+    pub fn with_interner(mut self, interner: Interner) -> Self {
+        self.interner = Some(interner);
+        self
+    }
+
+    pub fn cur(&self) -> Option<char> {
+        self.current
+    }
+
+    #[allow(clippy::should_implement_trait)] // a char cursor, not a token iterator
+    pub fn next(&mut self) -> Option<char> {
+        self.advance();
+        self.cur()
+    }
+
+    pub fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    /// looks `k` characters ahead without moving, `peek_n(1)` is `peek()`
+    ///
+    /// `peek_n(0)` is the current character.
+    pub fn peek_n(&self, k: usize) -> Option<char> {
+        if k == 0 {
+            return self.cur();
+        }
+
+        let mut chars = self.rest().chars();
+
+        for _ in 1..k {
+            chars.next()?;
+        }
+
+        chars.next()
+    }
+
+    /// saves where the lexer is, to come back to with `rollback`
+    ///
+    /// ```
+    /// # use newton::newton_lex::Lexer;
+    /// let mut lexer = Lexer::new("a ` b");
+    /// let saved = lexer.checkpoint();
+    ///
+    /// lexer.next_token();
+    /// lexer.next_token(); // reports the `
+    /// lexer.rollback(saved);
+    ///
+    /// assert!(lexer.errors.is_empty());
+    /// assert_eq!(lexer.next_token().unwrap().body, "a");
+    /// ```
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint {
+            pos: self.pos,
+            current: self.current,
+            line: self.line,
+            errors: self.errors.len(),
+            finished: self.finished,
+        }
+    }
+
+    /// goes back to a `checkpoint`, forgetting any errors found since
+    pub fn rollback(&mut self, checkpoint: Checkpoint) {
+        self.pos = checkpoint.pos;
+        self.current = checkpoint.current;
+        self.line = checkpoint.line;
+        self.errors.truncate(checkpoint.errors);
+        self.finished = checkpoint.finished;
+    }
+
+    /// moves onto the next character, counting lines as it goes
+    ///
+    /// `\n`, `\r\n`, and a lone `\r` all end a line.
+    pub fn advance(&mut self) {
+        match self.cur() {
+            Some('\n') => self.line += 1,
+            Some('\r') if self.peek() != Some('\n') => self.line += 1,
+            _ => {}
+        }
+
+        self.pos = self.next_pos();
+        self.current = self.buffer[self.pos..].chars().next();
+    }
+
+    /// the byte offset of the character after the current one, the end of the buffer once
+    /// there are no more
+    fn next_pos(&self) -> usize {
+        self.pos + self.current.map_or(0, char::len_utf8)
+    }
+
+    /// the source after the current character
+    fn rest(&self) -> &'src str {
+        &self.buffer[self.next_pos()..]
+    }
+
+    /// if the lexer hasn't moved onto the first character of the file yet
+    fn at_start(&self) -> bool {
+        !self.continued && self.pos == 0 && self.current.is_none()
+    }
+
+    /// advances `n` characters
+    fn skip(&mut self, n: usize) {
+        for _ in 0..n {
+            self.advance();
+        }
+    }
+
+    /// if the source continues with `text` from the current character
+    fn at(&self, text: &str) -> bool {
+        self.current.is_some() && self.buffer[self.pos..].starts_with(text)
+    }
+
+    /// borrows `start..end` out of the source
+    fn slice(&self, start: usize, end: usize) -> &'src str {
+        &self.buffer[start..end]
+    }
+
+    /// turns the lexer's input stream into a list of tokens
+    ///
+    /// Each token contains location information, specially for the parser to be able to
+    /// find and report errors in the source code.
+    ///
+    /// The token list always ends with an `Eof` token, a zero-width span at the end of the
+    /// input, so parsers can report "unexpected end of file" with a location.
+    ///
+    /// The lexer never panics, whatever the input. Anything it can't make sense of is
+    /// collected in `errors`, and lexing carries on after it.
+    ///
+    /// A byte order mark at the very start is skipped, but spans still count it, so they
+    /// index the buffer as given. So is a `#!` shebang line, which is kept as a `Comment` in
+    /// trivia mode.
+    pub fn lexeme(&mut self) -> Vec<Token<'src>> {
+        std::iter::from_fn(|| self.next_token()).collect()
+    }
+
+    /// `lexeme`, reporting each error to `sink` as soon as it's found, rather than after the
+    /// whole file
+    ///
+    /// The errors are still collected in `errors` too.
+    ///
+    /// ```
+    /// # use newton::newton_diag::Diagnostic;
+    /// # use newton::newton_lex::Lexer;
+    /// let mut lexer = Lexer::new("a ` b @");
+    /// let mut reported: Vec<Diagnostic> = Vec::new();
+    ///
+    /// lexer.lexeme_into(&mut reported);
+    ///
+    /// assert_eq!(reported.len(), 2);
+    /// assert_eq!(reported[0].to_string(), "error[N0003]: unexpected character ```");
+    /// ```
+    pub fn lexeme_into<S: DiagnosticSink>(&mut self, sink: &mut S) -> Vec<Token<'src>> {
+        let mut tokens = Vec::new();
+        let mut reported = self.errors.len();
+
+        while let Some(token) = self.next_token() {
+            for error in &self.errors[reported..] {
+                sink.report(Diagnostic::from(error));
+            }
+
+            reported = self.errors.len();
+            tokens.push(token);
+        }
+
+        tokens
+    }
+
+    /// hands over the errors found so far, leaving `errors` empty
+    ///
+    /// Tokens and errors come out separately, so callers that only want tokens never have
+    /// to look at the errors, and the other way round.
+    ///
+    /// ```
+    /// # use newton::newton_lex::{Lexer, LexErrorKind};
+    /// let mut lexer = Lexer::new("a ` b");
+    /// let tokens = lexer.lexeme();
+    /// let errors = lexer.take_errors();
+    ///
+    /// assert_eq!(tokens.len(), 3);
+    /// assert_eq!(errors[0].kind, LexErrorKind::UnexpectedChar('`'));
+    /// assert!(lexer.errors.is_empty());
+    /// ```
+    pub fn take_errors(&mut self) -> Vec<LexError> {
+        std::mem::take(&mut self.errors)
+    }
+
+    /// lexes just the next token, `None` once the `Eof` token has been handed out
+    ///
+    /// This is the step `lexeme` repeats, so a parser can pull tokens on demand instead.
+    pub fn next_token(&mut self) -> Option<Token<'src>> {
+        let token = self.lex_token()?;
+
+        if let Some(max) = self.options.max_token_len {
+            if token.span.len() > max {
+                self.error(
+                    LexErrorKind::TokenTooLong(max),
+                    token.span.start,
+                    token.span.end,
+                );
+            }
+        }
+
+        Some(token)
+    }
+
+    /// `next_token` without the checks that apply to every token
+    fn lex_token(&mut self) -> Option<Token<'src>> {
+        if self.finished {
+            return None;
+        }
+
+        if self.at_start() && self.buffer.starts_with('\u{feff}') {
+            self.advance(); // onto the BOM, the loop moves past it
+
+            if self.options.trivia {
+                return Some(self.digest_single(Type::Whitespace));
+            }
+        }
+
+        while let Some(ch) = self.next() {
+            let start = self.pos;
+            let errors = self.errors.len();
+
+            if ch.is_whitespace() {
+                if self.options.trivia {
+                    return Some(self.digest_whitespace());
+                }
+
+                continue;
+            }
+
+            // `#!/usr/bin/env newton` on the first line, so scripts can be run directly
+            if self.at("#!")
+                && !self.continued
+                && self.buffer[..start]
+                    .trim_start_matches('\u{feff}')
+                    .is_empty()
+            {
+                let shebang = self.digest_comment();
+
+                if self.options.trivia {
+                    return Some(shebang);
+                }
+
+                continue;
+            }
+
+            if self.at(self.options.comments.block().0) {
+                let comment = self.digest_block_comment();
+
+                if self.options.trivia {
+                    return Some(comment);
+                }
+
+                continue;
+            }
+
+            if self.at(self.options.comments.doc_prefix()) {
+                return Some(self.digest_doc_comment());
+            }
+
+            if self.at(self.options.comments.prefix()) {
+                let comment = self.digest_comment();
+
+                if self.options.trivia {
+                    return Some(comment);
+                }
+
+                continue;
+            }
+
+            let token = match ch {
+                'r' if self.peek() == Some('\"') => self.digest_raw_literal(),
+                'a'..='z' | 'A'..='Z' | '_' => Some(self.digest_ident()),
+                '$' => Some(self.digest_sigil()),
+                '\'' => self.digest_char(),
+                '\"' if self.at("\"\"\"") => self.digest_text_block(),
+                '\"' => self.digest_literal(),
+                '0'..='9' => Some(self.digest_number()),
+
+                '(' => Some(self.digest_single(Type::OpenParen)),
+                ')' => Some(self.digest_single(Type::CloseParen)),
+                '{' => Some(self.digest_single(Type::OpenBrace)),
+                '#' if self.peek() == Some('{') => Some(self.digest_double(Type::OpenMap)),
+                '}' => Some(self.digest_single(Type::CloseBrace)),
+                '[' => Some(self.digest_single(Type::OpenBracket)),
+                ']' => Some(self.digest_single(Type::CloseBracket)),
+                ',' => Some(self.digest_single(Type::Comma)),
+                '?' => Some(self.digest_single(Type::Question)),
+                '.' if self.at("..=") => Some(self.digest_triple(Type::DotDotEqual)),
+                '.' if self.peek() == Some('.') => Some(self.digest_double(Type::DotDot)),
+                '.' => Some(self.digest_single(Type::Dot)),
+
+                // operators
+                '+' if self.peek() == Some('=') => Some(self.digest_double(Type::PlusEqual)),
+                '-' if self.peek() == Some('=') => Some(self.digest_double(Type::MinusEqual)),
+                '*' if self.peek() == Some('=') => Some(self.digest_double(Type::MultiplyEqual)),
+                '/' if self.peek() == Some('=') => Some(self.digest_double(Type::DivideEqual)),
+                '%' if self.peek() == Some('=') => Some(self.digest_double(Type::ModuloEqual)),
+                '+' => Some(self.digest_single(Type::Plus)),
+                '-' if self.peek() == Some('>') => Some(self.digest_double(Type::Arrow)),
+                '=' if self.peek() == Some('>') => Some(self.digest_double(Type::FatArrow)),
+                '-' => Some(self.digest_single(Type::Minus)),
+                '*' => Some(self.digest_single(Type::Multiply)),
+                '/' => Some(self.digest_single(Type::Divide)),
+                '%' if self.peek().is_some_and(|c| c.is_alphabetic() || c == '_') => {
+                    Some(self.digest_directive())
+                }
+                '%' => Some(self.digest_single(Type::Modulo)),
+                '=' if self.peek() == Some('=') => Some(self.digest_double(Type::EqualEqual)),
+                '!' if self.peek() == Some('=') => Some(self.digest_double(Type::NotEqual)),
+                '>' if self.peek() == Some('=') => Some(self.digest_double(Type::GreaterEqual)),
+                '<' if self.peek() == Some('=') => Some(self.digest_double(Type::LessEqual)),
+                '&' if self.peek() == Some('&') => Some(self.digest_double(Type::And)),
+                '|' if self.peek() == Some('|') => Some(self.digest_double(Type::Or)),
+                '=' => Some(self.digest_single(Type::Equal)),
+                '>' => Some(self.digest_single(Type::Greater)),
+                '<' => Some(self.digest_single(Type::Less)),
+
+                ':' => Some(self.digest_access()),
+
+                ';' => Some(self.digest_single(Type::SemiColon)),
+
+                /* report it otherwise */
+                _ => self.digest_unexpected(ch, self.pos, self.next_pos()),
+            };
+
+            if token.is_some() {
+                return token;
+            }
+
+            // whatever a broken literal swallowed is kept too, so nothing goes missing
+            if self.options.recover && self.errors.len() > errors {
+                let end = self.next_pos().min(self.buffer.len());
+
+                return Some(Token {
+                    ty: Type::Error,
+                    body: self.slice(start, end),
+                    span: Span::new(start, end),
+                    value: None,
+                });
+            }
+        }
+
+        self.finished = true;
+
+        Some(Token {
+            ty: Type::Eof,
+            body: "",
+            span: Span::new(self.buffer.len(), self.buffer.len()),
+            value: None,
+        })
+    }
+
+    /// streams tokens lazily, see [`Tokens`]
+    ///
+    /// ```
+    /// # use newton::newton_lex::{Lexer, Type};
+    /// let mut lexer = Lexer::new("new x \"\\q\"");
+    /// let mut tokens = lexer.tokens();
+    ///
+    /// assert_eq!(tokens.next().unwrap().unwrap().ty, Type::ReservedKeyword);
+    /// assert_eq!(tokens.next().unwrap().unwrap().body, "x");
+    /// assert!(tokens.next().unwrap().is_err()); // the unknown escape
+    /// ```
+    pub fn tokens(&mut self) -> Tokens<'_, 'src> {
+        Tokens {
+            reported: self.errors.len(),
+            lexer: self,
+            pending: None,
+        }
+    }
+
+    /// # Relexing
+    ///
+    /// Patches `tokens`, lexed from the source before an edit, into tokens for this lexer's
+    /// buffer, the source after it. `changed` is the range of the old source that was
+    /// replaced with `new_text`.
+    ///
+    /// Tokens before the edit are kept, but for the last of them, which is lexed again since
+    /// where it ends can turn on what comes after it, like the `1` of `1..` when the edit
+    /// makes it `1.1`. Lexing picks up from there until it lines back up with a token after
+    /// the edit, from where the old tokens are reused. Only errors in the relexed part end
+    /// up in `errors`.
+    ///
+    /// ```
+    /// # use newton::newton_lex::{Lexer, Span};
+    /// let old = "new x { logic }";
+    /// let tokens: Vec<_> = Lexer::new(old).lexeme();
+    ///
+    /// let new = "new wide { logic }";
+    /// let relexed = Lexer::new(new).relex(&tokens, Span::new(4, 5), "wide");
+    ///
+    /// assert_eq!(relexed[1].body, "wide");
+    /// assert_eq!(relexed[2].span, Span::new(9, 10));
+    /// ```
+    pub fn relex(
+        &mut self,
+        tokens: &[Token<'_>],
+        changed: Span,
+        new_text: &str,
+    ) -> Vec<Token<'src>> {
+        let edit_end = changed.start + new_text.len(); // where the edit ends in the new source
+        let before = tokens
+            .iter()
+            .take_while(|t| t.span.end < changed.start)
+            .count();
+        let kept = before.saturating_sub(1); // the one right before the edit is lexed again
+
+        let mut patched: Vec<Token<'src>> = tokens[..kept]
+            .iter()
+            .map(|t| self.rebase(t, t.span.start))
+            .collect();
+        let mut old = tokens[kept..]
+            .iter()
+            .filter(|t| t.span.start >= changed.end)
+            .peekable();
+
+        self.seek(tokens.get(kept).map_or(0, |t| t.span.start));
+
+        while let Some(token) = self.next_token() {
+            if token.span.start >= edit_end {
+                let shifted = |t: &Token| t.span.start - changed.end + edit_end;
+
+                while old.next_if(|t| shifted(t) < token.span.start).is_some() {}
+
+                // back in step with the old tokens, the rest is the same
+                if old.peek().is_some_and(|t| shifted(t) == token.span.start) {
+                    patched.extend(old.map(|t| self.rebase(t, shifted(t))));
+                    break;
+                }
+            }
+
+            patched.push(token);
+        }
+
+        self.pos = self.buffer.len();
+        self.current = None;
+        self.finished = true;
+
+        patched
+    }
+
+    /// an old token moved to `start` in this lexer's buffer
+    fn rebase(&self, token: &Token, start: usize) -> Token<'src> {
+        let quotes = match token.ty {
+            Type::String | Type::InterpolatedString
+                if self.buffer[start..].starts_with("\"\"\"") =>
+            {
+                3
+            }
+            Type::String | Type::InterpolatedString | Type::Char => 1,
+            Type::RawString => 2,
+            _ => 0,
+        };
+        let body = start + quotes;
+
+        Token {
+            ty: token.ty.clone(),
+            body: self.slice(body, body + token.body.len()),
+            span: token.span.shift(start as isize - token.span.start as isize),
+            value: token.value.clone(),
+        }
+    }
+
+    /// moves so the next token is lexed from `offset`, counting lines on the way
+    fn seek(&mut self, offset: usize) {
+        self.pos = 0;
+        self.current = None;
+        self.line = 1;
+        self.finished = false;
+
+        if offset > 0 {
+            while self.next_pos() < offset {
+                self.advance();
+            }
+        }
+    }
+
+    /// a token made of just the current character
+    pub fn digest_single(&self, ty: Type) -> Token<'src> {
+        Token {
+            ty,
+            body: self.slice(self.pos, self.next_pos()),
+            span: Span::new(self.pos, self.next_pos()),
+            value: None,
+        }
+    }
+
+    /// a token made of the current character and the one after it, e.g. `==`
+    pub fn digest_double(&mut self, ty: Type) -> Token<'src> {
+        let start = self.pos;
+
+        self.advance();
+
+        Token {
+            ty,
+            body: self.slice(start, self.next_pos()),
+            span: Span::new(start, self.next_pos()),
+            value: None,
+        }
+    }
+
+    /// a token made of the current character and the two after it, e.g. `..=`
+    pub fn digest_triple(&mut self, ty: Type) -> Token<'src> {
+        let start = self.pos;
+
+        self.advance();
+        self.advance();
+
+        Token {
+            ty,
+            body: self.slice(start, self.next_pos()),
+            span: Span::new(start, self.next_pos()),
+            value: None,
+        }
+    }
+
+    /// reports an unexpected character, in recovery mode it's also kept as an `Error` token
+    fn digest_unexpected(&mut self, ch: char, start: usize, end: usize) -> Option<Token<'src>> {
+        self.error(LexErrorKind::UnexpectedChar(ch), start, end);
+
+        if !self.options.recover {
+            return None;
+        }
+
+        Some(Token {
+            ty: Type::Error,
+            body: self.slice(start, end),
+            span: Span::new(start, end),
+            value: None,
+        })
+    }
+
+    /// a run of whitespace, for trivia mode
+    pub fn digest_whitespace(&mut self) -> Token<'src> {
+        let start = self.pos;
+
+        while self.peek().is_some_and(|c| c.is_whitespace()) {
+            self.advance();
+        }
+
+        let end = self.next_pos();
+
+        Token {
+            ty: Type::Whitespace,
+            body: self.slice(start, end),
+            span: Span::new(start, end),
+            value: None,
+        }
+    }
+
+    /// a line comment, up to but not including the line break
+    pub fn digest_comment(&mut self) -> Token<'src> {
+        let start = self.pos;
+
+        while self.peek().is_some_and(|c| c != '\n' && c != '\r') {
+            self.advance();
+        }
+
+        let end = self.next_pos();
+
+        Token {
+            ty: Type::Comment,
+            body: self.slice(start, end),
+            span: Span::new(start, end),
+            value: None,
+        }
+    }
+
+    /// # Doc Comments
+    ///
+    /// `;;; like this`. Unlike normal comments these become `DocComment` tokens, so the
+    /// documentation can be attached to whatever follows. The body is the whole line, prefix
+    /// included.
+    pub fn digest_doc_comment(&mut self) -> Token<'src> {
+        let start = self.pos;
+
+        while self.peek().is_some_and(|c| c != '\n' && c != '\r') {
+            self.advance();
+        }
+
+        let end = self.next_pos();
+
+        Token {
+            ty: Type::DocComment,
+            body: self.slice(start, end),
+            span: Span::new(start, end),
+            value: None,
+        }
+    }
+
+    /// # Block Comments
+    ///
+    /// `;* ... *;` (or the block form of the configured comment style). Block comments nest,
+    /// so commenting out code that already has one inside works as expected.
+    ///
+    /// An unterminated block comment is reported with the span of its opener.
+    pub fn digest_block_comment(&mut self) -> Token<'src> {
+        let (open, close) = self.options.comments.block();
+        let start = self.pos;
+        let mut depth = 0;
+
+        while self.cur().is_some() {
+            if self.at(open) {
+                depth += 1;
+                self.skip(open.len() - 1);
+            } else if self.at(close) {
+                depth -= 1;
+                self.skip(close.len() - 1);
+
+                if depth == 0 {
+                    break;
+                }
+            }
+
+            self.advance();
+        }
+
+        if depth > 0 {
+            self.error(
+                LexErrorKind::UnterminatedBlockComment,
+                start,
+                start + open.len(),
+            );
+        }
+
+        let end = self.next_pos().min(self.buffer.len());
+
+        Token {
+            ty: Type::Comment,
+            body: self.slice(start, end),
+            span: Span::new(start, end),
+            value: None,
+        }
+    }
+
+    /// Digests an identifier starting at the current character.
+    ///
+    /// Stops on the identifier's last character, so the main loop picks up whatever comes
+    /// right after it (`xs[0]` keeps its `[`).
+    pub fn digest_ident(&mut self) -> Token<'src> {
+        let start = self.pos;
+
+        while let Some(ch) = self.peek() {
+            if !(ch.is_alphanumeric() || ch == '_') {
+                break;
+            }
+
+            self.advance(); // advances without returning
+        }
+
+        let end = self.next_pos();
+        let ident = self.slice(start, end);
+
+        // see if it's a reserved keyword
+        if self.options.is_keyword(ident) {
+            return Token {
+                ty: Type::ReservedKeyword,
+                body: ident,
+                span: Span::new(start, end),
+                value: None,
+            };
+        }
+
+        Token {
+            ty: Type::Ident,
+            body: ident,
+            span: Span::new(start, end),
+            value: self
+                .interner
+                .as_mut()
+                .map(|interner| TokenValue::Symbol(interner.intern(ident))),
+        }
+    }
+
+    /// # Directives
+    ///
+    /// `%override` and friends, used inside `conditions` to change how a statement is matched.
+    /// The whole directive, `%` included, is a single token.
+    pub fn digest_directive(&mut self) -> Token<'src> {
+        let start = self.pos;
+
+        self.advance(); // move past the '%'
+
+        let name = self.digest_ident();
+
+        Token {
+            ty: Type::Directive,
+            body: self.slice(start, name.span.end),
+            span: Span::new(start, name.span.end),
+            value: None,
+        }
+    }
+
+    /// # Sigils
+    ///
+    /// The collect variables, `$` on its own or a named collector like `$args`.
+    pub fn digest_sigil(&mut self) -> Token<'src> {
+        let start = self.pos;
+        let mut end = self.next_pos();
+
+        if self.peek().is_some_and(|c| c.is_alphabetic() || c == '_') {
+            self.advance(); // move past the '$'
+
+            end = self.digest_ident().span.end;
+        }
+
+        Token {
+            ty: Type::Sigil,
+            body: self.slice(start, end),
+            span: Span::new(start, end),
+            value: None,
+        }
+    }
+
+    /// Digests "abc"
+    /// Tries to find the end quote,
+    ///
+    /// A string with `{...}` in it, like `"hello {name}"`, is an `InterpolatedString`; see
+    /// `Token::segments` for splitting it up. `\{` and `\}` are literal braces.
+    ///
+    /// The token body is what's between the quotes, exactly as written. The value has the
+    /// escapes resolved.
+    pub fn digest_literal(&mut self) -> Option<Token<'src>> {
+        let start = self.pos;
+
+        let mut value = String::new();
+        let mut interpolated = false;
+        let mut open_brace = None;
+
+        // this revising is the result
+        // of some very overestimated effort.
+        //
+        // from author ~ fixed now :)
+        while let Some(ch) = self.next() {
+            if ch == '\"' {
+                // if char is the end quote
+                if let Some(brace) = open_brace {
+                    self.error(LexErrorKind::UnterminatedInterpolation, brace, brace + 1);
+                }
+
+                let end = self.pos;
+
+                // stays on the end quote, so whatever follows isn't skipped
+                return Some(Token {
+                    ty: match interpolated {
+                        true => Type::InterpolatedString,
+                        false => Type::String,
+                    },
+                    body: self.slice(start + 1, end),
+                    span: Span::new(start, end + 1),
+                    value: match interpolated {
+                        true => None,
+                        false => Some(TokenValue::Str(value)),
+                    },
+                });
+            } else if ch == '\\' {
+                if let Some(escaped) = self.digest_escape() {
+                    value.push(escaped);
+                }
+            } else {
+                if ch == '{' && open_brace.is_none() {
+                    interpolated = true;
+                    open_brace = Some(self.pos);
+                } else if ch == '}' {
+                    open_brace = None;
+                }
+
+                value.push(ch);
+            }
+        }
+
+        // he never found his buddy
+        self.error(LexErrorKind::UnterminatedString, start, start + 1);
+
+        None
+    }
+
+    /// # Text Blocks
+    ///
+    /// `"""` strings, which can span many lines and hold bare `"` characters, so a `logic`
+    /// block can write out a whole block of text at once. Escapes work like in `"..."`.
+    ///
+    /// ```ignore
+    /// ::stdout write """
+    ///     usage: greet <name>
+    ///     says "hello" to <name>
+    /// """
+    /// ```
+    ///
+    /// A text block is a `String` token, and its body is what's between the `"""`s. Like
+    /// in `"..."`, one with `{...}` in it is an `InterpolatedString`.
+    pub fn digest_text_block(&mut self) -> Option<Token<'src>> {
+        let start = self.pos;
+        let mut value = String::new();
+        let mut interpolated = false;
+        let mut open_brace = None;
+
+        self.skip(2); // move onto the last opening quote
+
+        while let Some(ch) = self.next() {
+            if self.at("\"\"\"") {
+                if let Some(brace) = open_brace {
+                    self.error(LexErrorKind::UnterminatedInterpolation, brace, brace + 1);
+                }
+
+                let body = self.slice(start + 3, self.pos);
+
+                self.skip(2); // move onto the last closing quote
+
+                let end = self.next_pos();
+
+                return Some(Token {
+                    ty: match interpolated {
+                        true => Type::InterpolatedString,
+                        false => Type::String,
+                    },
+                    body,
+                    span: Span::new(start, end),
+                    value: match interpolated {
+                        true => None,
+                        false => Some(TokenValue::Str(value)),
+                    },
+                });
+            } else if ch == '\\' {
+                if let Some(escaped) = self.digest_escape() {
+                    value.push(escaped);
+                }
+            } else {
+                if ch == '{' && open_brace.is_none() {
+                    interpolated = true;
+                    open_brace = Some(self.pos);
+                } else if ch == '}' {
+                    open_brace = None;
+                }
+
+                value.push(ch);
+            }
+        }
+
+        self.error(LexErrorKind::UnterminatedString, start, start + 3);
+
+        None
+    }
+
+    /// # Raw Strings
+    ///
+    /// `r"..."`, where a backslash is just a backslash. Handy for regexes and Windows paths.
+    /// The token body is what's between the quotes, and so is the value.
+    pub fn digest_raw_literal(&mut self) -> Option<Token<'src>> {
+        let start = self.pos;
+
+        self.advance(); // move onto the opening quote
+
+        while let Some(ch) = self.next() {
+            if ch == '\"' {
+                let body = self.slice(start + 2, self.pos);
+                let end = self.next_pos();
+
+                return Some(Token {
+                    ty: Type::RawString,
+                    body,
+                    span: Span::new(start, end),
+                    value: Some(TokenValue::Str(body.to_owned())),
+                });
+            }
+        }
+
+        self.error(LexErrorKind::UnterminatedString, start, start + 2);
+
+        None
+    }
+
+    /// # Characters
+    ///
+    /// `'a'`, `'\n'`, `'\u{1F600}'`. Exactly one character (or escape) between single
+    /// quotes; the token body is what's between the quotes, and the value is the character.
+    pub fn digest_char(&mut self) -> Option<Token<'src>> {
+        let start = self.pos;
+
+        let value = match self.next() {
+            Some('\\') => self.digest_escape(),
+
+            Some('\'') => {
+                self.error(LexErrorKind::EmptyChar, start, self.next_pos());
+                return None;
+            }
+
+            Some('\n') | None => {
+                self.error(LexErrorKind::UnterminatedChar, start, start + 1);
+                return None;
+            }
+
+            ch => ch,
+        };
+
+        if self.peek() == Some('\'') {
+            self.advance(); // move onto the closing quote
+
+            let end = self.next_pos();
+
+            return Some(Token {
+                ty: Type::Char,
+                body: self.slice(start + 1, self.pos),
+                span: Span::new(start, end),
+                value: value.map(TokenValue::Char),
+            });
+        }
+
+        // more than one character, look for the closing quote on this line
+        while let Some(ch) = self.peek() {
+            if ch == '\n' {
+                break;
+            }
+
+            self.advance();
+
+            if ch == '\'' {
+                self.error(LexErrorKind::CharTooLong, start, self.next_pos());
+                return None;
+            }
+        }
+
+        self.error(LexErrorKind::UnterminatedChar, start, start + 1);
+
+        None
+    }
+
+    /// # Escapes
+    ///
+    /// Digests an escape sequence starting at the current `\`, and returns the character it
+    /// stands for. Stops on the escape's last character.
+    ///
+    /// | escape     | character            |
+    /// |------------|----------------------|
+    /// | `\n`       | newline              |
+    /// | `\t`       | tab                  |
+    /// | `\r`       | carriage return      |
+    /// | `\0`       | null                 |
+    /// | `\\`       | backslash            |
+    /// | `\"`       | double quote         |
+    /// | `\'`       | single quote         |
+    /// | `\{`, `\}` | literal braces       |
+    /// | `\xNN`     | ASCII, up to `\x7F`  |
+    /// | `\u{NNNN}` | any unicode scalar   |
+    ///
+    /// Anything else is recorded as an error and yields `None`.
+    pub fn digest_escape(&mut self) -> Option<char> {
+        let start = self.pos;
+
+        let escaped = match self.next()? {
+            'n' => '\n',
+            't' => '\t',
+            'r' => '\r',
+            '0' => '\0',
+            '\\' => '\\',
+            '\"' => '\"',
+            '\'' => '\'',
+            '{' => '{',
+            '}' => '}',
+            'x' => return self.digest_hex_escape(start),
+            'u' => return self.digest_unicode_escape(start),
+            ch => {
+                self.error(LexErrorKind::UnknownEscape(ch), start, self.next_pos());
+                return None;
+            }
+        };
+
+        Some(escaped)
+    }
+
+    /// `\xNN`, with the cursor on the `x`
+    fn digest_hex_escape(&mut self, start: usize) -> Option<char> {
+        let mut value = 0;
+
+        for _ in 0..2 {
+            match self.peek().and_then(|c| c.to_digit(16)) {
+                Some(digit) => {
+                    value = value * 16 + digit;
+                    self.advance();
+                }
+                None => {
+                    self.error(LexErrorKind::InvalidHexEscape, start, self.next_pos());
+                    return None;
+                }
+            }
+        }
+
+        if value > 0x7F {
+            self.error(LexErrorKind::InvalidHexEscape, start, self.next_pos());
+            return None;
+        }
+
+        char::from_u32(value)
+    }
+
+    /// `\u{NNNN}`, with the cursor on the `u`
+    fn digest_unicode_escape(&mut self, start: usize) -> Option<char> {
+        if self.peek() != Some('{') {
+            self.error(LexErrorKind::InvalidUnicodeEscape, start, self.next_pos());
+            return None;
+        }
+
+        self.advance(); // move onto the '{'
+
+        let mut value: u32 = 0;
+        let mut digits = 0;
+
+        while let Some(digit) = self.peek().and_then(|c| c.to_digit(16)) {
+            value = value.saturating_mul(16).saturating_add(digit);
+            digits += 1;
+            self.advance();
+        }
+
+        if self.peek() != Some('}') || digits == 0 || digits > 6 {
+            self.error(LexErrorKind::InvalidUnicodeEscape, start, self.next_pos());
+            return None;
+        }
+
+        self.advance(); // move onto the '}'
+
+        match char::from_u32(value) {
+            Some(ch) => Some(ch),
+            None => {
+                self.error(LexErrorKind::InvalidUnicodeEscape, start, self.next_pos());
+                None
+            }
+        }
+    }
+
+    /// records an error covering `start..end`
+    fn error(&mut self, kind: LexErrorKind, start: usize, end: usize) {
+        self.errors.push(LexError {
+            kind,
+            span: Span::new(start, end),
+        });
+    }
+
+    /// # Numbers
+    ///
+    /// `.newton` has very simple number support.
+    ///
+    /// All numbers are parsed as floats, but can be generally interpreted as an integer.
+    ///
+    /// `0x`, `0o`, and `0b` prefixes give hexadecimal, octal, and binary numbers, see
+    /// `Token::radix`. Decimal numbers can have an exponent, like `6.674e-11`.
+    ///
+    /// A number has at most one decimal point, with digits on both sides, and `_` separators
+    /// only go between two digits (`1_000_000`). Anything else is reported as an error.
+    pub fn digest_number(&mut self) -> Token<'src> {
+        let start = self.pos;
+
+        if self.cur() == Some('0') {
+            let radix = match self.peek() {
+                Some('x') => Some(Radix::Hexadecimal),
+                Some('o') => Some(Radix::Octal),
+                Some('b') => Some(Radix::Binary),
+                _ => None,
+            };
+
+            if let Some(radix) = radix {
+                return self.digest_radix_number(radix);
+            }
+        }
+
+        let mut exponent = None; // digits after the `e`, if there is one
+        let mut point = false; // if there's been a decimal point
+        let mut prev = None;
+
+        while let Some(ch) = self.cur() {
+            let between_digits = prev.is_some_and(|c: char| c.is_ascii_digit())
+                && self.peek().is_some_and(|c| c.is_ascii_digit());
+
+            match ch {
+                '.' => {
+                    if point || exponent.is_some() || !between_digits {
+                        self.error(
+                            LexErrorKind::MisplacedDecimalPoint,
+                            self.pos,
+                            self.next_pos(),
+                        );
+                    }
+
+                    point = true;
+                }
+
+                '_' => {
+                    if !between_digits {
+                        self.error(LexErrorKind::MisplacedUnderscore, self.pos, self.next_pos());
+                    }
+                }
+
+                'e' | 'E' => {
+                    exponent = Some(0);
+
+                    if matches!(self.peek(), Some('+' | '-')) {
+                        self.advance(); // the sign belongs to the exponent
+                    }
+                }
+
+                // a digit
+                _ => {
+                    if let Some(digits) = exponent.as_mut() {
+                        *digits += 1;
+                    }
+                }
+            }
+
+            prev = Some(ch);
+
+            // stop on the last character of the number, whatever comes next is its own token
+            match self.peek() {
+                Some('.') if self.buffer[self.next_pos()..].starts_with("..") => break, // 1..10
+                Some('0'..='9' | '.' | '_') => self.advance(),
+                Some('e' | 'E') if exponent.is_none() => self.advance(),
+                _ => break,
+            }
+        }
+
+        let end = self.next_pos();
+
+        if exponent == Some(0) {
+            self.error(LexErrorKind::MissingExponent, start, end);
+        }
+
+        // still two tokens, but strict mode wants something between them
+        if let Some(ch) = self
+            .peek()
+            .filter(|c| self.options.strict && c.is_alphabetic())
+        {
+            self.error(
+                LexErrorKind::InvalidDigit(ch, Radix::Decimal),
+                end,
+                end + ch.len_utf8(),
+            );
+        }
+
+        let body = self.slice(start, end);
+
+        Token {
+            ty: Type::Number,
+            body,
+            span: Span::new(start, end),
+            value: body.replace('_', "").parse().ok().map(TokenValue::Number),
+        }
+    }
+
+    /// `0x1F`, `0o17`, `0b1010`, with the cursor on the `0`
+    ///
+    /// Digits that don't belong to the radix are reported, but still end up in the token.
+    fn digest_radix_number(&mut self, radix: Radix) -> Token<'src> {
+        let start = self.pos;
+        let mut digits = 0;
+
+        self.advance(); // move onto the prefix letter
+
+        while let Some(ch) = self.peek() {
+            if ch == '_' {
+                let after_digit = self.cur().is_some_and(|c| c.is_digit(radix.base()));
+
+                self.advance();
+
+                if !after_digit || !self.peek().is_some_and(|c| c.is_digit(radix.base())) {
+                    self.error(LexErrorKind::MisplacedUnderscore, self.pos, self.next_pos());
+                }
+
+                continue;
+            }
+
+            if !ch.is_alphanumeric() {
+                break;
+            }
+
+            self.advance();
+
+            if ch.is_digit(radix.base()) {
+                digits += 1;
+            } else {
+                self.error(
+                    LexErrorKind::InvalidDigit(ch, radix),
+                    self.pos,
+                    self.next_pos(),
+                );
+            }
+        }
+
+        let end = self.next_pos();
+
+        if digits == 0 {
+            self.error(LexErrorKind::MissingDigits(radix), start, end);
+        }
+
+        let body = self.slice(start, end);
+        let digits = body[2..].replace('_', "");
+        let value = u64::from_str_radix(&digits, radix.base());
+
+        // bad and missing digits were reported on the way, only the size is left to check
+        if value
+            .as_ref()
+            .is_err_and(|e| *e.kind() == std::num::IntErrorKind::PosOverflow)
+        {
+            self.error(LexErrorKind::NumberTooLarge(radix), start, end);
+        }
+
+        Token {
+            ty: Type::Number,
+            body,
+            span: Span::new(start, end),
+            value: value.ok().map(|n| TokenValue::Number(n as f64)),
+        }
+    }
+
+    /// `::` is a member access, and a lone `:` a colon. Whatever follows is left to the next
+    /// token, so `::stdout` is `::` then `stdout`.
+    pub fn digest_access(&mut self) -> Token<'src> {
+        let start = self.pos;
+
+        if self.peek() != Some(':') {
+            return self.digest_single(Type::Colon);
+        }
+
+        self.advance();
+
+        Token {
+            ty: Type::MemberAccess,
+            body: self.slice(start, start + 2),
+            span: Span::new(start, start + 2),
+            value: None,
+        }
+    }
+}
+
+/// # Checkpoints
+///
+/// A saved lexer position from [`Lexer::checkpoint`], so a scan can be tried and undone with
+/// [`Lexer::rollback`]. Only valid for the lexer it came from.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Checkpoint {
+    pos: usize,
+    current: Option<char>,
+    line: usize,
+    errors: usize,
+    finished: bool,
+}
+
+/// # Token Stream
+///
+/// A lazy view of the lexer from [`Lexer::tokens`]. Tokens are lexed as they are asked for,
+/// and any error found along the way is handed out as an `Err` right before the token it
+/// was found in, e.g. an unknown escape comes before its string.
+///
+/// Errors are still collected in `Lexer::errors`, so stopping at the first one is up to the
+/// caller, e.g. `collect::<Result<Vec<_>, _>>()`. The token behind an error waits in the
+/// stream, so keep pulling from the same stream to carry on past it.
+pub struct Tokens<'lex, 'src> {
+    lexer: &'lex mut Lexer<'src>,
+    reported: usize,              // how many of the lexer's errors were handed out
+    pending: Option<Token<'src>>, // a token waiting behind its errors
+}
+
+impl<'src> Iterator for Tokens<'_, 'src> {
+    type Item = Result<Token<'src>, LexError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.lexer.errors.get(self.reported) {
+            self.reported += 1;
+            return Some(Err(error.clone()));
+        }
+
+        if let Some(token) = self.pending.take() {
+            return Some(Ok(token));
+        }
+
+        let token = self.lexer.next_token();
+
+        if let Some(error) = self.lexer.errors.get(self.reported) {
+            self.reported += 1;
+            self.pending = token;
+            return Some(Err(error.clone()));
+        }
+
+        token.map(Ok)
+    }
+}
+
+/// # Stream Lexer
+///
+/// Lexes from any `io::Read` a chunk at a time, from [`Lexer::from_reader`]. Only the part of
+/// the input that hasn't been lexed yet is kept in memory.
+///
+/// Chunks end on a line break, and a chunk that ends inside a string or block comment is
+/// grown until it doesn't, so no token is ever split. Spans count from the start of the
+/// stream, not the chunk, and only the start of the stream has a BOM or a `#!` line.
+///
+/// ```
+/// # use newton::newton_lex::{Lexer, Type};
+/// let mut stream = Lexer::from_reader("new x\nlogic".as_bytes());
+/// let mut types = Vec::new();
+///
+/// while let Some(tokens) = stream.next_chunk().unwrap() {
+///     types.extend(tokens.into_iter().map(|t| t.ty));
+/// }
+///
+/// assert_eq!(types.last(), Some(&Type::Eof));
+/// ```
+pub struct StreamLexer<R> {
+    reader: R,
+    pub options: LexerOptions, // host configuration
+    pub errors: Vec<LexError>, // errors found so far, spans count from the start of the stream
+    pub chunk_size: usize,     // how much is read at a time
+    pending: Vec<u8>,          // read but not yet handed out
+    consumed: usize,           // how much of `pending` the last chunk covered
+    base: usize,               // the stream offset `pending` starts at
+    line: usize,               // the line `pending` starts on
+    scanned: usize,            // where in `pending` the token left open last time starts
+    recheck: usize,            // how long the chunk has to get before it's looked at again
+    done: bool,                // if the reader ran dry and the last chunk was handed out
+}
+
+impl<'src> Lexer<'src> {
+    /// lexes from a reader instead of a buffer, see [`StreamLexer`]
+    pub fn from_reader<R: std::io::Read>(reader: R) -> StreamLexer<R> {
+        Self::from_reader_with(reader, LexerOptions::default())
+    }
+
+    /// lexes a memory-mapped file, see [`MappedSource`](crate::newton_source::MappedSource)
+    ///
+    /// A lexer can't own the mapping its tokens borrow from, so the file is opened first.
+    #[cfg(feature = "mmap")]
+    pub fn from_mapped(source: &'src crate::newton_source::MappedSource) -> Self {
+        Self::new(source.text())
+    }
+
+    /// lexes from a reader with host-provided options
+    pub fn from_reader_with<R: std::io::Read>(reader: R, options: LexerOptions) -> StreamLexer<R> {
+        StreamLexer {
+            reader,
+            options,
+            errors: Vec::new(),
+            chunk_size: 64 * 1024,
+            pending: Vec::new(),
+            consumed: 0,
+            base: 0,
+            line: 1,
+            scanned: 0,
+            recheck: 0,
+            done: false,
+        }
+    }
+}
+
+impl<R: std::io::Read> StreamLexer<R> {
+    /// lexes the next chunk, `None` once the stream is done
+    ///
+    /// The tokens borrow from the stream, so they have to be dropped before the next chunk is
+    /// asked for. Only the last chunk ends with `Eof`.
+    pub fn next_chunk(&mut self) -> std::io::Result<Option<Vec<Token<'_>>>> {
+        if self.done {
+            return Ok(None);
+        }
+
+        self.pending.drain(..self.consumed);
+        self.base += self.consumed;
+        self.consumed = 0;
+        self.scanned = 0;
+        self.recheck = 0;
+
+        let (cut, last) = loop {
+            let last = self.fill()?;
+
+            if last {
+                break (self.pending.len(), true);
+            }
+
+            // only cut on a line break, and never inside a token running past it
+            if let Some(newline) = self.pending.iter().rposition(|&b| b == b'\n') {
+                if self.is_whole(newline + 1)? {
+                    break (newline + 1, false);
+                }
+            }
+        };
+
+        let text = Self::text(&self.pending[..cut])?;
+        let mut lexer = Lexer::new_with(text, self.options.clone());
+
+        lexer.line = self.line;
+        lexer.continued = self.base > 0;
+
+        let mut tokens: Vec<Token> = lexer.lexeme();
+
+        if !last {
+            tokens.pop(); // the `Eof`, more is on the way
+        }
+
+        for token in tokens.iter_mut() {
+            token.span = token.span.shift(self.base as isize);
+        }
+
+        self.errors
+            .extend(lexer.errors.into_iter().map(|error| LexError {
+                span: error.span.shift(self.base as isize),
+                ..error
+            }));
+
+        self.line = lexer.line;
+        self.consumed = cut;
+        self.done = last;
+
+        Ok(Some(tokens))
+    }
+
+    /// reads another chunk onto `pending`, true if the reader is out
+    fn fill(&mut self) -> std::io::Result<bool> {
+        let start = self.pending.len();
+
+        self.pending.resize(start + self.chunk_size, 0);
+
+        let read = loop {
+            match self.reader.read(&mut self.pending[start..]) {
+                Ok(read) => break read,
+                Err(e) if e.kind() == std::io::ErrorKind::Interrupted => continue,
+                Err(e) => {
+                    self.pending.truncate(start);
+                    return Err(e);
+                }
+            }
+        };
+
+        self.pending.truncate(start + read);
+
+        Ok(read == 0)
+    }
+
+    /// if `pending[..cut]` lexes without anything left open at the end
+    ///
+    /// Everything before the token left open last time was whole then, so lexing picks up
+    /// at that token. While it's still open, the chunk has to double before it's lexed
+    /// again, so a string or comment running over many refills is lexed in time that grows
+    /// with its length, not its square.
+    fn is_whole(&mut self, cut: usize) -> std::io::Result<bool> {
+        if cut < self.recheck {
+            return Ok(false);
+        }
+
+        let options = self.options.clone().trivia(true); // so a comment is a token of its own
+        let mut lexer =
+            Lexer::new_with(Self::text(&self.pending[..cut])?, options).starting_at(self.scanned);
+        let mut reported = 0;
+
+        lexer.continued = self.base > 0;
+
+        while let Some(token) = lexer.next_token() {
+            let open = lexer.errors[reported..].iter().find(|error| {
+                matches!(
+                    error.kind,
+                    LexErrorKind::UnterminatedString
+                        | LexErrorKind::UnterminatedBlockComment
+                        | LexErrorKind::UnterminatedInterpolation
+                )
+            });
+
+            // an unterminated string has no token, the one after it is what came back, and
+            // an open `{` is reported inside its string, so whichever starts first
+            if let Some(error) = open {
+                self.scanned = error.span.start.min(token.span.start);
+                self.recheck = cut * 2;
+                return Ok(false);
+            }
+
+            reported = lexer.errors.len();
+        }
+
+        Ok(true)
+    }
+
+    fn text(bytes: &[u8]) -> std::io::Result<&str> {
+        std::str::from_utf8(bytes)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))
+    }
+}
+
+/// a file's tokens, or its errors if it had any
+pub type Lexed<'src> = Result<Vec<Token<'src>>, Vec<LexError>>;
+
+/// # Lexing Many Files
+///
+/// Lexes every file in `files`, in parallel with the `rayon` feature, for tools that go
+/// through a whole project at once. Each file comes back with its tokens, or with its
+/// errors if it had any, in the order of `files`.
+///
+/// The files have to be in `map` already, `SourceMap::load_all` reads a list of paths. The
+/// map is only borrowed, so it can still resolve the errors afterwards.
+///
+/// ```no_run
+/// # use newton::newton_lex::lex_many;
+/// # use newton::newton_source::{Location, SourceMap};
+/// let mut map = SourceMap::new();
+/// let files = map.load_all(&["a.newton", "b.newton"]).unwrap();
+///
+/// for (file, lexed) in lex_many(&map, &files) {
+///     for error in lexed.err().unwrap_or_default() {
+///         println!("{}: {}", map.resolve(Location::new(file, error.span)).unwrap(), error);
+///     }
+/// }
+/// ```
+pub fn lex_many<'map>(map: &'map SourceMap, files: &[FileId]) -> Vec<(FileId, Lexed<'map>)> {
+    lex_many_with(map, files, &LexerOptions::default())
+}
+
+/// lexes many files with host-provided options, see [`lex_many`]
+pub fn lex_many_with<'map>(
+    map: &'map SourceMap,
+    files: &[FileId],
+    options: &LexerOptions,
+) -> Vec<(FileId, Lexed<'map>)> {
+    let lex = |&file: &FileId| {
+        let mut lexer = Lexer::new_with(&map.file(file).text, options.clone());
+        let tokens = lexer.lexeme();
+
+        match lexer.errors.is_empty() {
+            true => (file, Ok(tokens)),
+            false => (file, Err(lexer.errors)),
+        }
+    };
+
+    #[cfg(feature = "rayon")]
+    {
+        use rayon::prelude::*;
+
+        files.par_iter().map(lex).collect()
+    }
+
+    #[cfg(not(feature = "rayon"))]
+    {
+        files.iter().map(lex).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_source::SourceMap;
+
+    /// test the span length
+    #[test]
+    pub fn test_simple_span() {
+        let span = Span::new(5, 10);
+        assert_eq!(span.len(), 5);
+    }
+
+    /// test the span peeking feature
+    #[test]
+    pub fn test_span_peek() {
+        let span = Span::new(6, 11);
+        let str = "hello world";
+        let slice = span.slice_and_dice(str);
+
+        assert_eq!(slice, Some("world"));
+    }
+
+    #[test]
+    pub fn test_span_out_of_range() {
+        let str = "hé";
+
+        assert_eq!(Span::new(0, 9).slice_and_dice(str), None);
+        assert_eq!(Span::new(2, 3).slice_and_dice(str), None);
+        assert_eq!(Span::new(2, 1).slice_and_dice(str), None);
+
+        assert_eq!(Span::new(0, 9).slice_clamped(str), "hé");
+        assert_eq!(Span::new(2, 3).slice_clamped(str), "é");
+        assert_eq!(Span::new(9, 12).slice_clamped(str), "");
+    }
+
+    #[test]
+    pub fn test_span_algebra() {
+        let a = Span::new(2, 6);
+        let b = Span::new(4, 9);
+        let c = Span::new(9, 12);
+
+        assert_eq!(a.join(b), Span::new(2, 9));
+        assert_eq!(c.join(a), Span::new(2, 12));
+        assert!(a.intersects(b) && b.intersects(a));
+        assert!(!b.intersects(c));
+        assert!(!a.intersects(Span::new(4, 4))); // empty spans hold nothing
+        assert!(b.contains(8) && !b.contains(9) && !b.contains(3));
+        assert_eq!(c.shift(-9), Span::new(0, 3));
+        assert_eq!(c.shift(0), c);
+    }
+
+    #[test]
+    pub fn test_span_perfect() {
+        let mut span = Span::new(5, 10);
+
+        assert!(span.forward());
+
+        span.start = 50;
+        span.end = 1; // backward span?
+
+        assert!(!span.forward());
+    }
+
+    #[test]
+    pub fn test_span_backward() {
+        let mut span = Span::new(5, 10);
+
+        assert!(!span.backward());
+
+        span.start = 50;
+        span.end = 1; // backward span?
+
+        assert!(span.backward());
+    }
+
+    #[test]
+    pub fn test_lex() {
+        let mut lexer = Lexer::new(
+            "; writes\n; basically that's what it does\n\t; so ya\n::write\nnew struct { }",
+        );
+
+        dbg!(&lexer);
+
+        let mut binding = lexer.lexeme();
+        dbg!(&binding);
+
+        assert_eq!(binding.len(), 7);
+
+        let first_token = binding.get_mut(0).unwrap();
+
+        assert_eq!(first_token.body, "::");
+
+        let second_token = binding.get_mut(1).unwrap();
+
+        assert_eq!(second_token.body, "write");
+    }
+
+    /// a megabyte of source should lex in linear time
+    #[test]
+    pub fn test_lex_large_input() {
+        let source = "new struct { }\n".repeat(1024 * 1024 / 15);
+        let mut lexer = Lexer::new(&source);
+
+        let tokens = lexer.lexeme();
+
+        assert_eq!(tokens.len(), 4 * (1024 * 1024 / 15) + 1);
+    }
+
+    #[test]
+    pub fn test_lex_multibyte_spans() {
+        let mut lexer = Lexer::new("; héllo wörld\nnew");
+
+        let tokens = lexer.lexeme();
+        let token = &tokens[0];
+
+        assert_eq!(token.body, "new");
+        assert_eq!(token.span.slice_and_dice(lexer.buffer), Some("new"));
+    }
+
+    #[test]
+    pub fn test_tokens_borrow_source() {
+        let source = "new \"hello \\\"world\\\"\"";
+        let mut lexer = Lexer::new(source);
+
+        let tokens = lexer.lexeme();
+        let string = &tokens[1];
+
+        assert_eq!(string.ty, Type::String);
+        assert_eq!(string.body, "hello \\\"world\\\"");
+        assert_eq!(string.body.as_ptr(), source[5..].as_ptr());
+    }
+
+    #[test]
+    pub fn test_lex_operators() {
+        let mut lexer = Lexer::new("x = a + b * 2");
+
+        let types: Vec<Type> = lexer.lexeme().into_iter().map(|t| t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                Type::Ident,
+                Type::Equal,
+                Type::Ident,
+                Type::Plus,
+                Type::Ident,
+                Type::Multiply,
+                Type::Number,
+                Type::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_comparison_operators() {
+        let mut lexer = Lexer::new("c - d / e % f > g < h");
+
+        let types: Vec<Type> = lexer.lexeme().into_iter().map(|t| t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                Type::Ident,
+                Type::Minus,
+                Type::Ident,
+                Type::Divide,
+                Type::Ident,
+                Type::Modulo,
+                Type::Ident,
+                Type::Greater,
+                Type::Ident,
+                Type::Less,
+                Type::Ident,
+                Type::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_compound_operators() {
+        let mut lexer = Lexer::new("a == b != c <= d >= e && f || g = h");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::Ident,
+                &Type::EqualEqual,
+                &Type::Ident,
+                &Type::NotEqual,
+                &Type::Ident,
+                &Type::LessEqual,
+                &Type::Ident,
+                &Type::GreaterEqual,
+                &Type::Ident,
+                &Type::And,
+                &Type::Ident,
+                &Type::Or,
+                &Type::Ident,
+                &Type::Equal,
+                &Type::Ident,
+                &Type::Eof,
+            ]
+        );
+
+        assert_eq!(tokens[1].body, "==");
+        assert_eq!(tokens[1].span, Span::new(2, 4));
+    }
+
+    #[test]
+    pub fn test_lex_single_char_spans() {
+        let mut lexer = Lexer::new("(x)é+");
+        let spans: Vec<Span> = lexer.lexeme().into_iter().map(|t| t.span).collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                Span::new(0, 1), // the very first character
+                Span::new(1, 2),
+                Span::new(2, 3),
+                Span::new(5, 6), // after the two bytes of `é`
+                Span::new(6, 6),
+            ]
+        );
+        assert_eq!(lexer.pos, 6);
+        assert_eq!(lexer.cur(), None);
+    }
+
+    #[test]
+    pub fn test_lex_brackets() {
+        let mut lexer = Lexer::new("[a, b, c] xs[i]");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::OpenBracket,
+                &Type::Ident,
+                &Type::Comma,
+                &Type::Ident,
+                &Type::Comma,
+                &Type::Ident,
+                &Type::CloseBracket,
+                &Type::Ident,
+                &Type::OpenBracket,
+                &Type::Ident,
+                &Type::CloseBracket,
+                &Type::Eof,
+            ]
+        );
+
+        assert_eq!(tokens[7].body, "xs");
+        assert_eq!(tokens[7].span, Span::new(10, 12));
+    }
+
+    #[test]
+    pub fn test_lex_arrows() {
+        let mut lexer = Lexer::new("a -> b => c - d > e");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::Ident,
+                &Type::Arrow,
+                &Type::Ident,
+                &Type::FatArrow,
+                &Type::Ident,
+                &Type::Minus,
+                &Type::Ident,
+                &Type::Greater,
+                &Type::Ident,
+                &Type::Eof,
+            ]
+        );
+
+        assert_eq!(tokens[1].body, "->");
+        assert_eq!(tokens[3].body, "=>");
+    }
+
+    #[test]
+    pub fn test_lex_directive() {
+        let mut lexer = Lexer::new("conditions {\n    any\n    %override\n}\na % b");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+
+        assert_eq!(tokens[3].ty, Type::Directive);
+        assert_eq!(tokens[3].body, "%override");
+        assert_eq!(tokens[3].span, Span::new(25, 34));
+
+        assert_eq!(tokens[6].ty, Type::Modulo);
+    }
+
+    #[test]
+    pub fn test_lex_sigil() {
+        let mut lexer = Lexer::new("collect as $\nfor $ as var { }\ncollect as $args");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+
+        assert_eq!(tokens[2].ty, Type::Sigil);
+        assert_eq!(tokens[2].body, "$");
+        assert_eq!(tokens[4].ty, Type::Sigil);
+        assert_eq!(tokens[4].body, "$");
+        assert_eq!(tokens[5].body, "as");
+
+        let named = &tokens[tokens.len() - 2];
+
+        assert_eq!(named.ty, Type::Sigil);
+        assert_eq!(named.body, "$args");
+        assert_eq!(named.span, Span::new(41, 46));
+    }
+
+    #[test]
+    pub fn test_lex_keywords() {
+        let mut lexer = Lexer::new(
+            "new conditions logic collect for any expect include let if else while match fn return break continue use print",
+        );
+
+        let mut tokens: Vec<Token> = lexer.lexeme();
+        assert_eq!(tokens.pop().unwrap().ty, Type::Eof);
+
+        let (last, keywords) = tokens.split_last().unwrap();
+
+        assert_eq!(keywords.len(), KEYWORDS.len());
+
+        for token in keywords {
+            assert_eq!(
+                token.ty,
+                Type::ReservedKeyword,
+                "{} should be a keyword",
+                token.body
+            );
+        }
+
+        assert_eq!(last.ty, Type::Ident);
+    }
+
+    #[test]
+    pub fn test_lex_registered_keywords() {
+        let options = LexerOptions::new().keyword("print").keyword("when");
+        let mut lexer = Lexer::new_with("print when new other", options);
+
+        let types: Vec<Type> = lexer.lexeme().into_iter().map(|t| t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                Type::ReservedKeyword,
+                Type::ReservedKeyword,
+                Type::ReservedKeyword,
+                Type::Ident,
+                Type::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_comment_styles() {
+        let source = "a; b\nc // slash\nd";
+
+        let lex = |style| {
+            let mut lexer = Lexer::new_with(source, LexerOptions::new().comments(style));
+
+            lexer
+                .lexeme()
+                .into_iter()
+                .map(|t| t.body)
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            lex(CommentStyle::Semicolon),
+            vec!["a", "c", "/", "/", "slash", "d", ""]
+        );
+        assert_eq!(
+            lex(CommentStyle::DoubleSlash),
+            vec!["a", ";", "b", "c", "d", ""]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_hash_comments() {
+        let options = LexerOptions::new().comments(CommentStyle::Hash);
+        let mut lexer = Lexer::new_with("a; # comment\nb;", options);
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::Ident,
+                &Type::SemiColon,
+                &Type::Ident,
+                &Type::SemiColon,
+                &Type::Eof
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_block_comments() {
+        let mut lexer = Lexer::new("a ;* one ;* two *; still one\n *; b ;**; c");
+
+        let bodies: Vec<&str> = lexer.lexeme().into_iter().map(|t| t.body).collect();
+
+        assert_eq!(bodies, vec!["a", "b", "c", ""]);
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_unterminated_block_comment() {
+        let options = LexerOptions::new().comments(CommentStyle::DoubleSlash);
+        let mut lexer = Lexer::new_with("a /* /* */ b", options);
+
+        let tokens = lexer.lexeme();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            lexer.errors,
+            vec![LexError {
+                kind: LexErrorKind::UnterminatedBlockComment,
+                span: Span::new(2, 4),
+            }]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_doc_comments() {
+        let mut lexer = Lexer::new("; plain\n;;; # Print\n;;; prints things\nnew print { }");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+
+        assert_eq!(tokens[0].ty, Type::DocComment);
+        assert_eq!(tokens[0].body, ";;; # Print");
+        assert_eq!(tokens[1].ty, Type::DocComment);
+        assert_eq!(tokens[1].body, ";;; prints things");
+        assert_eq!(tokens[2].body, "new");
+    }
+
+    #[test]
+    pub fn test_lex_escapes() {
+        let mut lexer = Lexer::new(r#""\n \t \r \0 \\ \" \x41 \u{1F600}""#);
+
+        let mut escapes = Vec::new();
+
+        lexer.next();
+
+        while let Some(ch) = lexer.next() {
+            if ch == '\\' {
+                escapes.push(lexer.digest_escape().unwrap());
+            }
+        }
+
+        assert_eq!(escapes, vec!['\n', '\t', '\r', '\0', '\\', '"', 'A', '😀']);
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_bad_escapes() {
+        let mut lexer = Lexer::new(r#""\q \xFF \x4 \u{D800} \u1234" ok"#);
+
+        let tokens = lexer.lexeme();
+        let kinds: Vec<LexErrorKind> = lexer.errors.into_iter().map(|e| e.kind).collect();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(
+            kinds,
+            vec![
+                LexErrorKind::UnknownEscape('q'),
+                LexErrorKind::InvalidHexEscape,
+                LexErrorKind::InvalidHexEscape,
+                LexErrorKind::InvalidUnicodeEscape,
+                LexErrorKind::InvalidUnicodeEscape,
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_bad_escape_span() {
+        let mut lexer = Lexer::new(r#"a "x\qy""#);
+
+        lexer.lexeme();
+
+        assert_eq!(lexer.errors[0].span, Span::new(4, 6));
+    }
+
+    #[test]
+    pub fn test_lex_raw_strings() {
+        let mut lexer = Lexer::new(r#"r"C:\new\path" r"\d+\q" rest"#);
+
+        let tokens: Vec<Token> = lexer.lexeme();
+
+        assert_eq!(tokens[0].ty, Type::RawString);
+        assert_eq!(tokens[0].body, r"C:\new\path");
+        assert_eq!(
+            tokens[0].value,
+            Some(TokenValue::Str(r"C:\new\path".to_string()))
+        );
+        assert_eq!(tokens[0].span, Span::new(0, 14));
+        assert_eq!(tokens[1].ty, Type::RawString);
+        assert_eq!(tokens[1].body, r"\d+\q");
+        assert_eq!(tokens[2].ty, Type::Ident);
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_unterminated_raw_string() {
+        let mut lexer = Lexer::new(r#"a r"never closed"#);
+
+        let tokens = lexer.lexeme();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(lexer.errors[0].kind, LexErrorKind::UnterminatedString);
+        assert_eq!(lexer.errors[0].span, Span::new(2, 4));
+    }
+
+    #[test]
+    pub fn test_lex_text_blocks() {
+        let source = "a \"\"\"\n    says \"hello\"\n    \\t done\n\"\"\" b \"\" c";
+        let mut lexer = Lexer::new(source);
+
+        let tokens: Vec<Token> = lexer.lexeme();
+
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(tokens[1].ty, Type::String);
+        assert_eq!(tokens[1].body, "\n    says \"hello\"\n    \\t done\n");
+        assert_eq!(
+            tokens[1].value,
+            Some(TokenValue::Str(
+                "\n    says \"hello\"\n    \t done\n".to_string()
+            ))
+        );
+        assert_eq!(tokens[2].body, "b");
+        assert_eq!(tokens[3].body, "");
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_unterminated_text_block() {
+        let mut lexer = Lexer::new("\"\"\" never \"\" closed");
+
+        let tokens = lexer.lexeme();
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].ty, Type::Eof);
+        assert_eq!(lexer.errors[0].kind, LexErrorKind::UnterminatedString);
+        assert_eq!(lexer.errors[0].span, Span::new(0, 3));
+    }
+
+    #[test]
+    pub fn test_lex_interpolated_strings() {
+        let source = r#""hello {name}, \{not\} {  count } \u{1F600}!" "plain \u{41}""#;
+        let mut lexer = Lexer::new(source);
+
+        let tokens: Vec<Token> = lexer.lexeme();
+
+        assert_eq!(tokens[0].ty, Type::InterpolatedString);
+        assert_eq!(tokens[1].ty, Type::String);
+        assert!(lexer.errors.is_empty());
+
+        let segments = tokens[0].segments();
+
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Text("hello ", Span::new(1, 7)),
+                Segment::Expr("name", Span::new(8, 12)),
+                Segment::Text(r#", \{not\} "#, Span::new(13, 23)),
+                Segment::Expr("  count ", Span::new(24, 32)),
+                Segment::Text(r#" \u{1F600}!"#, Span::new(33, 44)),
+            ]
+        );
+
+        for segment in segments {
+            if let Segment::Expr(text, span) = segment {
+                assert_eq!(span.slice_and_dice(source), Some(text));
+            }
+        }
+
+        assert_eq!(
+            tokens[1].segments(),
+            vec![Segment::Text(r#"plain \u{41}"#, Span::new(47, 59))]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_interpolated_text_blocks() {
+        let source = "\"\"\"\n  hi {name}\n  \\{x\\}\"\"\" \"\"\"plain\"\"\"";
+        let mut lexer = Lexer::new(source);
+
+        let tokens = lexer.lexeme();
+
+        assert_eq!(tokens[0].ty, Type::InterpolatedString);
+        assert_eq!(tokens[0].value, None);
+        assert_eq!(tokens[1].ty, Type::String);
+        assert!(lexer.errors.is_empty());
+
+        let segments = tokens[0].segments();
+
+        assert_eq!(
+            segments,
+            vec![
+                Segment::Text("\n  hi ", Span::new(3, 9)),
+                Segment::Expr("name", Span::new(10, 14)),
+                Segment::Text("\n  \\{x\\}", Span::new(15, 23)),
+            ]
+        );
+
+        for segment in segments {
+            let (Segment::Text(text, span) | Segment::Expr(text, span)) = segment;
+
+            assert_eq!(span.slice_and_dice(source), Some(text));
+        }
+
+        assert_eq!(
+            tokens[1].segments(),
+            vec![Segment::Text("plain", Span::new(30, 35))]
+        );
+
+        let mut lexer = Lexer::new("\"\"\"a {b\"\"\"");
+
+        lexer.lexeme();
+
+        assert_eq!(
+            lexer.errors[0].kind,
+            LexErrorKind::UnterminatedInterpolation
+        );
+    }
+
+    #[test]
+    pub fn test_lex_unterminated_interpolation() {
+        let mut lexer = Lexer::new(r#""hello {name""#);
+
+        let tokens = lexer.lexeme();
+
+        assert_eq!(tokens.len(), 2);
+        assert_eq!(
+            lexer.errors[0].kind,
+            LexErrorKind::UnterminatedInterpolation
+        );
+        assert_eq!(lexer.errors[0].span, Span::new(7, 8));
+    }
+
+    #[test]
+    pub fn test_lex_chars() {
+        let mut lexer = Lexer::new(r"'a' '\n' '\u{1F600}' '\'' 'é'");
+
+        let mut tokens: Vec<Token> = lexer.lexeme();
+        assert_eq!(tokens.pop().unwrap().ty, Type::Eof);
+
+        let bodies: Vec<&str> = tokens.iter().map(|t| t.body).collect();
+        let values: Vec<Option<TokenValue>> = tokens.iter().map(|t| t.value.clone()).collect();
+
+        assert!(tokens.iter().all(|t| t.ty == Type::Char));
+        assert_eq!(bodies, vec!["a", r"\n", r"\u{1F600}", r"\'", "é"]);
+        assert_eq!(
+            values,
+            vec![
+                Some(TokenValue::Char('a')),
+                Some(TokenValue::Char('\n')),
+                Some(TokenValue::Char('😀')),
+                Some(TokenValue::Char('\'')),
+                Some(TokenValue::Char('é')),
+            ]
+        );
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_bad_chars() {
+        let mut lexer = Lexer::new("'' 'print' x 'a\ny");
+
+        let bodies: Vec<&str> = lexer.lexeme().into_iter().map(|t| t.body).collect();
+        let errors: Vec<(LexErrorKind, Span)> =
+            lexer.errors.into_iter().map(|e| (e.kind, e.span)).collect();
+
+        assert_eq!(bodies, vec!["x", "y", ""]);
+        assert_eq!(
+            errors,
+            vec![
+                (LexErrorKind::EmptyChar, Span::new(0, 2)),
+                (LexErrorKind::CharTooLong, Span::new(3, 10)),
+                (LexErrorKind::UnterminatedChar, Span::new(13, 14)),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_radix_numbers() {
+        let mut lexer = Lexer::new("0xFF_ff 0o17 0b1010_1010 {0x1}\n12");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let numbers: Vec<(&str, Option<Radix>)> = tokens
+            .iter()
+            .filter(|t| t.ty == Type::Number)
+            .map(|t| (t.body, t.radix()))
+            .collect();
+
+        assert_eq!(
+            numbers,
+            vec![
+                ("0xFF_ff", Some(Radix::Hexadecimal)),
+                ("0o17", Some(Radix::Octal)),
+                ("0b1010_1010", Some(Radix::Binary)),
+                ("0x1", Some(Radix::Hexadecimal)),
+                ("12", Some(Radix::Decimal)),
+            ]
+        );
+
+        assert_eq!(tokens[5].ty, Type::CloseBrace);
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_bad_radix_numbers() {
+        let mut lexer = Lexer::new("0b102 0o8 0x");
+
+        let tokens = lexer.lexeme();
+        let errors: Vec<(LexErrorKind, Span)> =
+            lexer.errors.into_iter().map(|e| (e.kind, e.span)).collect();
+
+        assert_eq!(tokens.len(), 4);
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    LexErrorKind::InvalidDigit('2', Radix::Binary),
+                    Span::new(4, 5)
+                ),
+                (
+                    LexErrorKind::InvalidDigit('8', Radix::Octal),
+                    Span::new(8, 9)
+                ),
+                (LexErrorKind::MissingDigits(Radix::Octal), Span::new(6, 9)),
+                (
+                    LexErrorKind::MissingDigits(Radix::Hexadecimal),
+                    Span::new(10, 12)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_radix_overflow() {
+        let source = "0xFFFF_FFFF_FFFF_FFFF 0x1_0000_0000_0000_0000 0b1 0o7777777777777777777777";
+        let mut lexer = Lexer::new(source);
+
+        let tokens = lexer.lexeme();
+        let errors: Vec<(LexErrorKind, &str)> = lexer
+            .errors
+            .iter()
+            .map(|e| (e.kind.clone(), e.span.slice_clamped(source)))
+            .collect();
+
+        assert_eq!(tokens[0].value, Some(TokenValue::Number(u64::MAX as f64)));
+        assert_eq!(tokens[1].value, None);
+        assert_eq!(
+            errors,
+            vec![
+                (
+                    LexErrorKind::NumberTooLarge(Radix::Hexadecimal),
+                    "0x1_0000_0000_0000_0000"
+                ),
+                (
+                    LexErrorKind::NumberTooLarge(Radix::Octal),
+                    "0o7777777777777777777777"
+                ),
+            ]
+        );
+        assert_eq!(
+            lexer.errors[0].kind.to_string(),
+            "hexadecimal number doesn't fit in 64 bits"
+        );
+        assert_eq!(lexer.errors[0].kind.code(), "N0002");
+    }
+
+    #[test]
+    pub fn test_lex_scientific_notation() {
+        for source in ["1e9", "2.5e-3", "6.674E+11", "1_000e1_0"] {
+            let mut lexer = Lexer::new(source);
+
+            let tokens = lexer.lexeme();
+            let token = &tokens[0];
+
+            assert_eq!(tokens.len(), 2);
+            assert_eq!(token.ty, Type::Number);
+            assert_eq!(token.body, source);
+            assert!(lexer.errors.is_empty(), "{}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_lex_missing_exponent() {
+        for source in ["1e", "2.5e-"] {
+            let mut lexer = Lexer::new(source);
+
+            lexer.lexeme();
+
+            assert_eq!(lexer.errors[0].kind, LexErrorKind::MissingExponent);
+            assert_eq!(lexer.errors[0].span, Span::new(0, source.len()));
+        }
+    }
+
+    #[test]
+    pub fn test_lex_valid_separators() {
+        for source in ["1_000_000", "3.141_592", "1e1_0", "0xFF_FF", "0b1_0"] {
+            let mut lexer = Lexer::new(source);
+
+            lexer.lexeme();
+
+            assert!(lexer.errors.is_empty(), "{}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_lex_malformed_numbers() {
+        let cases = [
+            (
+                "1._2.3_",
+                vec![
+                    (LexErrorKind::MisplacedDecimalPoint, 1),
+                    (LexErrorKind::MisplacedUnderscore, 2),
+                    (LexErrorKind::MisplacedDecimalPoint, 4),
+                    (LexErrorKind::MisplacedUnderscore, 6),
+                ],
+            ),
+            ("1.2.3", vec![(LexErrorKind::MisplacedDecimalPoint, 3)]),
+            ("1e5.0", vec![(LexErrorKind::MisplacedDecimalPoint, 3)]),
+            (
+                "1__0",
+                vec![
+                    (LexErrorKind::MisplacedUnderscore, 1),
+                    (LexErrorKind::MisplacedUnderscore, 2),
+                ],
+            ),
+            ("0x_1", vec![(LexErrorKind::MisplacedUnderscore, 2)]),
+        ];
+
+        for (source, expected) in cases {
+            let mut lexer = Lexer::new(source);
+
+            let tokens = lexer.lexeme();
+            let errors: Vec<(LexErrorKind, usize)> = lexer
+                .errors
+                .into_iter()
+                .map(|e| (e.kind, e.span.start))
+                .collect();
+
+            assert_eq!(tokens.len(), 2, "{}", source);
+            assert_eq!(errors, expected, "{}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_lex_numbers_end_at_delimiters() {
+        let mut lexer = Lexer::new("new x { 123 }\n[1, 2.5, 3e2]\n1+2*3)\n7}");
+
+        let bodies: Vec<&str> = lexer.lexeme().into_iter().map(|t| t.body).collect();
+
+        assert_eq!(
+            bodies,
+            vec![
+                "new", "x", "{", "123", "}", "[", "1", ",", "2.5", ",", "3e2", "]", "1", "+", "2",
+                "*", "3", ")", "7", "}", "",
+            ]
+        );
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_number_then_ident() {
+        let mut lexer = Lexer::new("5abc 10 x");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::Number,
+                &Type::Ident,
+                &Type::Number,
+                &Type::Ident,
+                &Type::Eof
+            ]
+        );
+        assert_eq!(tokens[0].span, Span::new(0, 1));
+        assert_eq!(tokens[2].span, Span::new(5, 7));
+    }
+
+    #[test]
+    pub fn test_lex_string_values() {
+        let mut lexer = Lexer::new(r#""tab\tquote\"" "hi {name}""#);
+
+        let tokens: Vec<Token> = lexer.lexeme();
+
+        assert_eq!(tokens[0].body, r#"tab\tquote\""#);
+        assert_eq!(tokens[0].span, Span::new(0, 14));
+        assert_eq!(
+            tokens[0].value,
+            Some(TokenValue::Str("tab\tquote\"".to_string()))
+        );
+
+        assert_eq!(tokens[1].body, "hi {name}");
+        assert_eq!(tokens[1].value, None);
     }
 
     #[test]
-    pub fn test_span_backward() {
-        let mut span = Span::new(5, 10);
+    pub fn test_lex_number_values() {
+        let mut lexer = Lexer::new("1_000 2.5e3 0xFF 0o17 0b1010 6.674e-11");
 
-        assert_eq!(span.backward(), false);
+        let values: Vec<Option<TokenValue>> = lexer.lexeme().into_iter().map(|t| t.value).collect();
 
-        span.start = 50;
-        span.end = 1; // backward span?
+        assert_eq!(
+            values,
+            vec![
+                Some(TokenValue::Number(1000.0)),
+                Some(TokenValue::Number(2500.0)),
+                Some(TokenValue::Number(255.0)),
+                Some(TokenValue::Number(15.0)),
+                Some(TokenValue::Number(10.0)),
+                Some(TokenValue::Number(6.674e-11)),
+                None,
+            ]
+        );
+    }
 
-        assert_eq!(span.backward(), true);
+    #[test]
+    pub fn test_lex_malformed_number_has_no_value() {
+        let mut lexer = Lexer::new("1.2.3 0x");
+
+        let values: Vec<Option<TokenValue>> = lexer.lexeme().into_iter().map(|t| t.value).collect();
+
+        assert_eq!(values, vec![None, None, None]);
     }
 
     #[test]
-    pub fn test_lex() {
+    pub fn test_lex_bom() {
+        let source = "\u{feff}new x";
+        let mut lexer = Lexer::new(source);
+
+        let tokens: Vec<Token> = lexer.lexeme();
+
+        assert_eq!(tokens.len(), 3);
+        assert_eq!(tokens[0].body, "new");
+        assert_eq!(tokens[0].span, Span::new(3, 6));
+        assert_eq!(tokens[0].span.slice_and_dice(source), Some("new"));
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_bom_only_at_start() {
+        let mut lexer = Lexer::new("a \u{feff}");
+
+        lexer.lexeme();
+
+        assert_eq!(
+            lexer.errors[0].kind,
+            LexErrorKind::UnexpectedChar('\u{feff}')
+        );
+    }
+
+    #[test]
+    pub fn test_lex_crlf() {
+        let source = ";;; docs\r\nnew x { ; comment\r\n  logic\r\n}\r\n";
+        let mut lexer = Lexer::new(source);
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let bodies: Vec<&str> = tokens.iter().map(|t| t.body).collect();
+
+        assert_eq!(bodies, vec![";;; docs", "new", "x", "{", "logic", "}", ""]);
+        assert_eq!(tokens[4].span.slice_and_dice(source), Some("logic"));
+        assert_eq!(lexer.line, 5);
+    }
+
+    #[test]
+    pub fn test_lex_line_endings_count_once() {
+        for source in ["a\nb\nc", "a\r\nb\r\nc", "a\rb\rc", "a\r\nb\nc"] {
+            let mut lexer = Lexer::new(source);
+
+            assert_eq!(lexer.lexeme().len(), 4);
+            assert_eq!(lexer.line, 3, "{:?}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_lex_lone_cr_ends_comment() {
+        let mut lexer = Lexer::new("; comment\rnew");
+
+        let bodies: Vec<&str> = lexer.lexeme().into_iter().map(|t| t.body).collect();
+
+        assert_eq!(bodies, vec!["new", ""]);
+    }
+
+    #[test]
+    pub fn test_lex_eof() {
+        let mut lexer = Lexer::new("new x");
+        let tokens = lexer.lexeme();
+        let eof = tokens.last().unwrap();
+
+        assert_eq!(eof.ty, Type::Eof);
+        assert_eq!(eof.body, "");
+        assert_eq!(eof.span, Span::new(5, 5));
+
+        let mut empty = Lexer::new("");
+        let tokens = empty.lexeme();
+
+        assert_eq!(tokens.len(), 1);
+        assert_eq!(tokens[0].span, Span::new(0, 0));
+    }
+
+    #[test]
+    pub fn test_lex_tokens_match_lexeme() {
+        let source = "new x { logic :: print \"hi\" 0x1F; } ;;; doc";
+
+        let streamed: Vec<Token> = Lexer::new(source).tokens().map(|t| t.unwrap()).collect();
+        let collected: Vec<Token> = Lexer::new(source).lexeme().into_iter().collect();
+
+        assert_eq!(streamed, collected);
+    }
+
+    #[test]
+    pub fn test_lex_tokens_errors_come_first() {
+        let mut lexer = Lexer::new("a \"\\q\" ` b");
+        let items: Vec<Result<Token, LexError>> = lexer.tokens().collect();
+
+        assert_eq!(items.len(), 6);
+        assert_eq!(items[0].as_ref().unwrap().body, "a");
+        assert_eq!(
+            items[1].as_ref().unwrap_err().kind,
+            LexErrorKind::UnknownEscape('q')
+        );
+        assert_eq!(items[2].as_ref().unwrap().ty, Type::String);
+        assert_eq!(
+            items[3].as_ref().unwrap_err().kind,
+            LexErrorKind::UnexpectedChar('`')
+        );
+        assert_eq!(items[4].as_ref().unwrap().body, "b");
+        assert_eq!(items[5].as_ref().unwrap().ty, Type::Eof);
+
+        // the errors are still collected
+        assert_eq!(lexer.errors.len(), 2);
+    }
+
+    #[test]
+    pub fn test_lex_tokens_are_lazy() {
+        let mut lexer = Lexer::new("a b ` c");
+        let mut tokens = lexer.tokens();
+
+        let result: Result<Vec<Token>, LexError> = tokens.by_ref().collect();
+
+        assert_eq!(result.unwrap_err().kind, LexErrorKind::UnexpectedChar('`'));
+
+        // lexing stopped at the error, `c` is still to come
+        assert_eq!(tokens.next().unwrap().unwrap().body, "c");
+        assert_eq!(tokens.next().unwrap().unwrap().ty, Type::Eof);
+        assert!(tokens.next().is_none());
+    }
+
+    #[test]
+    pub fn test_lex_recovery() {
+        let source = "a ` b @ c \u{7}";
+
+        let mut lexer = Lexer::new_with(source, LexerOptions::new().recover(true));
+        let tokens: Vec<Token> = lexer.lexeme();
+        let errors: Vec<(&str, Span)> = tokens
+            .iter()
+            .filter(|t| t.ty == Type::Error)
+            .map(|t| (t.body, t.span))
+            .collect();
+
+        assert_eq!(
+            errors,
+            vec![
+                ("`", Span::new(2, 3)),
+                ("@", Span::new(6, 7)),
+                ("\u{7}", Span::new(10, 11))
+            ]
+        );
+
+        // every error token lines up with a reported error
+        let spans: Vec<Span> = lexer.errors.iter().map(|e| e.span).collect();
+
+        assert_eq!(spans, errors.iter().map(|e| e.1).collect::<Vec<_>>());
+
+        // and without recovery they're just dropped
+        let mut plain = Lexer::new(source);
+
+        assert_eq!(plain.lexeme().len(), tokens.len() - 3);
+        assert_eq!(plain.errors, lexer.errors);
+    }
+
+    #[test]
+    pub fn test_lex_string_then_delimiter() {
+        let mut lexer = Lexer::new("print(\"hi\")");
+        let tokens: Vec<Token> = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::Ident,
+                &Type::OpenParen,
+                &Type::String,
+                &Type::CloseParen,
+                &Type::Eof
+            ]
+        );
+        assert_eq!(tokens[2].span, Span::new(6, 10));
+    }
+
+    #[test]
+    pub fn test_lex_trivia() {
+        let source = "\u{feff}new x { ; note\r\n  ;* a ;* b *; *;\tlogic ` \"es\\qc\" }\n\"open";
+        let options = LexerOptions::new().trivia(true).recover(true);
+        let mut lexer = Lexer::new_with(source, options);
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::Whitespace,
+                &Type::ReservedKeyword,
+                &Type::Whitespace,
+                &Type::Ident,
+                &Type::Whitespace,
+                &Type::OpenBrace,
+                &Type::Whitespace,
+                &Type::Comment,
+                &Type::Whitespace,
+                &Type::Comment,
+                &Type::Whitespace,
+                &Type::ReservedKeyword,
+                &Type::Whitespace,
+                &Type::Error,
+                &Type::Whitespace,
+                &Type::String,
+                &Type::Whitespace,
+                &Type::CloseBrace,
+                &Type::Whitespace,
+                &Type::Error,
+                &Type::Eof,
+            ]
+        );
+        assert_eq!(tokens[7].body, "; note");
+        assert_eq!(tokens[9].body, ";* a ;* b *; *;");
+        assert_eq!(tokens[19].body, "\"open");
+
+        // every byte is accounted for, in order
+        let rebuilt: String = tokens
+            .iter()
+            .map(|t| match t.ty {
+                Type::String => format!("\"{}\"", t.body),
+                _ => t.body.to_owned(),
+            })
+            .collect();
+
+        assert_eq!(rebuilt, source);
+    }
+
+    #[test]
+    pub fn test_lex_relex() {
+        let old = "new x {\n  logic { ::stdout write \"hi\" 'c' }\n  ;* note *; y\n}";
+        let tokens: Vec<Token> = Lexer::new(old).lexeme();
+
+        let edits = [
+            (Span::new(4, 5), "renamed"),   // inside a token
+            (Span::new(5, 5), "yz"),        // right after one, growing it
+            (Span::new(7, 8), ""),          // a line break
+            (Span::new(34, 36), "there"),   // inside a string
+            (Span::new(45, 45), "\"open "), // opening a string
+            (Span::new(49, 52), "*;"),      // closing a block comment early
+            (Span::new(0, old.len()), "a"), // everything
+        ];
+
+        for (changed, new_text) in edits {
+            let new = format!(
+                "{}{}{}",
+                &old[..changed.start],
+                new_text,
+                &old[changed.end..]
+            );
+
+            let relexed = Lexer::new(&new).relex(&tokens, changed, new_text);
+            let fresh: Vec<Token> = Lexer::new(&new).lexeme();
+
+            assert_eq!(relexed, fresh, "{:?}", new);
+        }
+    }
+
+    #[test]
+    pub fn test_lex_relex_numbers() {
+        // (the old source, what changed, what it changed to)
+        let edits = [
+            ("1..", Span::new(2, 3), "1"),     // `1.1`
+            ("1..10", Span::new(2, 3), ""),    // `1.10`
+            ("x = 1.a", Span::new(6, 7), "5"), // `1.5`
+            ("x = 1. 5", Span::new(6, 7), ""), // `1.5`
+            ("2.5", Span::new(1, 2), "..."),   // `2...5`
+        ];
+
+        for (old, changed, new_text) in edits {
+            let tokens: Vec<Token> = Lexer::new(old).lexeme();
+            let new = format!(
+                "{}{}{}",
+                &old[..changed.start],
+                new_text,
+                &old[changed.end..]
+            );
+
+            let relexed = Lexer::new(&new).relex(&tokens, changed, new_text);
+            let fresh: Vec<Token> = Lexer::new(&new).lexeme();
+
+            assert_eq!(relexed, fresh, "{:?}", new);
+        }
+    }
+
+    #[test]
+    pub fn test_lex_interned_idents() {
         let mut lexer =
-            Lexer::new("; writes\n; basically that's what it does\n\t; so ya\n::write\nnew struct { }".to_string());
+            Lexer::new("for $ as var { ::stdout write var stdout }").with_interner(Interner::new());
+        let tokens: Vec<Token> = lexer.lexeme();
+        let interner = lexer.interner.take().unwrap();
 
-        dbg!(&lexer);
+        let symbols: Vec<&str> = tokens
+            .iter()
+            .filter_map(|t| match t.value {
+                Some(TokenValue::Symbol(symbol)) => Some(interner.resolve(symbol)),
+                _ => None,
+            })
+            .collect();
 
-        let mut binding = lexer.lexeme();
-        dbg!(&binding);
+        // reserved keywords aren't interned, contextual ones are names like any other
+        assert_eq!(
+            symbols,
+            vec!["as", "var", "stdout", "write", "var", "stdout"]
+        );
+        assert_eq!(interner.len(), 4);
+        assert_eq!(tokens[3].value, tokens[8].value);
+    }
+
+    #[test]
+    pub fn test_lex_strict() {
+        let source = "5abc 10 x 0x1F";
+
+        let mut permissive = Lexer::new(source);
+        let mut strict = Lexer::new_with(source, LexerOptions::new().strict(true));
+
+        assert_eq!(permissive.lexeme(), strict.lexeme());
+        assert!(permissive.errors.is_empty());
+        assert_eq!(
+            strict.errors,
+            vec![LexError {
+                kind: LexErrorKind::InvalidDigit('a', Radix::Decimal),
+                span: Span::new(1, 2),
+            }]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_max_token_len() {
+        let options = LexerOptions::new().max_token_len(4);
+        let mut lexer = Lexer::new_with("name longer \"ab\" == \"abcd\"", options);
+
+        let tokens = lexer.lexeme();
+        let spans: Vec<Span> = lexer.errors.iter().map(|e| e.span).collect();
+
+        assert_eq!(tokens.len(), 6);
+        assert_eq!(spans, vec![Span::new(5, 11), Span::new(20, 26)]);
+        assert_eq!(lexer.errors[0].kind, LexErrorKind::TokenTooLong(4));
+    }
+
+    #[test]
+    pub fn test_lex_shebang() {
+        for source in [
+            "#!/usr/bin/env newton\nnew x",
+            "\u{feff}#!/usr/bin/env newton\r\nnew x",
+        ] {
+            let mut lexer = Lexer::new(source);
+            let bodies: Vec<&str> = lexer.lexeme().into_iter().map(|t| t.body).collect();
+
+            assert_eq!(bodies, vec!["new", "x", ""]);
+            assert!(lexer.errors.is_empty());
+        }
+
+        // only on the first line
+        let mut lexer = Lexer::new("new x\n#!/usr/bin/env newton");
+
+        lexer.lexeme();
+
+        assert_eq!(lexer.errors[0].kind, LexErrorKind::UnexpectedChar('#'));
+
+        // and kept as trivia
+        let mut lexer = Lexer::new_with("#!newton\nx", LexerOptions::new().trivia(true));
+        let tokens = lexer.lexeme();
+
+        assert_eq!(tokens[0].ty, Type::Comment);
+        assert_eq!(tokens[0].body, "#!newton");
+    }
+
+    #[test]
+    pub fn test_token_render() {
+        let mut map = SourceMap::new();
+        let file = map.add("main.newton", "new x {\n  logic \"a\\tb\"\n}");
+        let source = map.file(file);
+
+        let lines: Vec<String> = Lexer::new(&source.text)
+            .lexeme()
+            .iter()
+            .map(|t| t.render(source))
+            .collect();
+
+        assert_eq!(lines[3], r#"2:3     ReservedKeyword   "logic""#);
+        assert_eq!(lines[4], r#"2:9     String            "\"a\\tb\"""#);
+        assert_eq!(lines[5], r#"3:1     CloseBrace        "}""#);
+        assert_eq!(lines[6], r#"3:2     Eof               """#);
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    pub fn test_serde_round_trip() {
+        let mut lexer = Lexer::new("new x { logic 0x1F 'c' \"a b\" ` }");
+        let tokens = lexer.lexeme();
+
+        let json = serde_json::to_string(&tokens).unwrap();
+        let back: Vec<Token> = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back, tokens);
+
+        let json = serde_json::to_string(&lexer.errors).unwrap();
+        let back: Vec<LexError> = serde_json::from_str(&json).unwrap();
 
-        assert_eq!(binding.len(), 5);
+        assert_eq!(back, lexer.errors);
+    }
+
+    #[test]
+    pub fn test_lex_unary_minus() {
+        let cases = [
+            ("-1", vec![true]),
+            ("a - 1", vec![false]),
+            ("a -1", vec![false]),
+            ("(-1)", vec![true]),
+            ("f(1, -2)", vec![true]),
+            ("xs[0] - -x", vec![false, true]),
+            ("$ - 1", vec![false]),
+            ("x = - 1", vec![true]),
+            ("} -1", vec![true]),
+        ];
+
+        for (source, expected) in cases {
+            let tokens = Lexer::new(source).lexeme();
+            let unary: Vec<bool> = tokens
+                .iter()
+                .enumerate()
+                .filter(|(_, t)| t.ty == Type::Minus)
+                .map(|(i, t)| t.is_unary(i.checked_sub(1).map(|p| &tokens[p])))
+                .collect();
+
+            assert_eq!(unary, expected, "{}", source);
+        }
+
+        // only a unary minus touching its number makes a literal
+        let tokens = Lexer::new("-1 - 2").lexeme();
+
+        assert!(tokens[0].touches(&tokens[1]));
+        assert!(!tokens[2].touches(&tokens[3]));
+    }
+
+    #[test]
+    pub fn test_lex_colons() {
+        let mut lexer = Lexer::new("a: b ::stdout $::1 :: ::: x:y");
+        let tokens: Vec<(Type, &str)> =
+            lexer.lexeme().into_iter().map(|t| (t.ty, t.body)).collect();
+
+        assert_eq!(
+            tokens,
+            vec![
+                (Type::Ident, "a"),
+                (Type::Colon, ":"),
+                (Type::Ident, "b"),
+                (Type::MemberAccess, "::"),
+                (Type::Ident, "stdout"),
+                (Type::Sigil, "$"),
+                (Type::MemberAccess, "::"),
+                (Type::Number, "1"),
+                (Type::MemberAccess, "::"),
+                (Type::MemberAccess, "::"),
+                (Type::Colon, ":"),
+                (Type::Ident, "x"),
+                (Type::Colon, ":"),
+                (Type::Ident, "y"),
+                (Type::Eof, ""),
+            ]
+        );
+        assert!(lexer.errors.is_empty());
+    }
+
+    #[test]
+    pub fn test_lex_contextual_keywords() {
+        let options = LexerOptions::new().contextual_keyword("into");
+        let mut lexer = Lexer::new_with("collect as $\nfor $ as as { into }", options);
+        let tokens = lexer.lexeme();
+
+        assert_eq!(tokens[1].ty, Type::Ident);
+        assert!(tokens[1].is_contextual("as"));
+        assert!(tokens[5].is_contextual("as"));
+        assert!(tokens[6].is_contextual("as")); // a name that happens to be `as`
+        assert!(tokens[8].is_contextual("into"));
+
+        assert!(lexer.options.is_contextual_keyword("as"));
+        assert!(lexer.options.is_contextual_keyword("into"));
+        assert!(!lexer.options.is_contextual_keyword("collect"));
+        assert!(!lexer.options.is_keyword("as"));
+    }
+
+    #[test]
+    pub fn test_lex_many() {
+        let dir = std::env::temp_dir().join(format!("newton-lex-many-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let paths: Vec<_> = ["new a { }", "new b { ` }", "new c"]
+            .iter()
+            .enumerate()
+            .map(|(i, source)| {
+                let path = dir.join(format!("{}.newton", i));
+                std::fs::write(&path, source).unwrap();
+                path
+            })
+            .collect();
+
+        let mut map = SourceMap::new();
+        let files = map.load_all(&paths).unwrap();
+        let lexed = lex_many(&map, &files);
+
+        assert_eq!(lexed.len(), 3);
+        assert_eq!(lexed[0].1.as_ref().unwrap()[1].body, "a");
+        assert_eq!(
+            lexed[1].1.as_ref().unwrap_err()[0].kind,
+            LexErrorKind::UnexpectedChar('`')
+        );
+        assert_eq!(lexed[2].1.as_ref().unwrap().len(), 3);
+
+        for ((file, _), path) in lexed.iter().zip(&paths) {
+            assert_eq!(&map.file(*file).path, path);
+        }
+
+        let missing = [paths[0].clone(), dir.join("missing.newton")];
+
+        assert!(SourceMap::new().load_all(&missing).is_err());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_lex_into_sink() {
+        let mut lexer = Lexer::new("` a \"\\q\" @");
+        lexer.next_token(); // finds the ` before the sink is there
+
+        let mut spans = Vec::new();
+        let tokens = lexer.lexeme_into(&mut |d: Diagnostic| spans.push(d.span));
+
+        assert_eq!(tokens.len(), 2); // the string and the `Eof`
+        assert_eq!(spans, vec![Span::new(5, 7), Span::new(9, 10)]);
+        assert_eq!(lexer.errors.len(), 3);
+    }
+
+    #[test]
+    pub fn test_lex_assign_operators() {
+        let mut lexer = Lexer::new("a += 1 -= 2 *= 3 /= 4 %= 5 %=x");
+
+        let tokens: Vec<Token> = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::Ident,
+                &Type::PlusEqual,
+                &Type::Number,
+                &Type::MinusEqual,
+                &Type::Number,
+                &Type::MultiplyEqual,
+                &Type::Number,
+                &Type::DivideEqual,
+                &Type::Number,
+                &Type::ModuloEqual,
+                &Type::Number,
+                &Type::ModuloEqual,
+                &Type::Ident,
+                &Type::Eof,
+            ]
+        );
+
+        assert_eq!(tokens[3].body, "-=");
+        assert_eq!(tokens[3].span, Span::new(7, 9));
+    }
+
+    #[test]
+    pub fn test_lex_dot() {
+        let mut lexer = Lexer::new("p.x 1.5");
+        let tokens = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::Ident,
+                &Type::Dot,
+                &Type::Ident,
+                &Type::Number,
+                &Type::Eof,
+            ]
+        );
+        assert_eq!(tokens[3].body, "1.5"); // a point between digits is still a number
+    }
+
+    #[test]
+    pub fn test_lex_question() {
+        let mut lexer = Lexer::new("a ? b : c");
+        let types: Vec<Type> = lexer.lexeme().into_iter().map(|t| t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                Type::Ident,
+                Type::Question,
+                Type::Ident,
+                Type::Colon,
+                Type::Ident,
+                Type::Eof,
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_lex_ranges() {
+        let mut lexer = Lexer::new("1..10 1..=2.5 x..");
+        let tokens = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert!(lexer.errors.is_empty(), "{:?}", lexer.errors);
+        assert_eq!(
+            types,
+            vec![
+                &Type::Number,
+                &Type::DotDot,
+                &Type::Number,
+                &Type::Number,
+                &Type::DotDotEqual,
+                &Type::Number,
+                &Type::Ident,
+                &Type::DotDot,
+                &Type::Eof,
+            ]
+        );
+        assert_eq!(tokens[0].value, Some(TokenValue::Number(1.0)));
+        assert_eq!(tokens[4].span, Span::new(7, 10));
+        assert_eq!(tokens[5].body, "2.5");
+    }
+
+    #[test]
+    pub fn test_lex_open_map() {
+        let mut lexer = Lexer::new("#{ \"a\": 1 } { }");
+        let tokens = lexer.lexeme();
+        let types: Vec<&Type> = tokens.iter().map(|t| &t.ty).collect();
+
+        assert_eq!(
+            types,
+            vec![
+                &Type::OpenMap,
+                &Type::String,
+                &Type::Colon,
+                &Type::Number,
+                &Type::CloseBrace,
+                &Type::OpenBrace,
+                &Type::CloseBrace,
+                &Type::Eof,
+            ]
+        );
+        assert_eq!(tokens[0].span, Span::new(0, 2));
+
+        // a `#` on its own is still unexpected
+        let mut lexer = Lexer::new("# {");
+
+        lexer.lexeme();
+
+        assert_eq!(lexer.errors[0].kind, LexErrorKind::UnexpectedChar('#'));
+    }
+
+    #[test]
+    pub fn test_lex_peek_n() {
+        let mut lexer = Lexer::new("abéd");
+
+        assert_eq!(lexer.peek_n(1), Some('a'));
+
+        lexer.advance();
+
+        assert_eq!(lexer.peek_n(0), Some('a'));
+        assert_eq!(lexer.peek_n(1), lexer.peek());
+        assert_eq!(lexer.peek_n(2), Some('é'));
+        assert_eq!(lexer.peek_n(3), Some('d'));
+        assert_eq!(lexer.peek_n(4), None);
+        assert_eq!(lexer.cur(), Some('a'));
+    }
+
+    #[test]
+    pub fn test_lex_checkpoint_rollback() {
+        let mut lexer = Lexer::new("new x\n` y\nz");
+
+        lexer.next_token();
+
+        let saved = lexer.checkpoint();
+        let ahead: Vec<&str> = std::iter::from_fn(|| lexer.next_token())
+            .map(|t| t.body)
+            .collect();
+
+        assert_eq!(ahead, vec!["x", "y", "z", ""]);
+        assert_eq!(lexer.errors.len(), 1);
+        assert_eq!(lexer.line, 3);
+
+        lexer.rollback(saved);
+
+        assert!(lexer.errors.is_empty());
+        assert_eq!(lexer.line, 1);
+        assert_eq!(lexer.next_token().unwrap().body, "x");
+        assert_eq!(lexer.next_token().unwrap().body, "y");
+        assert_eq!(lexer.errors.len(), 1);
+    }
+
+    #[test]
+    pub fn test_lex_from_reader() {
+        let source = "t = \"\"\"\nline one\nline two\nline three\n\"\"\"\ns = \"one\ntwo\nthree\nfour {n}\"\nnew x {\n  logic \"a\nb\" ;* one\ntwo *; y\n  0xFF é\n}\n` z";
+        let mut lexer = Lexer::new(source);
+        let collected: Vec<(Type, String, Span)> = lexer
+            .lexeme()
+            .into_iter()
+            .map(|t| (t.ty, t.body.to_owned(), t.span))
+            .collect();
+
+        // from small enough to split every construct, to the whole file in one
+        for chunk_size in [1, 3, 4, 8, 16, 64 * 1024] {
+            let mut stream = Lexer::from_reader(source.as_bytes());
+            let mut streamed = Vec::new();
+
+            stream.chunk_size = chunk_size;
+
+            while let Some(tokens) = stream.next_chunk().unwrap() {
+                streamed.extend(
+                    tokens
+                        .into_iter()
+                        .map(|t| (t.ty, t.body.to_owned(), t.span)),
+                );
+            }
+
+            assert_eq!(streamed, collected, "{}", chunk_size);
+            assert_eq!(stream.errors, lexer.errors, "{}", chunk_size);
+        }
+    }
+
+    #[test]
+    pub fn test_lex_from_reader_stream_start() {
+        // each line is a chunk of its own, so the `#!` and the BOM after the first start one
+        let source = "#!a\nb\n#!c\n\u{feff}d\n";
+
+        for trivia in [false, true] {
+            let options = LexerOptions::new().trivia(trivia);
+            let mut stream = Lexer::from_reader_with(source.as_bytes(), options.clone());
+
+            stream.chunk_size = 2;
+
+            let mut streamed = Vec::new();
+
+            while let Some(tokens) = stream.next_chunk().unwrap() {
+                streamed.extend(tokens.into_iter().map(|t| (t.ty, t.span)));
+            }
+
+            let mut lexer = Lexer::new_with(source, options);
+            let collected: Vec<(Type, Span)> =
+                lexer.lexeme().into_iter().map(|t| (t.ty, t.span)).collect();
+
+            assert_eq!(streamed, collected, "{}", trivia);
+            assert_eq!(stream.errors, lexer.errors, "{}", trivia);
+            assert!(!stream.errors.is_empty());
+        }
+    }
+
+    #[test]
+    pub fn test_lex_from_reader_bad_utf8() {
+        let mut stream = Lexer::from_reader(&[b'a', b'\n', 0xFF, b'\n'][..]);
+
+        stream.chunk_size = 1;
 
-        let first_token = binding.get_mut(0).unwrap().as_mut().unwrap();
+        assert_eq!(stream.next_chunk().unwrap().unwrap()[0].body, "a");
+        assert!(stream.next_chunk().is_err());
+    }
+}
+
+#[cfg(test)]
+mod property_tests {
+    use super::*;
+
+    /// characters that are likely to trip the lexer up
+    const POOL: &str = "aZ_09exbor \t\n\r\"'\\{}()[]:;*#/%$!=<>&|+-.,@^~`?\0\u{7}\u{1b}\u{feff}\
+                        éß😀🦀中文。、「」٣½\u{200b}\u{10ffff}";
+
+    /// a tiny xorshift generator, so runs are reproducible without extra dependencies
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn string(&mut self, len: usize) -> String {
+            let pool: Vec<char> = POOL.chars().collect();
+
+            (0..len)
+                .map(|_| pool[self.next() as usize % pool.len()])
+                .collect()
+        }
+    }
+
+    /// lexes `source`, checking that every span stays inside it
+    fn check(source: &str) {
+        let mut lexer = Lexer::new(source);
+
+        for token in lexer.lexeme() {
+            assert!(
+                token.span.end <= source.len(),
+                "{:?} in {:?}",
+                token,
+                source
+            );
+            assert!(source.is_char_boundary(token.span.start), "{:?}", token);
+            assert!(source.is_char_boundary(token.span.end), "{:?}", token);
+        }
 
-        assert_eq!(first_token.body, "hello");
+        for error in &lexer.errors {
+            assert!(
+                error.span.end <= source.len(),
+                "{:?} in {:?}",
+                error,
+                source
+            );
+            assert!(source.is_char_boundary(error.span.start), "{:?}", error);
+            assert!(source.is_char_boundary(error.span.end), "{:?}", error);
+        }
+    }
+
+    #[test]
+    pub fn test_random_input_never_panics() {
+        let mut rng = Rng(0x5EED_0F2E_570A);
+
+        for round in 0..5_000 {
+            let len = (rng.next() % 32) as usize;
+            let source = rng.string(len + round % 4);
+
+            check(&source);
+        }
+    }
+
+    #[test]
+    pub fn test_weird_input_is_reported() {
+        for source in ["@", "中文。", "😀", "\"unterminated", "\u{7}new"] {
+            let mut lexer = Lexer::new(source);
+
+            lexer.lexeme();
+
+            assert!(!lexer.errors.is_empty(), "{:?}", source);
+        }
+    }
+
+    #[test]
+    pub fn test_unexpected_char_span() {
+        let mut lexer = Lexer::new("a 😀 b");
 
-        let second_token = binding.get_mut(1).unwrap().as_mut().unwrap();
+        let bodies: Vec<&str> = lexer.lexeme().into_iter().map(|t| t.body).collect();
 
-        assert_eq!(second_token.body, "\"world");
+        assert_eq!(bodies, vec!["a", "b", ""]);
+        assert_eq!(
+            lexer.errors,
+            vec![LexError {
+                kind: LexErrorKind::UnexpectedChar('😀'),
+                span: Span::new(2, 6),
+            }]
+        );
     }
 }
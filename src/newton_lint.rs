@@ -0,0 +1,275 @@
+//! # Newton Lint Levels
+//!
+//! Which warnings a user wants to see. Each code can be allowed, which hides it, warned
+//! about, the default, or denied, which makes it an error. The host sets levels through
+//! `LintLevels`, and a file can set its own with a directive:
+//!
+//! ```text
+//! %allow N0042
+//! %deny N0043
+//!
+//! new hello_world { ... }
+//! ```
+//!
+//! The file has the last word, its directives are read after the host's levels. Errors stay
+//! errors whatever the level, only warnings and below can be hidden.
+//!
+//! ```
+//! # use newton::newton_diag::Diagnostic;
+//! # use newton::newton_lex::{Lexer, Span};
+//! # use newton::newton_lint::LintLevels;
+//! let tokens = Lexer::new("%deny N0042\nnew x { }").lexeme();
+//! let levels = LintLevels::new().allow("N0043").directives(&tokens);
+//!
+//! let pedantic = Diagnostic::warning("pedantic", Span::new(0, 1)).with_code("N0043");
+//! let serious = Diagnostic::warning("serious", Span::new(0, 1)).with_code("N0042");
+//!
+//! assert_eq!(levels.apply(pedantic), None);
+//! assert!(levels.apply(serious).unwrap().is_error());
+//! ```
+
+use std::collections::HashMap;
+
+use crate::newton_diag::{Diagnostic, DiagnosticSink, Severity};
+use crate::newton_lex::{Token, Type};
+
+/// # Levels
+///
+/// What to do with a diagnostic of some code.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Level {
+    Allow, // hide it
+    Warn,  // show it as a warning
+    Deny,  // make it an error
+}
+
+impl Level {
+    /// the level a name stands for, as in `%allow`, `None` if it isn't one
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "allow" => Some(Level::Allow),
+            "warn" => Some(Level::Warn),
+            "deny" => Some(Level::Deny),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Level {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Level::Allow => write!(f, "allow"),
+            Level::Warn => write!(f, "warn"),
+            Level::Deny => write!(f, "deny"),
+        }
+    }
+}
+
+/// # Lint Levels
+///
+/// The level of every code that has one. Codes without a level are left as they are.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct LintLevels {
+    levels: HashMap<String, Level>, // code -> level
+}
+
+impl LintLevels {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// hides diagnostics with `code`
+    pub fn allow(mut self, code: impl Into<String>) -> Self {
+        self.set(code, Level::Allow);
+        self
+    }
+
+    /// shows diagnostics with `code` as warnings
+    pub fn warn(mut self, code: impl Into<String>) -> Self {
+        self.set(code, Level::Warn);
+        self
+    }
+
+    /// makes diagnostics with `code` errors
+    pub fn deny(mut self, code: impl Into<String>) -> Self {
+        self.set(code, Level::Deny);
+        self
+    }
+
+    /// sets the level of `code`, replacing any it had
+    pub fn set(&mut self, code: impl Into<String>, level: Level) {
+        self.levels.insert(code.into(), level);
+    }
+
+    /// the level of `code`, if it has one
+    pub fn level(&self, code: &str) -> Option<Level> {
+        self.levels.get(code).copied()
+    }
+
+    /// # Directives
+    ///
+    /// Reads `%allow`, `%warn`, and `%deny` directives from a file's tokens, each followed by
+    /// the code it's for. Later directives win over earlier ones and over the levels set
+    /// before, and a directive without a code after it is ignored, like any other directive.
+    pub fn directives(mut self, tokens: &[Token]) -> Self {
+        let mut tokens = tokens.iter().filter(|t| !t.ty.is_trivia()).peekable();
+
+        while let Some(token) = tokens.next() {
+            let Some(level) = (token.ty == Type::Directive)
+                .then(|| Level::from_name(&token.body[1..]))
+                .flatten()
+            else {
+                continue;
+            };
+
+            if let Some(code) = tokens.next_if(|t| t.ty == Type::Ident) {
+                self.set(code.body, level);
+            }
+        }
+
+        self
+    }
+
+    /// the diagnostic at the level of its code, `None` if it's allowed
+    ///
+    /// Errors, and diagnostics without a code, come back as they are.
+    pub fn apply(&self, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        if diagnostic.is_error() {
+            return Some(diagnostic);
+        }
+
+        match diagnostic.code.as_deref().and_then(|code| self.level(code)) {
+            Some(Level::Allow) => return None,
+            Some(Level::Warn) => diagnostic.severity = Severity::Warning,
+            Some(Level::Deny) => diagnostic.severity = Severity::Error,
+            None => {}
+        }
+
+        Some(diagnostic)
+    }
+
+    /// `apply` to every diagnostic, dropping the allowed ones
+    pub fn apply_all(&self, diagnostics: Vec<Diagnostic>) -> Vec<Diagnostic> {
+        diagnostics
+            .into_iter()
+            .filter_map(|d| self.apply(d))
+            .collect()
+    }
+
+    /// a sink that applies the levels on the way into `sink`
+    pub fn sink<S: DiagnosticSink>(&self, sink: S) -> Leveled<'_, S> {
+        Leveled { levels: self, sink }
+    }
+}
+
+/// # Leveled Sinks
+///
+/// A sink that puts diagnostics at their level before handing them on, from
+/// [`LintLevels::sink`]. Allowed ones never reach the inner sink.
+pub struct Leveled<'lvl, S> {
+    levels: &'lvl LintLevels, // the levels to apply
+    pub sink: S,              // where the diagnostics go
+}
+
+impl<S: DiagnosticSink> DiagnosticSink for Leveled<'_, S> {
+    fn report(&mut self, diagnostic: Diagnostic) {
+        if let Some(diagnostic) = self.levels.apply(diagnostic) {
+            self.sink.report(diagnostic);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_lex::{Lexer, LexerOptions, Span};
+    use crate::newton_parse::parse_source;
+
+    fn coded(severity: Severity, code: &str) -> Diagnostic {
+        Diagnostic::new(severity, "something", Span::new(0, 1)).with_code(code)
+    }
+
+    #[test]
+    pub fn test_lint_apply() {
+        let levels = LintLevels::new()
+            .allow("N1")
+            .deny("N2")
+            .warn("N3")
+            .allow("N4");
+
+        assert_eq!(levels.apply(coded(Severity::Warning, "N1")), None);
+        assert_eq!(
+            levels
+                .apply(coded(Severity::Warning, "N2"))
+                .unwrap()
+                .severity,
+            Severity::Error
+        );
+        assert_eq!(
+            levels.apply(coded(Severity::Note, "N3")).unwrap().severity,
+            Severity::Warning
+        );
+        assert_eq!(
+            levels
+                .apply(coded(Severity::Warning, "N9"))
+                .unwrap()
+                .severity,
+            Severity::Warning
+        );
+
+        // errors stay errors
+        assert!(levels
+            .apply(coded(Severity::Error, "N4"))
+            .unwrap()
+            .is_error());
+
+        let kept = levels.apply_all(vec![
+            coded(Severity::Warning, "N1"),
+            coded(Severity::Warning, "N2"),
+            Diagnostic::warning("no code", Span::new(0, 1)),
+        ]);
+
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    pub fn test_lint_sink() {
+        let levels = LintLevels::new().allow("N1").deny("N2");
+        let mut sink = levels.sink(Vec::new());
+
+        sink.report(coded(Severity::Warning, "N1"));
+        sink.report(coded(Severity::Warning, "N2"));
+        sink.report(coded(Severity::Note, "N3"));
+
+        assert_eq!(sink.sink.len(), 2);
+        assert!(sink.sink[0].is_error());
+    }
+
+    #[test]
+    pub fn test_lint_directives() {
+        let source = "%allow N1 ; a comment\n%deny\n%warn N2\n%override N3\n%deny N1";
+        let tokens = Lexer::new_with(source, LexerOptions::new().trivia(true)).lexeme();
+        let levels = LintLevels::new().allow("N2").directives(&tokens);
+
+        assert_eq!(levels.level("N1"), Some(Level::Deny)); // the later directive wins
+        assert_eq!(levels.level("N2"), Some(Level::Warn)); // over the host too
+        assert_eq!(levels.level("N3"), None);
+        assert_eq!(Level::from_name("forbid"), None);
+        assert_eq!(Level::Deny.to_string(), "deny");
+    }
+
+    #[test]
+    pub fn test_lint_directives_parse() {
+        let source = "%allow N0042\n%deny N0043\n%warn\nnew x { logic { } }\n%allow N0044";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(program.items.len(), 1);
+
+        // anything else is still only for conditions
+        let (_, diagnostics) = parse_source("%override\nnew x { }");
+
+        assert_eq!(diagnostics.len(), 1);
+    }
+}
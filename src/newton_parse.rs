@@ -0,0 +1,4351 @@
+//! # Newton Parser
+//!
+//! Turns tokens into structure, a `newton_ast::Program`. `Parser` does the parsing, on top
+//! of a `TokenStream`, a cursor over the lexed tokens with the few moves every
+//! recursive-descent parser needs.
+//!
+//! ```
+//! # use newton::newton_ast::Item;
+//! # use newton::newton_lex::Lexer;
+//! # use newton::newton_parse::parse;
+//! let (program, diagnostics) = parse(Lexer::new("new hello_world { logic { } }").lexeme());
+//!
+//! let Item::Block(block) = &program.items[0] else { panic!() };
+//!
+//! assert_eq!(block.name.name, "hello_world");
+//! assert!(diagnostics.is_empty());
+//! ```
+//!
+//! Statements end at line breaks, which the parser only sees in tokens lexed with
+//! `LexerOptions::trivia` on. `parse_source` does that, and reports lex errors too.
+//!
+//! ```
+//! # use newton::newton_lex::{Lexer, Type};
+//! # use newton::newton_parse::TokenStream;
+//! let mut stream = TokenStream::new(Lexer::new("new hello_world { }").lexeme());
+//!
+//! stream.expect(Type::ReservedKeyword).unwrap();
+//! let name = stream.expect(Type::Ident).unwrap().body;
+//!
+//! assert_eq!(name, "hello_world");
+//! assert!(stream.eat(Type::OpenBrace).is_some());
+//! assert!(stream.expect(Type::Comma).is_err());
+//! ```
+
+use crate::newton_ast::{
+    Assign, BinOp, BlockDecl, Call, CollectStmt, Condition, ConditionKind, ConditionsBlock, Doc,
+    Else, EnumDecl, Expr, ExprKind, FnDecl, ForStmt, Ident, IfStmt, Include, Item, Lambda, LetStmt,
+    LogicBlock, MapEntry, Match, MatchArm, Pattern, PatternKind, Program, Section, Sigil, Spanned,
+    Stmt, StmtKind, StringPart, StructDecl, UnaryOp, Use, WhileStmt,
+};
+use crate::newton_diag::{Applicability, Diagnostic, DiagnosticSink};
+use crate::newton_lex::{Lexer, LexerOptions, Segment, Span, Token, TokenValue, Type};
+use crate::newton_lint::Level;
+use crate::newton_visit::Folder;
+
+/// # Parse Errors
+///
+/// Something the parser didn't expect, and where.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParseError {
+    pub kind: ParseErrorKind, // what went wrong
+    pub span: Span,           // where it went wrong
+}
+
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum ParseErrorKind {
+    Expected { expected: Type, found: Type }, // `new {`, expected an `Ident`
+    ExpectedItem { found: Type }, // something other than `new`, `fn`, `include`, or `use`
+    ExpectedSection { found: Type }, // something other than `conditions` or `logic`
+    ExpectedCondition { found: Type }, // a `conditions` entry that isn't one
+    ExpectedStmt { found: Type }, // a `logic` entry that isn't a statement
+    ExpectedExpr { found: Type }, // `for { }`, nothing to loop over
+    ExpectedKeyword { keyword: String, found: Type }, // `collect $`, expected `as`
+    NotAssignable,                // `1 = x`, nowhere to keep the value
+    ExpectedPattern { found: Type }, // a match arm that doesn't start with one
+    NotOnStrings { op: BinOp },   // `"a" - 1`, an operator strings don't have
+    UnclosedParen,                // `(1 + 2`, at the `(`
+    TooDeep { max: usize },       // nested past `ParserOptions::max_depth`
+}
+
+impl ParseErrorKind {
+    /// the stable error code, see `newton_codes`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ParseErrorKind::Expected { .. } => "N0100",
+            ParseErrorKind::ExpectedItem { .. } => "N0101",
+            ParseErrorKind::ExpectedSection { .. } => "N0102",
+            ParseErrorKind::ExpectedCondition { .. } => "N0103",
+            ParseErrorKind::ExpectedStmt { .. } => "N0104",
+            ParseErrorKind::ExpectedExpr { .. } => "N0105",
+            ParseErrorKind::ExpectedKeyword { .. } => "N0100",
+            ParseErrorKind::NotAssignable => "N0106",
+            ParseErrorKind::ExpectedPattern { .. } => "N0107",
+            ParseErrorKind::NotOnStrings { .. } => "N0108",
+            ParseErrorKind::UnclosedParen => "N0109",
+            ParseErrorKind::TooDeep { .. } => "N0110",
+        }
+    }
+}
+
+impl std::fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseErrorKind::Expected { expected, found } => {
+                write!(f, "expected {}, found {}", expected, found)
+            }
+            ParseErrorKind::ExpectedItem { found } => {
+                write!(
+                    f,
+                    "expected `new`, `fn`, `include`, or `use`, found {}",
+                    found
+                )
+            }
+            ParseErrorKind::ExpectedSection { found } => {
+                write!(f, "expected `conditions` or `logic`, found {}", found)
+            }
+            ParseErrorKind::ExpectedCondition { found } => {
+                write!(f, "expected a condition, found {}", found)
+            }
+            ParseErrorKind::ExpectedStmt { found } => {
+                write!(f, "expected a statement, found {}", found)
+            }
+            ParseErrorKind::ExpectedExpr { found } => {
+                write!(f, "expected an expression, found {}", found)
+            }
+            ParseErrorKind::ExpectedKeyword { keyword, found } => {
+                write!(f, "expected `{}`, found {}", keyword, found)
+            }
+            ParseErrorKind::NotAssignable => write!(f, "can't assign to this expression"),
+            ParseErrorKind::ExpectedPattern { found } => {
+                write!(f, "expected a pattern, found {}", found)
+            }
+            ParseErrorKind::NotOnStrings { op } => {
+                write!(f, "can't use `{}` on a string", op.symbol())
+            }
+            ParseErrorKind::UnclosedParen => write!(f, "unclosed parenthesis"),
+            ParseErrorKind::TooDeep { .. } => write!(f, "program too deeply nested"),
+        }
+    }
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.kind, self.span)
+    }
+}
+
+/// # Token Stream
+///
+/// The tokens of a file, and how far the parser got through them.
+///
+/// Trivia and doc comments are dropped on the way in, all but where the line breaks were,
+/// see `at_line_start`, and which doc comments came before each token, see `docs`. The
+/// stream always ends with an `Eof`, one is added if the tokens don't have it, and the
+/// cursor never moves past it, so looking ahead never fails.
+///
+/// Besides the cursor, the stream remembers the span of the last token it moved past, so a
+/// node's span is the span of its first token joined with `prev_span` once it's parsed.
+#[derive(Debug, PartialEq)]
+pub struct TokenStream<'src> {
+    tokens: Vec<Token<'src>>,    // the tokens, ending with `Eof`
+    breaks: Vec<bool>,           // if a line break comes before each token
+    docs: Vec<Vec<Token<'src>>>, // the doc comments right before each token
+    pos: usize,                  // the index of the next token
+    prev: Span,                  // the span of the last token moved past
+}
+
+impl<'src> TokenStream<'src> {
+    pub fn new(all: Vec<Token<'src>>) -> Self {
+        let mut tokens = Vec::with_capacity(all.len());
+        let mut breaks = Vec::with_capacity(all.len());
+        let mut docs = Vec::with_capacity(all.len());
+        let mut line_break = false;
+        let mut doc = Vec::new();
+
+        for token in all {
+            match token.ty {
+                Type::Whitespace | Type::Comment => {
+                    line_break |= token.body.contains(['\n', '\r']);
+                }
+                Type::DocComment => doc.push(token),
+                _ => {
+                    tokens.push(token);
+                    breaks.push(std::mem::take(&mut line_break));
+                    docs.push(std::mem::take(&mut doc));
+                }
+            }
+        }
+
+        if tokens.last().is_none_or(|t| t.ty != Type::Eof) {
+            let end = tokens.last().map_or(0, |t| t.span.end);
+
+            tokens.push(Token {
+                ty: Type::Eof,
+                body: "",
+                span: Span::new(end, end),
+                value: None,
+            });
+            breaks.push(line_break);
+            docs.push(doc);
+        }
+
+        Self {
+            tokens,
+            breaks,
+            docs,
+            pos: 0,
+            prev: Span::new(0, 0),
+        }
+    }
+
+    /// the doc comment lines between the last token moved past and the next one, in order
+    pub fn docs(&self) -> &[Token<'src>] {
+        &self.docs[self.pos]
+    }
+
+    /// if a line break comes between the last token moved past and the next one
+    ///
+    /// Only tokens from a lexer in trivia mode know where the line breaks are, without them
+    /// this is always `false`.
+    pub fn at_line_start(&self) -> bool {
+        self.breaks[self.pos]
+    }
+
+    /// if the next token starts right where the last one moved past ends
+    pub fn touching(&self) -> bool {
+        self.pos > 0 && self.prev.end == self.peek().span.start
+    }
+
+    /// the next token, without moving
+    pub fn peek(&self) -> &Token<'src> {
+        self.peek_n(0)
+    }
+
+    /// looks `k` tokens past the next one, `peek_n(0)` is `peek()`
+    ///
+    /// Anything past the end is the `Eof`.
+    pub fn peek_n(&self, k: usize) -> &Token<'src> {
+        let last = self.tokens.len() - 1;
+
+        &self.tokens[(self.pos + k).min(last)]
+    }
+
+    /// how many tokens the cursor has moved past, to tell if parsing something got anywhere
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// if the next token is a `ty`
+    pub fn at(&self, ty: Type) -> bool {
+        self.peek().ty == ty
+    }
+
+    /// if the next token is the reserved keyword `word`
+    pub fn at_keyword(&self, word: &str) -> bool {
+        self.peek().is_keyword(word)
+    }
+
+    /// if only the `Eof` is left
+    pub fn is_eof(&self) -> bool {
+        self.at(Type::Eof)
+    }
+
+    /// moves past the next token and hands it over, staying put on the `Eof`
+    pub fn bump(&mut self) -> &Token<'src> {
+        let index = self.pos;
+
+        if index < self.tokens.len() - 1 {
+            self.pos += 1;
+        }
+
+        self.prev = self.tokens[index].span;
+
+        &self.tokens[index]
+    }
+
+    /// moves past the next token if it's a `ty`
+    pub fn eat(&mut self, ty: Type) -> Option<&Token<'src>> {
+        match self.at(ty) {
+            true => Some(self.bump()),
+            false => None,
+        }
+    }
+
+    /// moves past the next token if it's a `ty`, and reports it otherwise, without moving
+    pub fn expect(&mut self, ty: Type) -> Result<&Token<'src>, ParseError> {
+        if !self.at(ty.clone()) {
+            let found = self.peek();
+
+            return Err(ParseError {
+                kind: ParseErrorKind::Expected {
+                    expected: ty,
+                    found: found.ty.clone(),
+                },
+                span: found.span,
+            });
+        }
+
+        Ok(self.bump())
+    }
+
+    /// the span of the last token moved past, empty at the start
+    pub fn prev_span(&self) -> Span {
+        self.prev
+    }
+
+    /// from `start` up to the end of the last token moved past, e.g. the span of a node that
+    /// started at `start`
+    pub fn span_from(&self, start: Span) -> Span {
+        start.join(self.prev)
+    }
+}
+
+/// the default `ParserOptions::max_depth`
+pub const MAX_DEPTH: usize = 64;
+
+/// # Parser Options
+///
+/// How the parser behaves, the builders chain like `LexerOptions`'s.
+///
+/// ```
+/// # use newton::newton_parse::ParserOptions;
+/// let options = ParserOptions::new().max_depth(32);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ParserOptions {
+    pub max_depth: usize, // how deep blocks, expressions, and patterns may nest
+}
+
+impl Default for ParserOptions {
+    fn default() -> Self {
+        Self {
+            max_depth: MAX_DEPTH,
+        }
+    }
+}
+
+impl ParserOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// sets how deep things may nest before the program is too deeply nested, `MAX_DEPTH`
+    /// by default
+    ///
+    /// Each block, operand, and pattern inside another is a level, and each one is a call
+    /// deeper on the stack, so this is what keeps untrusted input from overflowing it.
+    pub fn max_depth(mut self, max: usize) -> Self {
+        self.max_depth = max;
+        self
+    }
+}
+
+/// # Parser
+///
+/// A recursive-descent parser from tokens to a `Program`. It never stops at an error: each
+/// one is reported to the sink, as a `Diagnostic`, and parsing picks up again at the next
+/// thing it recognizes, so one mistake doesn't hide the rest.
+///
+/// The sink is the parser's own, take it back out of `sink` when done. Use [`parse`] to
+/// just collect the diagnostics.
+pub struct Parser<'src, S> {
+    stream: TokenStream<'src>, // the tokens
+    options: ParserOptions,    // how to parse them
+    depth: usize,              // how deeply nested the parser is right now
+    pub sink: S,               // where diagnostics go
+}
+
+impl<'src, S: DiagnosticSink> Parser<'src, S> {
+    pub fn new(tokens: Vec<Token<'src>>, sink: S) -> Self {
+        Self::new_with(tokens, sink, ParserOptions::default())
+    }
+
+    pub fn new_with(tokens: Vec<Token<'src>>, sink: S, options: ParserOptions) -> Self {
+        Self {
+            stream: TokenStream::new(tokens),
+            options,
+            depth: 0,
+            sink,
+        }
+    }
+
+    /// runs `parse` a level deeper, see `ParserOptions::max_depth`
+    ///
+    /// Past the limit, the program is reported too deeply nested instead, and the rest of
+    /// the line skipped, so the levels above it see a mistake they've already been told
+    /// about.
+    fn nested<T>(&mut self, parse: impl FnOnce(&mut Self) -> Option<T>) -> Option<T> {
+        if self.depth >= self.options.max_depth {
+            let max = self.options.max_depth;
+            let error = ParseError {
+                kind: ParseErrorKind::TooDeep { max },
+                span: self.stream.peek().span,
+            };
+
+            self.sink.report(
+                Diagnostic::from(error)
+                    .with_help(format!("it can only go {} levels deep, split it up", max)),
+            );
+            self.skip_line();
+            return None;
+        }
+
+        self.depth += 1;
+        let result = parse(self);
+        self.depth -= 1;
+
+        result
+    }
+
+    /// reports an error
+    fn report(&mut self, error: ParseError) {
+        self.sink.report(Diagnostic::from(error));
+    }
+
+    /// parses the whole file
+    pub fn parse_program(&mut self) -> Program {
+        let mut items = Vec::new();
+
+        while !self.stream.is_eof() {
+            if let Some(item) = self.parse_item() {
+                items.push(item);
+            }
+        }
+
+        Program {
+            items,
+            span: Span::new(0, self.stream.peek().span.end),
+        }
+    }
+
+    /// parses one top-level item, `None` if it was too broken to make anything of
+    ///
+    /// A lint directive, `%allow N0042`, is skipped along with its code, and is `None` too,
+    /// it's for `LintLevels::directives` rather than the program.
+    pub fn parse_item(&mut self) -> Option<Item> {
+        if self.at_lint_directive() {
+            self.stream.bump();
+            self.stream.eat(Type::Ident);
+            return None;
+        }
+
+        if self.at_declaration("struct") {
+            return self.parse_struct().map(Item::Struct);
+        }
+
+        if self.at_declaration("enum") {
+            return self.parse_enum().map(Item::Enum);
+        }
+
+        if self.stream.at_keyword("new") {
+            return Some(Item::Block(self.parse_block_decl()));
+        }
+
+        if self.stream.at_keyword("include") {
+            return self.parse_include().map(Item::Include);
+        }
+
+        if self.stream.at_keyword("use") {
+            return self.parse_use().map(Item::Use);
+        }
+
+        if self.stream.at_keyword("fn") {
+            return self.parse_fn().map(Item::Fn);
+        }
+
+        let found = self.stream.peek();
+        let error = ParseError {
+            kind: ParseErrorKind::ExpectedItem {
+                found: found.ty.clone(),
+            },
+            span: found.span,
+        };
+
+        self.report(error);
+        self.recover_item();
+        None
+    }
+
+    /// if the next token starts an item, `new`, `fn` and a name, `include`, or `use`
+    ///
+    /// A `fn` and a `(` is a lambda, in a statement.
+    fn at_item(&self) -> bool {
+        let function = self.stream.at_keyword("fn") && self.stream.peek_n(1).ty == Type::Ident;
+
+        self.stream.at_keyword("new")
+            || function
+            || self.stream.at_keyword("include")
+            || self.stream.at_keyword("use")
+    }
+
+    /// if the next token is `%allow`, `%warn`, or `%deny`
+    fn at_lint_directive(&self) -> bool {
+        let token = self.stream.peek();
+
+        token.ty == Type::Directive && Level::from_name(&token.body[1..]).is_some()
+    }
+
+    /// if the next tokens are `new`, the contextual keyword `word`, and a name, as in
+    /// `new struct Point`
+    ///
+    /// `word` is only a keyword here, `new struct { }` is a block called `struct`.
+    fn at_declaration(&self, word: &str) -> bool {
+        self.stream.at_keyword("new")
+            && self.stream.peek_n(1).is_contextual(word)
+            && self.stream.peek_n(2).ty == Type::Ident
+    }
+
+    /// if the next token starts a section, `conditions` or `logic`
+    fn at_section(&self) -> bool {
+        self.stream.at_keyword("conditions") || self.stream.at_keyword("logic")
+    }
+
+    /// skips ahead to where the next item could start
+    fn recover_item(&mut self) {
+        self.stream.bump();
+
+        while !self.stream.is_eof() && !self.at_item() {
+            self.stream.bump();
+        }
+    }
+
+    /// `include! "core/internal"`
+    ///
+    /// The `!` doesn't lex yet, see `LexErrorKind::UnexpectedChar`, so the path comes right
+    /// after the keyword as far as the parser is concerned.
+    fn parse_include(&mut self) -> Option<Include> {
+        let start = self.stream.bump().span;
+
+        let path = match self.stream.expect(Type::String) {
+            Ok(token) => match &token.value {
+                Some(TokenValue::Str(path)) => path.clone(),
+                _ => token.body.to_owned(),
+            },
+            Err(error) => {
+                self.report(error);
+                self.recover_item();
+                return None;
+            }
+        };
+
+        Some(Include {
+            path,
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// `use core::str`, the names split up by `::`s with nothing between them
+    fn parse_use(&mut self) -> Option<Use> {
+        let start = self.stream.bump().span;
+        let mut path = Vec::new();
+
+        loop {
+            let Some(name) = self.expect_ident() else {
+                self.recover_item();
+                return None;
+            };
+
+            path.push(name);
+
+            if !self.stream.touching() || self.stream.eat(Type::MemberAccess).is_none() {
+                break;
+            }
+        }
+
+        Some(Use {
+            path,
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// # Function Declarations
+    ///
+    /// `fn greet(name, greeting) { ... }`, a trailing comma in the parameters is fine. Without
+    /// a name it gets an empty one, right after `fn`, and without its `(` or `{` it's given up
+    /// on, up to the next item.
+    fn parse_fn(&mut self) -> Option<FnDecl> {
+        let doc = self.parse_doc();
+        let start = self.stream.bump().span;
+
+        let name = match self.stream.expect(Type::Ident) {
+            Ok(token) => Ident {
+                name: token.body.to_owned(),
+                span: token.span,
+            },
+            Err(error) => {
+                self.report(error);
+
+                Ident {
+                    name: String::new(),
+                    span: Span::new(start.end, start.end),
+                }
+            }
+        };
+
+        let open = match self.stream.expect(Type::OpenParen) {
+            Ok(token) => token.span,
+            Err(error) => {
+                self.sink.report(
+                    Diagnostic::from(error).with_label(start, "the function needs parameters"),
+                );
+                self.recover_item();
+                return None;
+            }
+        };
+
+        let Some(params) = self.parse_params(open) else {
+            self.recover_item();
+            return None;
+        };
+
+        let open = match self.stream.expect(Type::OpenBrace) {
+            Ok(token) => token.span,
+            Err(error) => {
+                self.sink
+                    .report(Diagnostic::from(error).with_label(start, "the function needs a body"));
+                self.recover_item();
+                return None;
+            }
+        };
+
+        let body = self.parse_stmts(open);
+
+        Some(FnDecl {
+            doc,
+            name,
+            params,
+            body,
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// the doc comment before the next token, `None` if there isn't one
+    ///
+    /// Every doc prefix is three characters long, `;;;`, `###`, or `///`, so one space
+    /// after it is all that's left to take off each line.
+    fn parse_doc(&self) -> Option<Doc> {
+        let lines = self.stream.docs();
+        let span = lines.first()?.span.join(lines.last()?.span);
+
+        let text = lines
+            .iter()
+            .map(|line| {
+                let text = line.body.get(3..).unwrap_or("");
+                text.strip_prefix(' ').unwrap_or(text)
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        Some(Doc { text, span })
+    }
+
+    /// the names split up by commas up to a `)`, for the `(` at `open`, a trailing comma is
+    /// fine
+    fn parse_params(&mut self, open: Span) -> Option<Vec<Ident>> {
+        let mut params = Vec::new();
+
+        while self.stream.eat(Type::CloseParen).is_none() {
+            params.push(self.expect_ident()?);
+
+            if self.stream.eat(Type::Comma).is_none() && !self.stream.at(Type::CloseParen) {
+                let found = self.stream.peek();
+                let error = ParseError {
+                    kind: ParseErrorKind::Expected {
+                        expected: Type::CloseParen,
+                        found: found.ty.clone(),
+                    },
+                    span: found.span,
+                };
+
+                self.sink
+                    .report(Diagnostic::from(error).with_label(open, "the parameters start here"));
+                return None;
+            }
+        }
+
+        Some(params)
+    }
+
+    /// # Struct Declarations
+    ///
+    /// `new struct Point { x, y }`, the fields split up by commas or line breaks, a trailing
+    /// comma is fine. Anything but a name in the fields is reported and skipped.
+    fn parse_struct(&mut self) -> Option<StructDecl> {
+        let start = self.stream.bump().span;
+
+        self.stream.bump();
+
+        let name = self.expect_ident()?;
+        let fields = self.parse_names()?;
+
+        Some(StructDecl {
+            name,
+            fields,
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// # Enum Declarations
+    ///
+    /// `new enum State { Idle, Running }`, the variants written like the fields of a struct.
+    fn parse_enum(&mut self) -> Option<EnumDecl> {
+        let start = self.stream.bump().span;
+
+        self.stream.bump();
+
+        let name = self.expect_ident()?;
+        let variants = self.parse_names()?;
+
+        Some(EnumDecl {
+            name,
+            variants,
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// `{ x, y }`, the names of a struct or enum, split up by commas or line breaks
+    fn parse_names(&mut self) -> Option<Vec<Ident>> {
+        let open = match self.stream.expect(Type::OpenBrace) {
+            Ok(token) => token.span,
+            Err(error) => {
+                self.report(error);
+                self.recover_item();
+                return None;
+            }
+        };
+
+        let mut names = Vec::new();
+
+        while !self.block_end(open) {
+            match self.stream.expect(Type::Ident) {
+                Ok(token) => names.push(Ident {
+                    name: token.body.to_owned(),
+                    span: token.span,
+                }),
+                Err(error) => {
+                    self.report(error);
+
+                    if self.stream.bump().ty == Type::OpenBrace {
+                        self.skip_block(self.stream.prev_span());
+                    }
+                }
+            }
+
+            self.stream.eat(Type::Comma);
+        }
+
+        Some(names)
+    }
+
+    /// # Block Declarations
+    ///
+    /// `new hello_world { ... }`. A block is still made of whatever's there when parts are
+    /// missing, so the sections of `new { logic { } }` aren't lost:
+    ///
+    /// - without a name, it gets an empty one, right after `new`, and a token that isn't a
+    ///   name but isn't a `{` or a section either is taken as the name that should be there
+    /// - without its `{`, the sections right after count, and there's nothing to close
+    /// - without its `}`, it ends where the next item starts, or at the end of the file
+    fn parse_block_decl(&mut self) -> BlockDecl {
+        let doc = self.parse_doc();
+        let start = self.stream.bump().span;
+
+        let name = match self.stream.expect(Type::Ident) {
+            Ok(token) => Ident {
+                name: token.body.to_owned(),
+                span: token.span,
+            },
+            Err(error) => {
+                self.report(error);
+
+                if !self.stream.at(Type::OpenBrace) && !self.at_section() && !self.at_item() {
+                    self.stream.bump();
+                }
+
+                Ident {
+                    name: String::new(),
+                    span: Span::new(start.end, start.end),
+                }
+            }
+        };
+
+        // a `new` right at the end, the missing name is all there is to say
+        if self.stream.is_eof() && name.name.is_empty() {
+            return BlockDecl {
+                doc,
+                name,
+                sections: Vec::new(),
+                span: self.stream.span_from(start),
+            };
+        }
+
+        let open = match self.stream.expect(Type::OpenBrace) {
+            Ok(token) => Some(token.span),
+            Err(error) => {
+                self.report(error);
+
+                if !self.at_section() {
+                    self.recover_item();
+
+                    return BlockDecl {
+                        doc,
+                        name,
+                        sections: Vec::new(),
+                        span: self.stream.span_from(start),
+                    };
+                }
+
+                None
+            }
+        };
+
+        let mut sections = Vec::new();
+
+        while self.stream.eat(Type::CloseBrace).is_none() {
+            if self.stream.is_eof() || self.at_item() {
+                if let Some(open) = open {
+                    self.report_unclosed(open);
+                }
+
+                break;
+            }
+
+            if let Some(section) = self.parse_section() {
+                sections.push(section);
+            }
+        }
+
+        BlockDecl {
+            doc,
+            name,
+            sections,
+            span: self.stream.span_from(start),
+        }
+    }
+
+    /// `conditions { ... }` or `logic { ... }`, `None` for anything else, which is skipped
+    fn parse_section(&mut self) -> Option<Section> {
+        let conditions = self.stream.at_keyword("conditions");
+
+        if !conditions && !self.stream.at_keyword("logic") {
+            let found = self.stream.peek();
+            let error = ParseError {
+                kind: ParseErrorKind::ExpectedSection {
+                    found: found.ty.clone(),
+                },
+                span: found.span,
+            };
+
+            self.report(error);
+
+            if self.stream.at(Type::OpenBrace) {
+                let open = self.stream.bump().span;
+                self.skip_block(open);
+            } else {
+                self.stream.bump();
+            }
+
+            return None;
+        }
+
+        let start = self.stream.bump().span;
+
+        let open = match self.stream.expect(Type::OpenBrace) {
+            Ok(token) => token.span,
+            Err(error) => {
+                self.report(error);
+                return None;
+            }
+        };
+
+        if conditions {
+            let conditions = self.parse_conditions(open);
+
+            return Some(Section::Conditions(ConditionsBlock {
+                conditions,
+                span: self.stream.span_from(start),
+            }));
+        }
+
+        let body = self.parse_stmts(open);
+
+        Some(Section::Logic(LogicBlock {
+            body,
+            span: self.stream.span_from(start),
+        }))
+    }
+
+    /// if the block opened at `open` is over, moving past its `}`, or reporting it missing
+    /// if the file or the next item comes first
+    fn block_end(&mut self, open: Span) -> bool {
+        if self.stream.eat(Type::CloseBrace).is_some() {
+            return true;
+        }
+
+        if self.stream.is_eof() || self.at_item() {
+            self.report_unclosed(open);
+            return true;
+        }
+
+        false
+    }
+
+    /// the entries of a `conditions` block, up to the `}` for the `{` at `open`
+    fn parse_conditions(&mut self, open: Span) -> Vec<Condition> {
+        let mut conditions = Vec::new();
+
+        while !self.block_end(open) {
+            if let Some(condition) = self.parse_condition() {
+                conditions.push(condition);
+            }
+        }
+
+        conditions
+    }
+
+    /// # Conditions
+    ///
+    /// One entry of a `conditions` block:
+    ///
+    /// - `%override`, a directive
+    /// - `any`, or any other bare name, a predicate on its own
+    /// - `expect ident 'print'`, which takes the kind of token to expect and, optionally,
+    ///   the literals it has to be
+    ///
+    /// Line breaks don't end an entry, so only `expect` takes arguments, and a name after
+    /// a predicate is the next predicate.
+    fn parse_condition(&mut self) -> Option<Condition> {
+        let token = self.stream.peek();
+        let start = token.span;
+
+        match token.ty {
+            Type::Directive => {
+                let name = Ident {
+                    name: token.body[1..].to_owned(),
+                    span: token.span,
+                };
+
+                self.stream.bump();
+
+                return Some(Condition {
+                    kind: ConditionKind::Directive(name),
+                    span: start,
+                });
+            }
+            Type::Ident | Type::ReservedKeyword if !self.at_section() => {}
+            _ => {
+                let error = ParseError {
+                    kind: ParseErrorKind::ExpectedCondition {
+                        found: token.ty.clone(),
+                    },
+                    span: token.span,
+                };
+
+                self.report(error);
+
+                if self.stream.bump().ty == Type::OpenBrace {
+                    self.skip_block(start);
+                }
+
+                return None;
+            }
+        }
+
+        let token = self.stream.bump();
+        let expect = token.is_keyword("expect");
+        let name = Ident {
+            name: token.body.to_owned(),
+            span: token.span,
+        };
+
+        let mut args = Vec::new();
+
+        if expect {
+            match self.stream.expect(Type::Ident) {
+                Ok(kind) => args.push(Expr {
+                    kind: ExprKind::Ident(kind.body.to_owned()),
+                    span: kind.span,
+                }),
+                Err(error) => self.report(error),
+            }
+
+            while let Some(literal) = self.parse_literal() {
+                args.push(literal);
+            }
+        }
+
+        Some(Condition {
+            kind: ConditionKind::Predicate { name, args },
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// the statements of a block, up to the `}` for the `{` at `open`
+    ///
+    /// After a broken statement, the parser picks up again at the next one, see
+    /// `recover_stmt`.
+    fn parse_stmts(&mut self, open: Span) -> Vec<Stmt> {
+        let nested = self.nested(|parser| Some(parser.parse_stmts_at(open)));
+
+        nested.unwrap_or_else(|| {
+            self.skip_block(open);
+            Vec::new()
+        })
+    }
+
+    /// `parse_stmts`, a level deeper
+    fn parse_stmts_at(&mut self, open: Span) -> Vec<Stmt> {
+        let mut stmts = Vec::new();
+
+        while !self.block_end(open) {
+            let start = self.stream.position();
+
+            match self.parse_stmt() {
+                Some(stmt) => stmts.push(stmt),
+                None => self.recover_stmt(start),
+            }
+        }
+
+        stmts
+    }
+
+    /// skips what's left of a broken statement, up to the next line, a `}`, or a keyword
+    /// that starts a statement, like `let`, past any blocks on the way
+    ///
+    /// So a mistake is reported once, and the statements after it still get parsed, and
+    /// their mistakes reported too. A statement broken at its very first token, from
+    /// `start`, hasn't moved past anything, so that token is skipped whatever it is, or the
+    /// same statement would be tried again forever.
+    fn recover_stmt(&mut self, start: usize) {
+        let mut stuck = self.stream.position() == start;
+
+        while std::mem::take(&mut stuck)
+            || !self.stream.at_line_start()
+                && !self.stream.at(Type::CloseBrace)
+                && !self.stream.is_eof()
+                && !self.at_stmt_keyword()
+        {
+            if matches!(self.stream.bump().ty, Type::OpenBrace | Type::OpenMap) {
+                self.skip_block(self.stream.prev_span());
+            }
+        }
+    }
+
+    /// if the next token is a keyword that starts a statement, like `let` or `return`
+    fn at_stmt_keyword(&self) -> bool {
+        [
+            "collect", "for", "let", "if", "while", "return", "break", "continue",
+        ]
+        .iter()
+        .any(|keyword| self.stream.at_keyword(keyword))
+    }
+
+    /// # Statements
+    ///
+    /// One statement of a `logic` block, `None` if it was too broken to make anything of:
+    ///
+    /// - `collect as $`
+    /// - `for $ as var { ... }`
+    /// - `let x = 5`
+    /// - `if x > 1 { ... } else { ... }`
+    /// - `while x > 1 { ... }`
+    /// - `return`, with a value on the same line if there is one, `break`, and `continue`
+    /// - `::stdout write_newline var`, a call, its arguments up to the end of the line
+    /// - `x = 5` or `x += 1`, an assignment
+    /// - an expression on its own
+    ///
+    /// Where a statement is doesn't matter here, a `break` outside a loop or a `return`
+    /// outside a function parses fine, and is left to the passes after parsing.
+    pub fn parse_stmt(&mut self) -> Option<Stmt> {
+        let start = self.stream.peek().span;
+
+        let kind = if self.stream.at_keyword("collect") {
+            StmtKind::Collect(self.parse_collect()?)
+        } else if self.stream.at_keyword("for") {
+            StmtKind::For(self.parse_for()?)
+        } else if self.stream.at_keyword("let") {
+            StmtKind::Let(self.parse_let()?)
+        } else if self.stream.at_keyword("if") {
+            StmtKind::If(self.parse_if()?)
+        } else if self.stream.at_keyword("while") {
+            StmtKind::While(self.parse_while()?)
+        } else if self.stream.at_keyword("return") {
+            self.stream.bump();
+
+            match self.at_expr() && !self.stream.at_line_start() {
+                true => StmtKind::Return(Some(self.parse_expr()?)),
+                false => StmtKind::Return(None),
+            }
+        } else if self.stream.at_keyword("break") {
+            self.stream.bump();
+            StmtKind::Break
+        } else if self.stream.at_keyword("continue") {
+            self.stream.bump();
+            StmtKind::Continue
+        } else if self.stream.at(Type::MemberAccess) {
+            StmtKind::Call(self.parse_call()?)
+        } else if self.at_expr() {
+            self.parse_expr_stmt()?
+        } else {
+            let found = self.stream.peek();
+            let dangling = found.is_keyword("else");
+            let error = ParseError {
+                kind: ParseErrorKind::ExpectedStmt {
+                    found: found.ty.clone(),
+                },
+                span: found.span,
+            };
+
+            let mut diagnostic = Diagnostic::from(error);
+
+            if dangling {
+                diagnostic = diagnostic.with_help("an `else` goes right after an `if`'s body");
+            }
+
+            self.sink.report(diagnostic);
+
+            // a stray `else` has its body skipped along with it
+            if dangling {
+                self.stream.bump();
+                self.skip_body();
+            } else if self.stream.bump().ty == Type::OpenBrace {
+                self.skip_block(start);
+            }
+
+            return None;
+        };
+
+        Some(Stmt {
+            kind,
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// an expression on its own, or assigned to with `=` or `+=` on the same line
+    ///
+    /// A target that can't be assigned to, like `1 = x`, is reported but kept.
+    fn parse_expr_stmt(&mut self) -> Option<StmtKind> {
+        let target = self.parse_expr()?;
+
+        let op = match self.stream.peek().ty {
+            _ if self.stream.at_line_start() => return Some(StmtKind::Expr(target)),
+            Type::Equal => None,
+            Type::PlusEqual => Some(BinOp::Add),
+            Type::MinusEqual => Some(BinOp::Sub),
+            Type::MultiplyEqual => Some(BinOp::Mul),
+            Type::DivideEqual => Some(BinOp::Div),
+            Type::ModuloEqual => Some(BinOp::Rem),
+            _ => return Some(StmtKind::Expr(target)),
+        };
+
+        let assign = self.stream.bump().span;
+
+        if !target.is_assignable() {
+            let error = ParseError {
+                kind: ParseErrorKind::NotAssignable,
+                span: target.span,
+            };
+
+            self.sink.report(
+                Diagnostic::from(error)
+                    .with_label(assign, "assigned here")
+                    .with_help("only names, sigils, and their members can be assigned to"),
+            );
+        }
+
+        let value = self.parse_expr()?;
+
+        Some(StmtKind::Assign(Assign { target, op, value }))
+    }
+
+    /// moves past the contextual keyword `word`, reporting it if it isn't there
+    ///
+    /// When a `then`, what comes after `word`, is there instead, `word` is suggested in front
+    /// of it and parsing goes on as if it was there.
+    fn expect_contextual(&mut self, word: &str, then: Type) -> Option<()> {
+        if self.stream.peek().is_contextual(word) {
+            self.stream.bump();
+            return Some(());
+        }
+
+        let found = self.stream.peek();
+        let at = found.span;
+        let fixable = found.ty == then;
+        let error = ParseError {
+            kind: ParseErrorKind::ExpectedKeyword {
+                keyword: word.to_owned(),
+                found: found.ty.clone(),
+            },
+            span: at,
+        };
+
+        if !fixable {
+            self.report(error);
+            return None;
+        }
+
+        self.sink.report(Diagnostic::from(error).with_suggestion(
+            Span::new(at.start, at.start),
+            format!("{} ", word),
+            Applicability::MachineApplicable,
+        ));
+
+        Some(())
+    }
+
+    /// moves past a name, reporting it if it isn't there
+    fn expect_ident(&mut self) -> Option<Ident> {
+        match self.stream.expect(Type::Ident) {
+            Ok(token) => Some(Ident {
+                name: token.body.to_owned(),
+                span: token.span,
+            }),
+            Err(error) => {
+                self.report(error);
+                None
+            }
+        }
+    }
+
+    /// # Collect Statements
+    ///
+    /// `collect as $` or `collect as $name`. Two slips are fixed up, with the fix suggested:
+    /// a missing `as`, `collect $`, and a name without its `$`, `collect as args`.
+    fn parse_collect(&mut self) -> Option<CollectStmt> {
+        self.stream.bump();
+        self.expect_contextual("as", Type::Sigil)?;
+
+        let token = self.stream.peek();
+        let named = token.ty == Type::Ident && !self.stream.at_line_start();
+        let target = Sigil {
+            name: token.body.trim_start_matches('$').to_owned(),
+            span: token.span,
+        };
+
+        match self.stream.expect(Type::Sigil) {
+            Ok(_) => {}
+            Err(error) if named => {
+                let replacement = format!("${}", target.name);
+
+                self.sink.report(Diagnostic::from(error).with_suggestion(
+                    target.span,
+                    replacement,
+                    Applicability::MachineApplicable,
+                ));
+                self.stream.bump();
+            }
+            Err(error) => {
+                self.report(error);
+                return None;
+            }
+        }
+
+        Some(CollectStmt { target })
+    }
+
+    /// # For Statements
+    ///
+    /// `for $ as var { ... }`. The binding is a name, or a pattern in parentheses,
+    /// `for $ as (key, value) { }`.
+    ///
+    /// It keeps going past the mistakes it can make sense of:
+    ///
+    /// - without `as`, but with a name, `for $ var { }`, the `as` is suggested where it goes
+    /// - without a name, `for $ as { }`, the binding is an empty one, right after `as`
+    /// - without its `}`, the body runs up to the next item or the end of the file
+    ///
+    /// Without its `{` there's no body to speak of, and the loop is given up on.
+    fn parse_for(&mut self) -> Option<ForStmt> {
+        let start = self.stream.bump().span;
+
+        let Some(source) = self.parse_expr() else {
+            self.skip_body();
+            return None;
+        };
+
+        self.expect_contextual("as", Type::Ident)?;
+
+        let binding = if self.stream.at(Type::OpenParen) {
+            let Some(pattern) = self.parse_pattern() else {
+                self.skip_body();
+                return None;
+            };
+
+            pattern
+        } else {
+            match self.stream.expect(Type::Ident) {
+                Ok(token) => Pattern {
+                    kind: PatternKind::Ident(token.body.to_owned()),
+                    span: token.span,
+                },
+                Err(error) => {
+                    let after = self.stream.prev_span().end;
+
+                    self.sink.report(
+                        Diagnostic::from(error)
+                            .with_label(start, "in this loop")
+                            .with_help("name each thing looped over, like `for $ as var { }`"),
+                    );
+
+                    Pattern {
+                        kind: PatternKind::Ident(String::new()),
+                        span: Span::new(after, after),
+                    }
+                }
+            }
+        };
+
+        let body = self.parse_body(start, "the loop needs a body")?;
+
+        Some(ForStmt {
+            source,
+            binding,
+            body,
+        })
+    }
+
+    /// # If Statements
+    ///
+    /// `if x > 1 { ... }`, then any number of `else if y { ... }`, then maybe an
+    /// `else { ... }`. The `else` can go on the line after the `}` too.
+    fn parse_if(&mut self) -> Option<IfStmt> {
+        let start = self.stream.bump().span;
+
+        let Some(condition) = self.parse_expr() else {
+            self.skip_body();
+            return None;
+        };
+        let then = self.parse_body(start, "the `if` needs a body")?;
+
+        if !self.stream.at_keyword("else") {
+            return Some(IfStmt {
+                condition,
+                then,
+                otherwise: None,
+            });
+        }
+
+        let otherwise = self.stream.bump().span;
+
+        let otherwise = if self.stream.at_keyword("if") {
+            let start = self.stream.peek().span;
+            let kind = StmtKind::If(self.parse_if()?);
+
+            Else::If(Box::new(Stmt {
+                kind,
+                span: self.stream.span_from(start),
+            }))
+        } else {
+            Else::Block(self.parse_body(otherwise, "the `else` needs a body")?)
+        };
+
+        Some(IfStmt {
+            condition,
+            then,
+            otherwise: Some(otherwise),
+        })
+    }
+
+    /// `while x > 1 { ... }`
+    fn parse_while(&mut self) -> Option<WhileStmt> {
+        let start = self.stream.bump().span;
+
+        let Some(condition) = self.parse_expr() else {
+            self.skip_body();
+            return None;
+        };
+
+        let body = self.parse_body(start, "the loop needs a body")?;
+
+        Some(WhileStmt { condition, body })
+    }
+
+    /// the `{ ... }` body of the statement started at `start`, reporting it with `label`
+    /// if the `{` is missing, and skipping the rest of the line
+    fn parse_body(&mut self, start: Span, label: &str) -> Option<Vec<Stmt>> {
+        match self.stream.expect(Type::OpenBrace) {
+            Ok(token) => {
+                let open = token.span;
+
+                Some(self.parse_stmts(open))
+            }
+            Err(error) => {
+                self.sink
+                    .report(Diagnostic::from(error).with_label(start, label));
+                self.skip_line();
+                None
+            }
+        }
+    }
+
+    /// moves past a `{ ... }` body if one comes next, for a statement too broken to keep
+    fn skip_body(&mut self) {
+        if let Some(token) = self.stream.eat(Type::OpenBrace) {
+            let open = token.span;
+
+            self.skip_block(open);
+        }
+    }
+
+    /// # Let Statements
+    ///
+    /// `let x = 5`, or `let x` without a value, or `let (a, b) = pair` to take the value
+    /// apart. A value on the same line without its `=`, `let x 5`, gets the `=` suggested,
+    /// and a missing name an empty one, right after `let`.
+    fn parse_let(&mut self) -> Option<LetStmt> {
+        let start = self.stream.bump().span;
+
+        let pattern = if self.stream.at(Type::OpenParen) {
+            let Some(pattern) = self.parse_pattern() else {
+                self.skip_line();
+                return None;
+            };
+
+            pattern
+        } else {
+            match self.stream.expect(Type::Ident) {
+                Ok(token) => Pattern {
+                    kind: PatternKind::Ident(token.body.to_owned()),
+                    span: token.span,
+                },
+                Err(error) => {
+                    self.report(error);
+
+                    if !self.stream.at(Type::Equal) {
+                        return None;
+                    }
+
+                    Pattern {
+                        kind: PatternKind::Ident(String::new()),
+                        span: Span::new(start.end, start.end),
+                    }
+                }
+            }
+        };
+
+        if self.stream.at_line_start() {
+            return Some(LetStmt {
+                pattern,
+                value: None,
+            });
+        }
+
+        if self.stream.eat(Type::Equal).is_none() {
+            if !self.at_expr() {
+                return Some(LetStmt {
+                    pattern,
+                    value: None,
+                });
+            }
+
+            let found = self.stream.peek();
+            let at = found.span;
+            let error = ParseError {
+                kind: ParseErrorKind::Expected {
+                    expected: Type::Equal,
+                    found: found.ty.clone(),
+                },
+                span: at,
+            };
+
+            self.sink.report(Diagnostic::from(error).with_suggestion(
+                Span::new(at.start, at.start),
+                "= ",
+                Applicability::MachineApplicable,
+            ));
+        }
+
+        let value = self.parse_expr()?;
+
+        Some(LetStmt {
+            pattern,
+            value: Some(value),
+        })
+    }
+
+    /// # Calls
+    ///
+    /// `::stdout write_newline var $::1`, a module, a function, and the arguments up to the
+    /// end of the line. An argument can be any expression, `a + 1` is one argument, and a
+    /// call in the arguments takes the rest of them, `::stdout write ::str upper var` writes
+    /// what `::str upper var` gives back.
+    ///
+    /// An argument that doesn't parse gives up on the rest of the line.
+    fn parse_call(&mut self) -> Option<Call> {
+        self.stream.bump();
+
+        let module = self.expect_ident()?;
+        let function = self.expect_ident()?;
+
+        let mut args = Vec::new();
+
+        while !self.stream.at_line_start() {
+            let arg = if self.stream.at(Type::MemberAccess) {
+                let start = self.stream.peek().span;
+
+                self.nested(Self::parse_call).map(|call| Expr {
+                    kind: ExprKind::Call(call),
+                    span: self.stream.span_from(start),
+                })
+            } else if self.at_expr() {
+                self.parse_expr()
+            } else {
+                break;
+            };
+
+            let Some(arg) = arg else {
+                self.skip_line();
+                return None;
+            };
+
+            args.push(arg);
+        }
+
+        Some(Call {
+            module,
+            function,
+            args,
+        })
+    }
+
+    /// moves up to the next line, or the `}` or `Eof` that comes first, past any blocks
+    /// on the way
+    fn skip_line(&mut self) {
+        while !self.stream.at_line_start()
+            && !self.stream.at(Type::CloseBrace)
+            && !self.stream.is_eof()
+        {
+            if matches!(self.stream.bump().ty, Type::OpenBrace | Type::OpenMap) {
+                self.skip_block(self.stream.prev_span());
+            }
+        }
+    }
+
+    /// if the next token can start an expression
+    fn at_expr(&self) -> bool {
+        let operand = matches!(
+            self.stream.peek().ty,
+            Type::Number
+                | Type::String
+                | Type::InterpolatedString
+                | Type::RawString
+                | Type::Char
+                | Type::Ident
+                | Type::Sigil
+                | Type::Minus
+                | Type::OpenParen
+                | Type::OpenBracket
+                | Type::OpenMap
+        );
+
+        operand || self.stream.at_keyword("match") || self.stream.at_keyword("fn")
+    }
+
+    /// # Expressions
+    ///
+    /// Operands joined by binary operators, by precedence climbing: `1 + 2 * 3` is
+    /// `1 + (2 * 3)`, and operators of the same precedence go left to right, see `BinOp`.
+    ///
+    /// An operator at the start of a line ends the expression instead, it's the start of
+    /// the next statement.
+    pub fn parse_expr(&mut self) -> Option<Expr> {
+        self.nested(Self::parse_conditional)
+    }
+
+    /// `cond ? a : b`, looser than anything else, and right-associative, so
+    /// `a ? b : c ? d : e` is `a ? b : (c ? d : e)`
+    fn parse_conditional(&mut self) -> Option<Expr> {
+        let condition = self.parse_range()?;
+
+        if self.stream.at_line_start() || !self.stream.at(Type::Question) {
+            return Some(condition);
+        }
+
+        let question = self.stream.bump().span;
+        let then = self.parse_expr()?;
+
+        if let Err(error) = self.stream.expect(Type::Colon) {
+            self.sink.report(
+                Diagnostic::from(error)
+                    .with_label(question, "for this `?`")
+                    .with_help("say what it is otherwise too, as in `x > 1 ? \"many\" : \"few\"`"),
+            );
+            return None;
+        }
+
+        let otherwise = self.nested(Self::parse_conditional)?;
+
+        Some(Expr {
+            span: condition.span.join(otherwise.span),
+            kind: ExprKind::Conditional {
+                condition: Box::new(condition),
+                then: Box::new(then),
+                otherwise: Box::new(otherwise),
+            },
+        })
+    }
+
+    /// `1..10` or `1..=10`, looser than any binary operator, so `0..n - 1` ends at `n - 1`
+    ///
+    /// A range can't be the start or the end of another one, `1..2..3` is reported at the
+    /// second `..`.
+    fn parse_range(&mut self) -> Option<Expr> {
+        let start = self.parse_binary(0)?;
+
+        if self.stream.at_line_start()
+            || !matches!(self.stream.peek().ty, Type::DotDot | Type::DotDotEqual)
+        {
+            return Some(start);
+        }
+
+        let inclusive = self.stream.bump().ty == Type::DotDotEqual;
+        let end = self.parse_binary(0)?;
+
+        if !self.stream.at_line_start()
+            && matches!(self.stream.peek().ty, Type::DotDot | Type::DotDotEqual)
+        {
+            let found = self.stream.peek();
+            let error = ParseError {
+                kind: ParseErrorKind::ExpectedExpr {
+                    found: found.ty.clone(),
+                },
+                span: found.span,
+            };
+
+            self.sink.report(
+                Diagnostic::from(error).with_help("put the range in a variable to use it again"),
+            );
+        }
+
+        Some(Expr {
+            span: start.span.join(end.span),
+            kind: ExprKind::Range {
+                start: Box::new(start),
+                end: Box::new(end),
+                inclusive,
+            },
+        })
+    }
+
+    /// an expression of operators binding tighter than `min`
+    fn parse_binary(&mut self, min: u8) -> Option<Expr> {
+        let mut lhs = self.parse_unary()?;
+
+        while !self.stream.at_line_start() {
+            let Some(op) = binary_op(&self.stream.peek().ty).filter(|op| op.precedence() > min)
+            else {
+                break;
+            };
+
+            let at = self.stream.bump().span;
+            let rhs = self.parse_binary(op.precedence())?;
+
+            self.check_strings(op, at, [&lhs, &rhs]);
+
+            lhs = Expr {
+                span: lhs.span.join(rhs.span),
+                kind: ExprKind::Binary {
+                    op,
+                    lhs: Box::new(lhs),
+                    rhs: Box::new(rhs),
+                },
+            };
+        }
+
+        Some(lhs)
+    }
+
+    /// reports a string literal on either side of an operator that doesn't take strings,
+    /// `"a" - 1` or `"a{b}" - 1`, at the operator, see `BinOp::takes_strings`
+    ///
+    /// The expression is still kept. Only literals are checked, what a name holds isn't known
+    /// yet.
+    fn check_strings(&mut self, op: BinOp, at: Span, operands: [&Expr; 2]) {
+        if op.takes_strings() {
+            return;
+        }
+
+        let Some(string) = operands.iter().find(|operand| {
+            matches!(
+                operand.kind,
+                ExprKind::String(_) | ExprKind::Interpolated(_)
+            )
+        }) else {
+            return;
+        };
+
+        let error = ParseError {
+            kind: ParseErrorKind::NotOnStrings { op },
+            span: at,
+        };
+
+        self.sink.report(
+            Diagnostic::from(error)
+                .with_label(string.span, "this is a string")
+                .with_help("strings only have `+`, to join them, and the comparisons"),
+        );
+    }
+
+    /// `-x`, or an operand on its own
+    fn parse_unary(&mut self) -> Option<Expr> {
+        if !self.stream.at(Type::Minus) {
+            return self.parse_member();
+        }
+
+        let start = self.stream.bump().span;
+        let operand = self.nested(Self::parse_unary)?;
+
+        Some(Expr {
+            span: start.join(operand.span),
+            kind: ExprKind::Unary {
+                op: UnaryOp::Neg,
+                operand: Box::new(operand),
+            },
+        })
+    }
+
+    /// a literal, a name, or a sigil, with any `::` members, `.` fields, `(...)` calls, or
+    /// `[...]` indexes right after it, like `$::1`, `p.x`, `greet("world")`, or `xs[0]`
+    fn parse_member(&mut self) -> Option<Expr> {
+        let mut expr = self.parse_atom()?;
+
+        loop {
+            if !self.stream.touching() {
+                break;
+            }
+
+            if let Some(token) = self.stream.eat(Type::OpenParen) {
+                let open = token.span;
+                let args = self.parse_list(open, Type::CloseParen)?;
+
+                expr = Expr {
+                    span: self.stream.span_from(expr.span),
+                    kind: ExprKind::FnCall {
+                        callee: Box::new(expr),
+                        args,
+                    },
+                };
+
+                continue;
+            }
+
+            if self.stream.eat(Type::Dot).is_some() {
+                if !self.stream.touching() {
+                    let found = self.stream.peek();
+                    let error = ParseError {
+                        kind: ParseErrorKind::Expected {
+                            expected: Type::Ident,
+                            found: found.ty.clone(),
+                        },
+                        span: found.span,
+                    };
+
+                    self.sink.report(
+                        Diagnostic::from(error)
+                            .with_help("the field goes right after the `.`, as in `p.x`"),
+                    );
+                    return None;
+                }
+
+                let field = self.expect_ident()?;
+
+                expr = Expr {
+                    span: expr.span.join(field.span),
+                    kind: ExprKind::Field {
+                        base: Box::new(expr),
+                        field,
+                    },
+                };
+
+                continue;
+            }
+
+            if let Some(token) = self.stream.eat(Type::OpenBracket) {
+                let open = token.span;
+                let index = self.parse_expr()?;
+
+                if let Err(error) = self.stream.expect(Type::CloseBracket) {
+                    self.sink
+                        .report(Diagnostic::from(error).with_label(open, "the index started here"));
+                    self.skip_list(Type::CloseBracket);
+                    return None;
+                }
+
+                expr = Expr {
+                    span: self.stream.span_from(expr.span),
+                    kind: ExprKind::Index {
+                        base: Box::new(expr),
+                        index: Box::new(index),
+                    },
+                };
+
+                continue;
+            }
+
+            if self.stream.eat(Type::MemberAccess).is_none() {
+                break;
+            }
+
+            if !self.stream.touching() {
+                let found = self.stream.peek();
+                let error = ParseError {
+                    kind: ParseErrorKind::ExpectedExpr {
+                        found: found.ty.clone(),
+                    },
+                    span: found.span,
+                };
+
+                self.report(error);
+                return None;
+            }
+
+            let member = self.parse_atom()?;
+
+            expr = Expr {
+                span: expr.span.join(member.span),
+                kind: ExprKind::Member {
+                    base: Box::new(expr),
+                    member: Box::new(member),
+                },
+            };
+        }
+
+        Some(expr)
+    }
+
+    /// expressions split up by commas up to a `close`, for the `(` or `[` at `open`, a
+    /// trailing comma is fine
+    fn parse_list(&mut self, open: Span, close: Type) -> Option<Vec<Expr>> {
+        let mut items = Vec::new();
+
+        while self.stream.eat(close.clone()).is_none() {
+            items.push(self.parse_expr()?);
+
+            if self.stream.eat(Type::Comma).is_none() && !self.stream.at(close.clone()) {
+                let found = self.stream.peek();
+                let error = ParseError {
+                    kind: ParseErrorKind::Expected {
+                        expected: close.clone(),
+                        found: found.ty.clone(),
+                    },
+                    span: found.span,
+                };
+
+                self.sink
+                    .report(Diagnostic::from(error).with_label(open, "the list started here"));
+                self.skip_list(close);
+                return None;
+            }
+        }
+
+        Some(items)
+    }
+
+    /// moves past the `close` of a broken list, unless a line break, a `}`, or the `Eof`
+    /// comes first
+    fn skip_list(&mut self, close: Type) {
+        while !self.stream.at(Type::CloseBrace)
+            && !self.stream.is_eof()
+            && !self.stream.at_line_start()
+        {
+            if self.stream.bump().ty == close {
+                return;
+            }
+        }
+    }
+
+    /// `(1 + 2)`, with the `(` at `open`, the expression inside with the parentheses in its
+    /// span
+    ///
+    /// Without its `)` it's reported at the `(`, and skipped up to a `)` on the same line.
+    fn parse_paren(&mut self, open: Span) -> Option<Expr> {
+        let Some(inner) = self.parse_expr() else {
+            self.skip_list(Type::CloseParen);
+            return None;
+        };
+
+        if self.stream.eat(Type::CloseParen).is_none() {
+            let found = self.stream.peek().span;
+            let error = ParseError {
+                kind: ParseErrorKind::UnclosedParen,
+                span: open,
+            };
+
+            self.sink
+                .report(Diagnostic::from(error).with_label(found, "expected `)` before this"));
+            self.skip_list(Type::CloseParen);
+            return None;
+        }
+
+        Some(Expr {
+            kind: inner.kind,
+            span: self.stream.span_from(open),
+        })
+    }
+
+    /// # Map Literals
+    ///
+    /// `#{ "key": value, ... }`, the `#` keeping it apart from a block. Entries are split up
+    /// by commas, a trailing one is fine, and a broken entry gives up on the rest of the map.
+    fn parse_map(&mut self, open: Span) -> Option<Vec<MapEntry>> {
+        let mut entries = Vec::new();
+
+        while self.stream.eat(Type::CloseBrace).is_none() {
+            if self.stream.is_eof() {
+                self.report_unclosed(open);
+                return None;
+            }
+
+            let Some(entry) = self.parse_map_entry() else {
+                self.skip_block(open);
+                return None;
+            };
+
+            entries.push(entry);
+
+            let closing = self.stream.at(Type::CloseBrace) || self.stream.is_eof();
+
+            if self.stream.eat(Type::Comma).is_none() && !closing {
+                let found = self.stream.peek();
+                let error = ParseError {
+                    kind: ParseErrorKind::Expected {
+                        expected: Type::CloseBrace,
+                        found: found.ty.clone(),
+                    },
+                    span: found.span,
+                };
+
+                self.sink
+                    .report(Diagnostic::from(error).with_label(open, "the map started here"));
+                self.skip_block(open);
+                return None;
+            }
+        }
+
+        Some(entries)
+    }
+
+    /// `"key": value`
+    fn parse_map_entry(&mut self) -> Option<MapEntry> {
+        let key = self.parse_expr()?;
+
+        if let Err(error) = self.stream.expect(Type::Colon) {
+            self.report(error);
+            return None;
+        }
+
+        let value = self.parse_expr()?;
+
+        Some(MapEntry { key, value })
+    }
+
+    /// # Lambdas
+    ///
+    /// `fn (x) { ... }`, a function without a name, as a value, for the functions that take
+    /// others, like `map(xs, fn (x) { return x * 2 })`.
+    fn parse_lambda(&mut self) -> Option<Lambda> {
+        let start = self.stream.bump().span;
+
+        let open = match self.stream.expect(Type::OpenParen) {
+            Ok(token) => token.span,
+            Err(error) => {
+                self.sink.report(
+                    Diagnostic::from(error).with_label(start, "the lambda needs parameters"),
+                );
+                self.skip_line();
+                return None;
+            }
+        };
+
+        let Some(params) = self.parse_params(open) else {
+            self.skip_list(Type::CloseParen);
+            self.skip_body();
+            return None;
+        };
+
+        let body = self.parse_body(start, "the lambda needs a body")?;
+
+        Some(Lambda { params, body })
+    }
+
+    /// a literal, a `( ... )`, a list, a map, a lambda, a name, or a sigil, reporting
+    /// anything else
+    fn parse_atom(&mut self) -> Option<Expr> {
+        if let Some(literal) = self.parse_literal() {
+            return Some(literal);
+        }
+
+        if self.stream.at(Type::InterpolatedString) {
+            return Some(self.parse_interpolated());
+        }
+
+        if let Some(token) = self.stream.eat(Type::OpenParen) {
+            let open = token.span;
+
+            return self.parse_paren(open);
+        }
+
+        if self.stream.at_keyword("fn") {
+            let start = self.stream.peek().span;
+            let kind = ExprKind::Lambda(self.parse_lambda()?);
+
+            return Some(Expr {
+                kind,
+                span: self.stream.span_from(start),
+            });
+        }
+
+        if let Some(token) = self.stream.eat(Type::OpenBracket) {
+            let open = token.span;
+            let items = self.parse_list(open, Type::CloseBracket)?;
+
+            return Some(Expr {
+                kind: ExprKind::ListLit(items),
+                span: self.stream.span_from(open),
+            });
+        }
+
+        if let Some(token) = self.stream.eat(Type::OpenMap) {
+            let open = token.span;
+            let entries = self.parse_map(open)?;
+
+            return Some(Expr {
+                kind: ExprKind::MapLit(entries),
+                span: self.stream.span_from(open),
+            });
+        }
+
+        if self.stream.at_keyword("match") {
+            let start = self.stream.peek().span;
+            let kind = ExprKind::Match(self.parse_match()?);
+
+            return Some(Expr {
+                kind,
+                span: self.stream.span_from(start),
+            });
+        }
+
+        let token = self.stream.peek();
+
+        let expr = match token.ty {
+            Type::Ident => Expr {
+                kind: ExprKind::Ident(token.body.to_owned()),
+                span: token.span,
+            },
+            Type::Sigil => sigil(token),
+            _ => {
+                let error = ParseError {
+                    kind: ParseErrorKind::ExpectedExpr {
+                        found: token.ty.clone(),
+                    },
+                    span: token.span,
+                };
+
+                self.report(error);
+                return None;
+            }
+        };
+
+        self.stream.bump();
+        Some(expr)
+    }
+
+    /// # Match Expressions
+    ///
+    /// `match x { pattern => body, ... }`, with the arms split up by commas or line breaks,
+    /// and a trailing comma is fine. A body is one statement, or a `{ ... }` of them.
+    ///
+    /// An arm that doesn't parse is skipped up to the next comma or line, the others are
+    /// kept.
+    fn parse_match(&mut self) -> Option<Match> {
+        let start = self.stream.bump().span;
+
+        let Some(scrutinee) = self.parse_expr() else {
+            self.skip_body();
+            return None;
+        };
+
+        let open = match self.stream.expect(Type::OpenBrace) {
+            Ok(token) => token.span,
+            Err(error) => {
+                self.sink
+                    .report(Diagnostic::from(error).with_label(start, "the match needs arms"));
+                self.skip_line();
+                return None;
+            }
+        };
+
+        let mut arms = Vec::new();
+
+        while !self.block_end(open) {
+            match self.parse_arm() {
+                Some(arm) => arms.push(arm),
+                None => self.skip_arm(),
+            }
+
+            self.stream.eat(Type::Comma);
+        }
+
+        Some(Match {
+            scrutinee: Box::new(scrutinee),
+            arms,
+        })
+    }
+
+    /// `pattern => body`
+    fn parse_arm(&mut self) -> Option<MatchArm> {
+        let start = self.stream.peek().span;
+        let pattern = self.parse_pattern()?;
+
+        if let Err(error) = self.stream.expect(Type::FatArrow) {
+            self.report(error);
+            return None;
+        }
+
+        let body = match self.stream.eat(Type::OpenBrace) {
+            Some(token) => {
+                let open = token.span;
+
+                self.parse_stmts(open)
+            }
+            None => vec![self.parse_stmt()?],
+        };
+
+        Some(MatchArm {
+            pattern,
+            body,
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// a literal, `-` and a number, a variant like `State::Idle`, a name, a `(a, b)` of other
+    /// patterns, or `_`, reporting anything else
+    fn parse_pattern(&mut self) -> Option<Pattern> {
+        let start = self.stream.peek().span;
+
+        if self.stream.peek().is_contextual("_") {
+            self.stream.bump();
+
+            return Some(Pattern {
+                kind: PatternKind::Wildcard,
+                span: start,
+            });
+        }
+
+        if self.stream.at(Type::Ident) && self.stream.peek_n(1).ty == Type::MemberAccess {
+            let name = self.expect_ident()?;
+
+            self.stream.bump();
+
+            let variant = self.expect_ident()?;
+
+            return Some(Pattern {
+                kind: PatternKind::Variant { name, variant },
+                span: self.stream.span_from(start),
+            });
+        }
+
+        if self.stream.at(Type::Ident) {
+            let name = self.stream.bump().body.to_owned();
+
+            return Some(Pattern {
+                kind: PatternKind::Ident(name),
+                span: start,
+            });
+        }
+
+        if let Some(token) = self.stream.eat(Type::OpenParen) {
+            let open = token.span;
+            let mut items = Vec::new();
+
+            while self.stream.eat(Type::CloseParen).is_none() {
+                let Some(item) = self.nested(Self::parse_pattern) else {
+                    self.skip_list(Type::CloseParen);
+                    return None;
+                };
+
+                items.push(item);
+
+                if self.stream.eat(Type::Comma).is_none() && !self.stream.at(Type::CloseParen) {
+                    let found = self.stream.peek();
+                    let error = ParseError {
+                        kind: ParseErrorKind::Expected {
+                            expected: Type::CloseParen,
+                            found: found.ty.clone(),
+                        },
+                        span: found.span,
+                    };
+
+                    self.sink.report(
+                        Diagnostic::from(error).with_label(open, "the pattern started here"),
+                    );
+                    self.skip_list(Type::CloseParen);
+                    return None;
+                }
+            }
+
+            return Some(Pattern {
+                kind: PatternKind::Tuple(items),
+                span: self.stream.span_from(start),
+            });
+        }
+
+        let negative = self.stream.at(Type::Minus) && self.stream.peek_n(1).ty == Type::Number;
+
+        if negative {
+            self.stream.bump();
+        }
+
+        let kind = match self.parse_literal().map(|literal| literal.kind) {
+            Some(ExprKind::Number(n)) if negative => PatternKind::Number(-n),
+            Some(ExprKind::Number(n)) => PatternKind::Number(n),
+            Some(ExprKind::String(s)) => PatternKind::String(s),
+            Some(ExprKind::Char(c)) => PatternKind::Char(c),
+            _ => {
+                let found = self.stream.peek();
+                let error = ParseError {
+                    kind: ParseErrorKind::ExpectedPattern {
+                        found: found.ty.clone(),
+                    },
+                    span: found.span,
+                };
+
+                self.sink.report(Diagnostic::from(error).with_help(
+                    "a pattern is a number, a string, a character, a variant like \
+                         `State::Idle`, a name, a `(a, b)`, or `_`",
+                ));
+                return None;
+            }
+        };
+
+        Some(Pattern {
+            kind,
+            span: self.stream.span_from(start),
+        })
+    }
+
+    /// moves up to the next arm, past the next comma or line, or up to the `}` or `Eof`
+    fn skip_arm(&mut self) {
+        while !self.stream.at(Type::Comma) && !self.stream.at(Type::CloseBrace) {
+            if self.stream.is_eof() {
+                return;
+            }
+
+            if self.stream.bump().ty == Type::OpenBrace {
+                self.skip_block(self.stream.prev_span());
+            }
+
+            if self.stream.at_line_start() {
+                return;
+            }
+        }
+    }
+
+    /// a number, string, or character, if that's what comes next
+    ///
+    /// One without a value was already reported by the lexer, like `0x` or `'\q'`, so it
+    /// stands in as `0`, `""`, or `'\u{FFFD}'` rather than being reported again.
+    fn parse_literal(&mut self) -> Option<Expr> {
+        let token = self.stream.peek();
+
+        let kind = match (&token.ty, &token.value) {
+            (Type::Number, Some(TokenValue::Number(n))) => ExprKind::Number(*n),
+            (Type::String | Type::RawString, Some(TokenValue::Str(s))) => {
+                ExprKind::String(s.clone())
+            }
+            (Type::Char, Some(TokenValue::Char(c))) => ExprKind::Char(*c),
+            (Type::Number, None) => ExprKind::Number(0.0),
+            (Type::String | Type::RawString, None) => ExprKind::String(String::new()),
+            (Type::Char, None) => ExprKind::Char(char::REPLACEMENT_CHARACTER),
+            _ => return None,
+        };
+
+        Some(Expr {
+            kind,
+            span: self.stream.bump().span,
+        })
+    }
+
+    /// `"hello {name}!"`, the text and what's between each `{` and `}`, parsed as an
+    /// expression of its own
+    fn parse_interpolated(&mut self) -> Expr {
+        let token = self.stream.bump();
+        let span = token.span;
+        let mut parts = Vec::new();
+
+        for segment in token.segments() {
+            match segment {
+                Segment::Text(text, _) => parts.push(StringPart::Text(unescape(text))),
+                Segment::Expr(source, span) => {
+                    if let Some(expr) = self.parse_segment(source, span) {
+                        parts.push(StringPart::Expr(expr));
+                    }
+                }
+            }
+        }
+
+        Expr {
+            kind: ExprKind::Interpolated(parts),
+            span,
+        }
+    }
+
+    /// parses `source`, the inside of a `{...}` at `span`, as one expression
+    ///
+    /// The string's lexer skipped over it, so it's lexed here, and its spans moved to where
+    /// it is in the file.
+    fn parse_segment(&mut self, source: &'src str, span: Span) -> Option<Expr> {
+        let delta = span.start as isize;
+        let mut lexer = Lexer::new(source);
+        let mut tokens = lexer.lexeme();
+
+        for error in &mut lexer.errors {
+            error.span = error.span.shift(delta);
+            self.sink.report(Diagnostic::from(&*error));
+        }
+
+        for token in &mut tokens {
+            token.span = token.span.shift(delta);
+        }
+
+        let outer = std::mem::replace(&mut self.stream, TokenStream::new(tokens));
+        let expr = self.parse_expr();
+
+        if expr.is_some() && !self.stream.is_eof() {
+            let found = self.stream.peek();
+            let error = ParseError {
+                kind: ParseErrorKind::Expected {
+                    expected: Type::CloseBrace,
+                    found: found.ty.clone(),
+                },
+                span: found.span,
+            };
+
+            self.report(error);
+        }
+
+        self.stream = outer;
+        expr
+    }
+
+    /// moves past the `}` matching the `{` at `open`, reporting it missing if the file or
+    /// the next item comes first
+    fn skip_block(&mut self, open: Span) {
+        let mut depth = 1;
+
+        loop {
+            if self.stream.is_eof() || self.at_item() {
+                self.report_unclosed(open);
+                return;
+            }
+
+            match self.stream.bump().ty {
+                Type::OpenBrace | Type::OpenMap => depth += 1,
+                Type::CloseBrace if depth == 1 => return,
+                Type::CloseBrace => depth -= 1,
+                _ => {}
+            }
+        }
+    }
+
+    /// reports the `}` for the `{` at `open` missing, where the next token is
+    fn report_unclosed(&mut self, open: Span) {
+        let found = self.stream.peek();
+        let error = ParseError {
+            kind: ParseErrorKind::Expected {
+                expected: Type::CloseBrace,
+                found: found.ty.clone(),
+            },
+            span: found.span,
+        };
+
+        self.sink
+            .report(Diagnostic::from(error).with_label(open, "the block started here"));
+    }
+}
+
+/// a `Sigil` expression from its token, the name without the `$`
+fn sigil(token: &Token) -> Expr {
+    Expr {
+        kind: ExprKind::Sigil(token.body[1..].to_owned()),
+        span: token.span,
+    }
+}
+
+/// the text of a string segment with its escapes worked out
+///
+/// The string's lexer already reported any it didn't know, those are left out.
+fn unescape(text: &str) -> String {
+    let mut lexer = Lexer::new(text);
+    let mut value = String::new();
+
+    while let Some(ch) = lexer.next() {
+        if ch != '\\' {
+            value.push(ch);
+        } else if let Some(escaped) = lexer.digest_escape() {
+            value.push(escaped);
+        }
+    }
+
+    value
+}
+
+/// the binary operator a token stands for, if it's one
+fn binary_op(ty: &Type) -> Option<BinOp> {
+    let op = match ty {
+        Type::Plus => BinOp::Add,
+        Type::Minus => BinOp::Sub,
+        Type::Multiply => BinOp::Mul,
+        Type::Divide => BinOp::Div,
+        Type::Modulo => BinOp::Rem,
+        Type::EqualEqual => BinOp::Eq,
+        Type::NotEqual => BinOp::Ne,
+        Type::Less => BinOp::Lt,
+        Type::LessEqual => BinOp::Le,
+        Type::Greater => BinOp::Gt,
+        Type::GreaterEqual => BinOp::Ge,
+        Type::And => BinOp::And,
+        Type::Or => BinOp::Or,
+        _ => return None,
+    };
+
+    Some(op)
+}
+
+/// parses `tokens` into a `Program`, collecting the diagnostics
+pub fn parse(tokens: Vec<Token>) -> (Program, Vec<Diagnostic>) {
+    parse_with(tokens, ParserOptions::default())
+}
+
+/// `parse`, with `options`
+pub fn parse_with(tokens: Vec<Token>, options: ParserOptions) -> (Program, Vec<Diagnostic>) {
+    let mut parser = Parser::new_with(tokens, Vec::new(), options);
+    let program = parser.parse_program();
+
+    (program, parser.sink)
+}
+
+/// lexes and parses `source`, with the lex errors first, then the parse errors
+///
+/// ```
+/// # use newton::newton_parse::parse_source;
+/// let (program, diagnostics) = parse_source("new x {\n  logic { ::stdout write ` }\n}");
+///
+/// assert_eq!(program.items.len(), 1);
+/// assert_eq!(diagnostics[0].to_string(), "error[N0003]: unexpected character ```");
+/// ```
+pub fn parse_source(source: &str) -> (Program, Vec<Diagnostic>) {
+    let mut lexer = Lexer::new_with(source, LexerOptions::new().trivia(true));
+    let mut diagnostics = Vec::new();
+
+    let tokens = lexer.lexeme_into(&mut diagnostics);
+    let (program, parsed) = parse(tokens);
+
+    diagnostics.extend(parsed);
+    (program, diagnostics)
+}
+
+/// # Parses
+///
+/// A program, the source it was parsed from, and what was reported about it, kept together
+/// so an editor can `reparse` it as the source changes.
+///
+/// ```
+/// # use newton::newton_lex::Span;
+/// # use newton::newton_parse::Parse;
+/// let mut parse = Parse::new("new a { logic { x } }\nnew b { }");
+///
+/// assert!(parse.reparse(Span::new(16, 17), "y + 1"));
+/// assert_eq!(parse.source, "new a { logic { y + 1 } }\nnew b { }");
+/// assert_eq!(parse, Parse::new("new a { logic { y + 1 } }\nnew b { }"));
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Parse {
+    pub source: String,               // what was parsed
+    pub program: Program,             // what it parsed into
+    pub diagnostics: Vec<Diagnostic>, // what the lexer and the parser reported, in order
+}
+
+impl Parse {
+    /// parses `source`, see `parse_source`
+    pub fn new(source: impl Into<String>) -> Self {
+        let source = source.into();
+        let (program, diagnostics) = parse_source(&source);
+
+        Self {
+            source,
+            program,
+            diagnostics,
+        }
+    }
+
+    /// # Reparsing
+    ///
+    /// Replaces `changed`, a range of the source, with `new_text`, and brings the program and
+    /// the diagnostics up to date, the same as parsing the new source from scratch.
+    ///
+    /// When the edit is inside one block, function, struct, or enum, only that item is lexed
+    /// and parsed again, and put back in place of the old one, with everything after it
+    /// moved along. That's when the new item parses without a mistake, and the old one had
+    /// none either, since a mistake like a missing `}` changes how what comes after it is
+    /// parsed. Anything else parses the whole source again. Gives back true if only the item
+    /// was parsed.
+    ///
+    /// A `changed` that isn't a range of the source, or that starts or ends inside a
+    /// character, isn't an edit at all. Nothing is changed, and it gives back false.
+    pub fn reparse(&mut self, changed: Span, new_text: &str) -> bool {
+        let in_source = changed.start <= changed.end
+            && self.source.is_char_boundary(changed.start)
+            && self.source.is_char_boundary(changed.end);
+
+        if !in_source {
+            return false;
+        }
+
+        let delta = new_text.len() as isize - changed.len() as isize;
+
+        self.source
+            .replace_range(changed.start..changed.end, new_text);
+
+        if self.reparse_item(changed, delta) {
+            return true;
+        }
+
+        let (program, diagnostics) = parse_source(&self.source);
+
+        self.program = program;
+        self.diagnostics = diagnostics;
+        false
+    }
+
+    /// parses the item around `changed` again, see `reparse`, false if it can't be done
+    /// alone, leaving the program as it was
+    fn reparse_item(&mut self, changed: Span, delta: isize) -> bool {
+        let Some(index) = self.program.items.iter().position(|item| {
+            let span = item.span();
+
+            span.start < changed.start && changed.end < span.end
+        }) else {
+            return false;
+        };
+
+        let old = &self.program.items[index];
+        let span = old.span();
+
+        // only these end in a `}`, which nothing after them can change
+        let braced = matches!(
+            old,
+            Item::Block(_) | Item::Fn(_) | Item::Struct(_) | Item::Enum(_)
+        );
+
+        if !braced || self.diagnostics.iter().any(|d| touches(d, span)) {
+            return false;
+        }
+
+        let end = span.end.saturating_add_signed(delta);
+        let options = LexerOptions::new().trivia(true);
+        let mut lexer = Lexer::new_with(&self.source[..end], options).starting_at(span.start);
+        let mut diagnostics = Vec::new();
+
+        let tokens = lexer.lexeme_into(&mut diagnostics);
+        let (mut program, parsed) = parse(tokens);
+
+        let whole =
+            program.items.len() == 1 && program.items[0].span() == Span::new(span.start, end);
+
+        if !diagnostics.is_empty() || !parsed.is_empty() || !whole {
+            return false;
+        }
+
+        // the doc comment is before the item, out of the fragment and out of the edit's reach
+        let mut item = program.items.remove(0);
+
+        match (&mut item, &self.program.items[index]) {
+            (Item::Block(new), Item::Block(old)) => new.doc.clone_from(&old.doc),
+            (Item::Fn(new), Item::Fn(old)) => new.doc.clone_from(&old.doc),
+            (new, old) if std::mem::discriminant(new) != std::mem::discriminant(old) => {
+                return false;
+            }
+            _ => {}
+        }
+
+        let mut shift = Shift(delta);
+        let after = self.program.items.split_off(index + 1);
+
+        self.program.items[index] = item;
+        self.program
+            .items
+            .extend(after.into_iter().map(|item| shift.fold_item(item)));
+        self.program.span = Span::new(0, self.source.len());
+
+        // none of them are about the item, so they're all before or after it
+        for diagnostic in &mut self.diagnostics {
+            if diagnostic.span.start >= span.end {
+                diagnostic.span = diagnostic.span.shift(delta);
+
+                for label in &mut diagnostic.labels {
+                    label.span = label.span.shift(delta);
+                }
+
+                for suggestion in &mut diagnostic.suggestions {
+                    suggestion.span = suggestion.span.shift(delta);
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// if any span of `diagnostic` is in `span` or right next to it
+fn touches(diagnostic: &Diagnostic, span: Span) -> bool {
+    let near = |other: Span| other.start <= span.end && span.start <= other.end;
+
+    near(diagnostic.span)
+        || diagnostic.labels.iter().any(|label| near(label.span))
+        || diagnostic.suggestions.iter().any(|s| near(s.span))
+}
+
+/// moves every span by as many bytes as it holds, for the items after an edit
+struct Shift(isize);
+
+impl Folder for Shift {
+    fn fold_span(&mut self, span: Span) -> Span {
+        span.shift(self.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_diag::apply_suggestions;
+
+    #[test]
+    pub fn test_stream_moves() {
+        let mut stream = TokenStream::new(Lexer::new("a :: b").lexeme());
+
+        assert_eq!(stream.peek().body, "a");
+        assert_eq!(stream.peek_n(1).ty, Type::MemberAccess);
+        assert_eq!(stream.peek_n(9).ty, Type::Eof);
+
+        assert_eq!(stream.bump().body, "a");
+        assert!(stream.eat(Type::Ident).is_none());
+        assert!(stream.eat(Type::MemberAccess).is_some());
+        assert_eq!(stream.expect(Type::Ident).unwrap().body, "b");
+
+        assert!(stream.is_eof());
+        assert_eq!(stream.bump().ty, Type::Eof);
+        assert_eq!(stream.bump().ty, Type::Eof); // stays on the end
+    }
+
+    #[test]
+    pub fn test_stream_expect_error() {
+        let mut stream = TokenStream::new(Lexer::new("new {").lexeme());
+
+        stream.bump();
+
+        let error = stream.expect(Type::Ident).unwrap_err();
+
+        assert_eq!(
+            error.kind,
+            ParseErrorKind::Expected {
+                expected: Type::Ident,
+                found: Type::OpenBrace
+            }
+        );
+        assert_eq!(error.span, Span::new(4, 5));
+        assert_eq!(error.kind.to_string(), "expected Ident, found OpenBrace");
+        assert!(stream.at(Type::OpenBrace)); // didn't move
+    }
+
+    #[test]
+    pub fn test_stream_spans() {
+        let mut stream = TokenStream::new(Lexer::new("new x { }").lexeme());
+
+        assert_eq!(stream.prev_span(), Span::new(0, 0));
+
+        let start = stream.bump().span;
+        stream.bump();
+        stream.bump();
+        stream.bump();
+
+        assert_eq!(stream.prev_span(), Span::new(8, 9));
+        assert_eq!(stream.span_from(start), Span::new(0, 9));
+    }
+
+    #[test]
+    pub fn test_stream_drops_trivia() {
+        let source = "new ; comment\n x";
+        let trivia = Lexer::new_with(source, LexerOptions::new().trivia(true)).lexeme();
+
+        let mut with = TokenStream::new(trivia);
+        let mut without = TokenStream::new(Lexer::new(source).lexeme());
+
+        assert_eq!(with.tokens, without.tokens);
+
+        with.bump();
+        without.bump();
+
+        assert!(with.at_line_start()); // only trivia knows about the line break
+        assert!(!without.at_line_start());
+    }
+
+    #[test]
+    pub fn test_stream_touching() {
+        let mut stream = TokenStream::new(Lexer::new("$::1 :: x").lexeme());
+
+        assert!(!stream.touching()); // nothing before the first token
+        stream.bump();
+        assert!(stream.touching());
+        stream.bump();
+        stream.bump();
+        assert!(!stream.touching());
+    }
+
+    #[test]
+    pub fn test_stream_adds_eof() {
+        let mut tokens = Lexer::new("x").lexeme();
+        tokens.pop();
+
+        let stream = TokenStream::new(tokens);
+
+        assert_eq!(stream.peek_n(1).ty, Type::Eof);
+        assert_eq!(stream.peek_n(1).span, Span::new(1, 1));
+        assert!(TokenStream::new(Vec::new()).is_eof());
+    }
+
+    #[test]
+    pub fn test_parse_program() {
+        let source = "include \"core/\\\\internal\"\nnew x {\n  conditions { any }\n  logic { for $ as v { } }\n}";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+        assert_eq!(program.span, Span::new(0, source.len()));
+        assert_eq!(
+            program.items[0],
+            Item::Include(Include {
+                path: "core/\\internal".to_owned(),
+                span: Span::new(0, 25),
+            })
+        );
+
+        let Item::Block(block) = &program.items[1] else {
+            panic!("{:?}", program.items[1]);
+        };
+
+        assert_eq!(block.name.name, "x");
+        assert_eq!(block.span, Span::new(26, source.len()));
+        assert_eq!(
+            block.sections,
+            vec![
+                Section::Conditions(ConditionsBlock {
+                    conditions: vec![Condition {
+                        kind: ConditionKind::Predicate {
+                            name: Ident {
+                                name: "any".to_owned(),
+                                span: Span::new(49, 52),
+                            },
+                            args: Vec::new(),
+                        },
+                        span: Span::new(49, 52),
+                    }],
+                    span: Span::new(36, 54)
+                }),
+                Section::Logic(LogicBlock {
+                    body: vec![Stmt {
+                        kind: StmtKind::For(ForStmt {
+                            source: Expr {
+                                kind: ExprKind::Sigil(String::new()),
+                                span: Span::new(69, 70),
+                            },
+                            binding: Pattern {
+                                kind: PatternKind::Ident("v".to_owned()),
+                                span: Span::new(74, 75),
+                            },
+                            body: Vec::new(),
+                        }),
+                        span: Span::new(65, 79),
+                    }],
+                    span: Span::new(57, 81)
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_recovers() {
+        let (program, diagnostics) = parse_source("x y\nnew 1 { }\nnew a { oops logic { } }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0101]: expected `new`, `fn`, `include`, or `use`, found Ident",
+                "error[N0100]: expected Ident, found Number",
+                "error[N0102]: expected `conditions` or `logic`, found Ident",
+            ]
+        );
+
+        let [Item::Block(nameless), Item::Block(block)] = &program.items[..] else {
+            panic!("{:?}", program.items);
+        };
+
+        assert_eq!(nameless.name.name, "");
+        assert_eq!(nameless.name.span, Span::new(7, 7));
+        assert_eq!(nameless.span, Span::new(4, 13));
+        assert_eq!(block.name.name, "a");
+        assert_eq!(block.sections.len(), 1);
+    }
+
+    #[test]
+    pub fn test_parse_missing_braces() {
+        let source = "new a\n  logic { }\nnew b {\n  conditions { }\nnew c { logic {\nnew d e\nnew";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected OpenBrace, found ReservedKeyword", // a
+                "error[N0100]: expected CloseBrace, found ReservedKeyword", // b
+                "error[N0100]: expected CloseBrace, found ReservedKeyword", // c's logic
+                "error[N0100]: expected CloseBrace, found ReservedKeyword", // c
+                "error[N0100]: expected OpenBrace, found Ident",           // d
+                "error[N0100]: expected Ident, found Eof",                 // the last one
+            ]
+        );
+
+        let blocks: Vec<&BlockDecl> = program
+            .items
+            .iter()
+            .map(|item| match item {
+                Item::Block(block) => block,
+                other => panic!("{:?}", other),
+            })
+            .collect();
+
+        assert_eq!(blocks.len(), 5);
+        assert_eq!(blocks[0].sections.len(), 1); // still has its logic
+        assert_eq!(blocks[0].span, Span::new(0, 17));
+        assert_eq!(blocks[1].sections.len(), 1);
+        assert_eq!(diagnostics[1].labels[0].span, Span::new(24, 25));
+        assert_eq!(blocks[2].sections.len(), 1);
+        assert!(blocks[3].sections.is_empty());
+        assert_eq!(blocks[4].name.name, "");
+    }
+
+    #[test]
+    pub fn test_parse_unclosed() {
+        let (program, diagnostics) = parse_source("new a {\n  logic {\n");
+
+        assert_eq!(program.items.len(), 1);
+        assert_eq!(diagnostics.len(), 2); // the `logic` block, then the `new` block
+        assert_eq!(diagnostics[0].span, Span::new(18, 18));
+        assert_eq!(diagnostics[0].labels[0].span, Span::new(16, 17));
+        assert_eq!(diagnostics[1].labels[0].span, Span::new(6, 7));
+    }
+
+    #[test]
+    pub fn test_parse_conditions() {
+        let source = "new x { conditions { expect ident \"print\" 1 %override any foo expect } }";
+        let (program, diagnostics) = parse_source(source);
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Conditions(conditions) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+
+        let shown: Vec<String> = conditions
+            .conditions
+            .iter()
+            .map(|c| match &c.kind {
+                ConditionKind::Predicate { name, args } => {
+                    format!("{} {:?}", name.name, args.len())
+                }
+                ConditionKind::Directive(name) => format!("%{}", name.name),
+            })
+            .collect();
+
+        assert_eq!(
+            shown,
+            vec!["expect 3", "%override", "any 0", "foo 0", "expect 0"]
+        );
+        assert_eq!(
+            conditions.conditions[0].span.slice_clamped(source),
+            "expect ident \"print\" 1"
+        );
+        assert_eq!(
+            conditions.conditions[1].span.slice_clamped(source),
+            "%override"
+        );
+
+        let ConditionKind::Predicate { args, .. } = &conditions.conditions[0].kind else {
+            panic!();
+        };
+
+        assert_eq!(args[1].kind, ExprKind::String("print".to_owned()));
+        assert_eq!(args[2].kind, ExprKind::Number(1.0));
+
+        // the last `expect` has no kind of token after it
+        assert_eq!(diagnostics.len(), 1);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[N0100]: expected Ident, found CloseBrace"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_conditions_recover() {
+        let (program, diagnostics) = parse_source("new x { conditions { any 1 { all } %o } }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0103]: expected a condition, found Number",
+                "error[N0103]: expected a condition, found OpenBrace",
+            ]
+        );
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Conditions(conditions) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+
+        assert_eq!(conditions.conditions.len(), 2); // any, %o
+    }
+
+    /// the statements of the first logic block in `source`, which has to parse cleanly
+    fn logic(source: &str) -> Vec<Stmt> {
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+
+        block
+            .sections
+            .iter()
+            .find_map(|section| match section {
+                Section::Logic(logic) => Some(logic.body.clone()),
+                Section::Conditions(_) => None,
+            })
+            .unwrap()
+    }
+
+    #[test]
+    pub fn test_parse_logic() {
+        let source = "new x {\n  logic {\n    collect as $args\n    for $ as var {\n      ::stdout write_newline var $::1 \"!\"\n    }\n    ::stdout write\n    var\n  }\n}";
+        let stmts = logic(source);
+        let spans: Vec<&str> = stmts.iter().map(|s| s.span.slice_clamped(source)).collect();
+
+        assert_eq!(
+            spans,
+            vec![
+                "collect as $args",
+                "for $ as var {\n      ::stdout write_newline var $::1 \"!\"\n    }",
+                "::stdout write",
+                "var",
+            ]
+        );
+
+        let StmtKind::Collect(collect) = &stmts[0].kind else {
+            panic!("{:?}", stmts[0]);
+        };
+
+        assert_eq!(collect.target.name, "args");
+
+        let StmtKind::For(for_stmt) = &stmts[1].kind else {
+            panic!("{:?}", stmts[1]);
+        };
+        let StmtKind::Call(call) = &for_stmt.body[0].kind else {
+            panic!("{:?}", for_stmt.body);
+        };
+
+        assert_eq!(call.module.name, "stdout");
+        assert_eq!(call.function.name, "write_newline");
+        assert_eq!(call.args.len(), 3);
+        assert_eq!(call.args[1].span.slice_clamped(source), "$::1");
+        assert_eq!(
+            call.args[1].kind,
+            ExprKind::Member {
+                base: Box::new(Expr {
+                    kind: ExprKind::Sigil(String::new()),
+                    span: Span::new(91, 92)
+                }),
+                member: Box::new(Expr {
+                    kind: ExprKind::Number(1.0),
+                    span: Span::new(94, 95)
+                }),
+            }
+        );
+
+        let StmtKind::Call(call) = &stmts[2].kind else {
+            panic!("{:?}", stmts[2]);
+        };
+
+        assert!(call.args.is_empty()); // `var` is on the next line
+    }
+
+    #[test]
+    pub fn test_parse_call_args() {
+        let source = "new x { logic {\n  ::stdout write n - 1 $::2 ::str upper var x\n} }";
+        let stmts = logic(source);
+
+        let StmtKind::Call(call) = &stmts[0].kind else {
+            panic!("{:?}", stmts[0]);
+        };
+        let args: Vec<&str> = call
+            .args
+            .iter()
+            .map(|a| a.span.slice_clamped(source))
+            .collect();
+
+        assert_eq!(args, vec!["n - 1", "$::2", "::str upper var x"]);
+
+        let ExprKind::Call(nested) = &call.args[2].kind else {
+            panic!("{:?}", call.args[2]);
+        };
+
+        assert_eq!(nested.function.name, "upper");
+        assert_eq!(nested.args.len(), 2);
+
+        // a broken argument gives up on the line, and only the line
+        let (program, diagnostics) =
+            parse_source("new x { logic {\n  ::stdout write a + * b c\n  ::stdout write d\n} }");
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(logic.body.len(), 1);
+    }
+
+    #[test]
+    pub fn test_parse_logic_recover() {
+        let (program, diagnostics) =
+            parse_source("new x { logic { } } new y { logic { = 1 collect $ ::stdout } }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0104]: expected a statement, found Equal",
+                "error[N0100]: expected `as`, found Sigil",
+                "error[N0100]: expected Ident, found CloseBrace",
+            ]
+        );
+        assert_eq!(program.items.len(), 2);
+    }
+
+    #[test]
+    pub fn test_parse_let() {
+        let source = "new x { logic {\n  let a = 1 + 2\n  let b\n  let c 3\n  let = 4\n  let\n} }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected Equal, found Number",
+                "error[N0100]: expected Ident, found Equal",
+                "error[N0100]: expected Ident, found CloseBrace",
+            ]
+        );
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let lets: Vec<(&str, Option<&str>)> = logic
+            .body
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Let(stmt) => Some((
+                    stmt.pattern.bindings()[0],
+                    stmt.value.as_ref().map(|v| v.span.slice_clamped(source)),
+                )),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            lets,
+            vec![
+                ("a", Some("1 + 2")),
+                ("b", None),
+                ("c", Some("3")),
+                ("", Some("4"))
+            ]
+        );
+        assert_eq!(logic.body[0].span.slice_clamped(source), "let a = 1 + 2");
+        assert!(apply_suggestions(source, &diagnostics).contains("let c = 3"));
+    }
+
+    #[test]
+    pub fn test_parse_assign() {
+        let stmts = logic(
+            "new x { logic {\n  x = 1\n  $::1 += 2 * 3\n  $args %= 2\n  xs[0] = 1\n  p.q[i + 1] += 1\n} }",
+        );
+
+        let ops: Vec<Option<BinOp>> = stmts
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Assign(assign) => Some(assign.op),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(
+            ops,
+            vec![
+                None,
+                Some(BinOp::Add),
+                Some(BinOp::Rem),
+                None,
+                Some(BinOp::Add)
+            ]
+        );
+
+        // an `=` on the next line doesn't assign
+        let (_, diagnostics) = parse_source("new x { logic {\n  y\n  = 4\n} }");
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[N0104]: expected a statement, found Equal"
+        );
+
+        let source = "new x { logic { 1 + x = 2 } }";
+        let (program, diagnostics) = parse_source(source);
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[N0106]: can't assign to this expression"
+        );
+        assert_eq!(diagnostics[0].span.slice_clamped(source), "1 + x");
+        assert_eq!(diagnostics[0].labels[0].span.slice_clamped(source), "=");
+
+        // kept anyway
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+
+        assert!(matches!(logic.body[0].kind, StmtKind::Assign(_)));
+    }
+
+    #[test]
+    pub fn test_parse_if() {
+        let source = "new x { logic {\n  if a > 1 {\n    b\n  } else if c {\n  }\n  else {\n    d\n    e\n  }\n  if f { }\n} }";
+        let stmts = logic(source);
+
+        assert_eq!(stmts.len(), 2);
+
+        let StmtKind::If(first) = &stmts[0].kind else {
+            panic!("{:?}", stmts[0]);
+        };
+
+        assert_eq!(first.condition.span.slice_clamped(source), "a > 1");
+        assert_eq!(first.then.len(), 1);
+
+        let Some(Else::If(chained)) = &first.otherwise else {
+            panic!("{:?}", first.otherwise);
+        };
+        let StmtKind::If(second) = &chained.kind else {
+            panic!("{:?}", chained);
+        };
+
+        assert!(chained.span.slice_clamped(source).starts_with("if c {"));
+        assert!(second.then.is_empty());
+        assert!(matches!(&second.otherwise, Some(Else::Block(body)) if body.len() == 2));
+
+        let StmtKind::If(last) = &stmts[1].kind else {
+            panic!("{:?}", stmts[1]);
+        };
+
+        assert_eq!(last.otherwise, None);
+    }
+
+    #[test]
+    pub fn test_parse_if_recover() {
+        let source = "new x { logic {\n  if a\n  b\n  else { c }\n  if { }\n} }";
+        let (_, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected OpenBrace, found Ident",
+                "error[N0104]: expected a statement, found ReservedKeyword",
+                "error[N0105]: expected an expression, found OpenBrace",
+            ]
+        );
+        assert_eq!(diagnostics[0].labels[0].span.slice_clamped(source), "if");
+        assert_eq!(diagnostics[1].children.len(), 1); // where an `else` goes
+    }
+
+    #[test]
+    pub fn test_parse_while() {
+        let source = "new x { logic {\n  while n > 0 {\n    n -= 1\n  }\n  while n\n  m\n} }";
+        let (program, diagnostics) = parse_source(source);
+
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[N0100]: expected OpenBrace, found Ident"
+        );
+        assert_eq!(diagnostics[0].labels[0].message, "the loop needs a body");
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let StmtKind::While(while_stmt) = &logic.body[0].kind else {
+            panic!("{:?}", logic.body);
+        };
+
+        assert_eq!(while_stmt.condition.span.slice_clamped(source), "n > 0");
+        assert!(matches!(while_stmt.body[0].kind, StmtKind::Assign(_)));
+        assert_eq!(logic.body.len(), 2); // and `m`, on the line after
+    }
+
+    #[test]
+    pub fn test_parse_match() {
+        let source = "new x { logic {\n  match $::1 {\n    1 => \"one\",\n    -2 => { a\n      b }\n    'c' => ::stdout write c\n    _ => 0,\n  }\n} }";
+        let stmts = logic(source);
+
+        let StmtKind::Expr(Expr {
+            kind: ExprKind::Match(matched),
+            span,
+        }) = &stmts[0].kind
+        else {
+            panic!("{:?}", stmts[0]);
+        };
+
+        assert!(span.slice_clamped(source).starts_with("match $::1 {"));
+        assert_eq!(matched.scrutinee.span.slice_clamped(source), "$::1");
+
+        let patterns: Vec<&PatternKind> = matched.arms.iter().map(|a| &a.pattern.kind).collect();
+
+        assert_eq!(
+            patterns,
+            vec![
+                &PatternKind::Number(1.0),
+                &PatternKind::Number(-2.0),
+                &PatternKind::Char('c'),
+                &PatternKind::Wildcard,
+            ]
+        );
+        assert_eq!(matched.arms[1].body.len(), 2);
+        assert_eq!(matched.arms[0].span.slice_clamped(source), "1 => \"one\"");
+        assert!(matches!(matched.arms[2].body[0].kind, StmtKind::Call(_)));
+    }
+
+    #[test]
+    pub fn test_parse_match_recover() {
+        let source =
+            "new x { logic {\n  match x {\n    $ => 1, 2 3\n    3 => { 3 }\n    4 => 4\n  }\n} }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0107]: expected a pattern, found Sigil",
+                "error[N0100]: expected FatArrow, found Number",
+            ]
+        );
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let StmtKind::Expr(Expr {
+            kind: ExprKind::Match(matched),
+            ..
+        }) = &logic.body[0].kind
+        else {
+            panic!("{:?}", logic.body);
+        };
+
+        assert_eq!(matched.arms.len(), 2); // 3 and 4
+    }
+
+    #[test]
+    pub fn test_parse_string_operators() {
+        assert_eq!(grouped("\"n = \" + n"), "(\"n = \" + n)");
+        assert_eq!(grouped("a + \"b\" < \"c\""), "((a + \"b\") < \"c\")");
+
+        let source = "new x { logic {\n  let a = \"ab\" - 1\n  let b = x * \"y\" && \"z\"\n  let c = \"a{b}\" - 1\n} }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0108]: can't use `-` on a string",
+                "error[N0108]: can't use `*` on a string",
+                "error[N0108]: can't use `&&` on a string",
+                "error[N0108]: can't use `-` on a string",
+            ]
+        );
+        assert_eq!(diagnostics[0].span.slice_clamped(source), "-");
+        assert_eq!(
+            diagnostics[0].labels[0].span.slice_clamped(source),
+            "\"ab\""
+        );
+        assert_eq!(diagnostics[2].labels[0].span.slice_clamped(source), "\"z\"");
+        assert_eq!(
+            diagnostics[3].labels[0].span.slice_clamped(source),
+            "\"a{b}\""
+        );
+
+        // both lets are still there, as they were written
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+
+        assert_eq!(logic.body.len(), 3);
+        assert!(BinOp::Add.takes_strings() && BinOp::Le.takes_strings());
+        assert!(!BinOp::Rem.takes_strings());
+    }
+
+    #[test]
+    pub fn test_parse_max_depth() {
+        fn deep(source: &str, max: usize) -> Vec<String> {
+            let tokens = Lexer::new_with(source, LexerOptions::new().trivia(true)).lexeme();
+            let (_, diagnostics) = parse_with(tokens, ParserOptions::new().max_depth(max));
+
+            diagnostics.iter().map(|d| d.to_string()).collect()
+        }
+
+        let parens = format!(
+            "new x {{ logic {{ let a = {}1{} }} }}",
+            "(".repeat(8),
+            ")".repeat(8)
+        );
+        let blocks = format!(
+            "new x {{ logic {{ {}{} }} }}",
+            "if x { ".repeat(8),
+            "} ".repeat(8)
+        );
+
+        assert!(deep(&parens, 12).is_empty());
+        assert_eq!(
+            deep(&parens, 6),
+            vec!["error[N0110]: program too deeply nested"]
+        );
+        assert!(deep(&blocks, 12).is_empty());
+        assert_eq!(
+            deep(&blocks, 6),
+            vec!["error[N0110]: program too deeply nested"]
+        );
+        assert_eq!(
+            deep("new x { logic { let a = - - - - 1\nlet (((b))) = 2 } }", 3),
+            vec!["error[N0110]: program too deeply nested"; 2]
+        );
+
+        // far past the default, without running out of stack
+        let source = format!("new x {{ logic {{ let a = {}1 }} }}", "(".repeat(100_000));
+        let (_, diagnostics) = parse_source(&source);
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[N0110]: program too deeply nested"
+        );
+        assert_eq!(diagnostics.len(), 1);
+
+        let source = format!("new x {{ logic {{ {} }} }}", "while x {\n".repeat(100_000));
+
+        assert_eq!(
+            parse_source(&source).1[0].to_string(),
+            "error[N0110]: program too deeply nested"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_recover_stmts() {
+        let source = "new x { logic {\n  let a = (1 2) + 3\n  let b = 1 + ] ] ] let c = 2\n  ) ) )\n  let d = \"x\" - 1\n  let e = 5\n} }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0109]: unclosed parenthesis",
+                "error[N0105]: expected an expression, found CloseBracket",
+                "error[N0104]: expected a statement, found CloseParen",
+                "error[N0108]: can't use `-` on a string",
+            ]
+        );
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let names: Vec<&str> = logic
+            .body
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Let(stmt) => Some(stmt.pattern.bindings()[0]),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(names, vec!["c", "d", "e"]);
+    }
+
+    #[test]
+    pub fn test_parse_recover_at_line_start() {
+        // each is broken right at its first token, which is at the start of a line
+        let broken = ["0xFFFFFFFFFFFFFFFFFF", "0x", "1e", "'\\q'"];
+
+        for token in broken {
+            let source = format!("new a {{\n logic {{\n {}\n let b = 1\n }}\n}}", token);
+            let (program, diagnostics) = parse_source(&source);
+
+            assert!(!diagnostics.is_empty(), "{:?}", source);
+
+            let Item::Block(block) = &program.items[0] else {
+                panic!("{:?}", program.items);
+            };
+            let Section::Logic(logic) = &block.sections[0] else {
+                panic!("{:?}", block.sections);
+            };
+
+            assert!(
+                matches!(
+                    logic.body.last().map(|stmt| &stmt.kind),
+                    Some(StmtKind::Let(_))
+                ),
+                "{:?}",
+                source
+            );
+        }
+    }
+
+    #[test]
+    pub fn test_parse_interpolated_strings() {
+        let source = "new x { logic { ::stdout write_newline \"hello {name}, {n + 1}\\{!\\}\" } }";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let StmtKind::Call(call) = &logic.body[0].kind else {
+            panic!("{:?}", logic.body);
+        };
+        let ExprKind::Interpolated(parts) = &call.args[0].kind else {
+            panic!("{:?}", call.args);
+        };
+
+        assert_eq!(
+            call.args[0].span.slice_and_dice(source),
+            Some("\"hello {name}, {n + 1}\\{!\\}\"")
+        );
+
+        let parts: Vec<String> = parts
+            .iter()
+            .map(|part| match part {
+                StringPart::Text(text) => format!("{:?}", text),
+                StringPart::Expr(expr) => expr.span.slice_clamped(source).to_owned(),
+            })
+            .collect();
+
+        assert_eq!(
+            parts,
+            vec!["\"hello \"", "name", "\", \"", "n + 1", "\"{!}\""]
+        );
+
+        let cases = [
+            (
+                "\"{a b}\"",
+                "error[N0100]: expected CloseBrace, found Ident",
+            ),
+            ("\"{}\"", "error[N0105]: expected an expression, found Eof"),
+            ("\"{`}\"", "error[N0003]: unexpected character ```"),
+        ];
+
+        for (string, expected) in cases {
+            let source = format!("new x {{ logic {{ let a = {}\nlet b = 1 }} }}", string);
+            let (_, diagnostics) = parse_source(&source);
+            let at = source.find('{').unwrap();
+
+            assert_eq!(diagnostics[0].to_string(), expected, "{}", string);
+            assert!(diagnostics[0].span.start > at, "{:?}", diagnostics[0]);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_broken_literals() {
+        let source = "new x { logic {\n  let a = 1e + 1\n  let b = '\\q'\n  ::stdout write 0b\n} }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        // only what the lexer said, nothing from the parser on top of it
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0002]: expected digits in the exponent",
+                "error[N0005]: unknown escape sequence `\\q`",
+                "error[N0002]: expected digits after `0b`",
+            ]
+        );
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let values: Vec<&ExprKind> = logic
+            .body
+            .iter()
+            .map(|stmt| match &stmt.kind {
+                StmtKind::Let(stmt) => &stmt.value.as_ref().unwrap().kind,
+                StmtKind::Call(call) => &call.args[0].kind,
+                _ => panic!("{:?}", stmt),
+            })
+            .collect();
+
+        assert!(matches!(values[0], ExprKind::Binary { lhs, .. }
+            if lhs.kind == ExprKind::Number(0.0)));
+        assert_eq!(values[1], &ExprKind::Char(char::REPLACEMENT_CHARACTER));
+        assert_eq!(values[2], &ExprKind::Number(0.0));
+    }
+
+    #[test]
+    pub fn test_parse_parens() {
+        assert_eq!(grouped("(1 + 2) * 3"), "((1 + 2) * 3)");
+        assert_eq!(grouped("-(a - b)"), "(-(a - b))");
+        assert_eq!(grouped("((x))"), "x");
+        assert_eq!(grouped("(a ? b : c)[0]"), "(a ? b : c)[0]");
+
+        let source = "new x { logic {\n  let a = (1 + 2) * 3\n  (b) = 4\n} }";
+        let logic = logic(source);
+        let StmtKind::Let(stmt) = &logic[0].kind else {
+            panic!("{:?}", logic);
+        };
+
+        assert_eq!(
+            stmt.value.as_ref().unwrap().span.slice_clamped(source),
+            "(1 + 2) * 3"
+        );
+        assert!(
+            matches!(&logic[1].kind, StmtKind::Assign(assign) if assign.target.is_assignable())
+        );
+
+        let source = "new x { logic {\n  let a = (1 + 2\n  let b = (1 2)\n} }";
+        let (_, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0109]: unclosed parenthesis",
+                "error[N0109]: unclosed parenthesis"
+            ]
+        );
+        assert_eq!(diagnostics[0].span, Span::new(26, 27));
+        assert_eq!(diagnostics[0].labels[0].span.slice_clamped(source), "let");
+        assert_eq!(diagnostics[1].labels[0].span.slice_clamped(source), "2");
+    }
+
+    #[test]
+    pub fn test_parse_conditionals() {
+        assert_eq!(
+            grouped("x > 1 ? \"many\" : \"few\""),
+            "((x > 1) ? \"many\" : \"few\")"
+        );
+        assert_eq!(grouped("a ? b : c ? d : e"), "(a ? b : (c ? d : e))");
+        assert_eq!(grouped("a ? b ? c : d : e"), "(a ? (b ? c : d) : e)");
+        assert_eq!(grouped("a ? 0..n : m"), "(a ? (0..n) : m)");
+        assert_eq!(grouped("#{k: a ? b : c}"), "#{k: (a ? b : c)}");
+
+        let (_, diagnostics) = parse_source("new x { logic { let y = a ? b\nlet z = 1 } }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec!["error[N0100]: expected Colon, found ReservedKeyword"]
+        );
+        assert_eq!(diagnostics[0].labels[0].message, "for this `?`");
+    }
+
+    #[test]
+    pub fn test_parse_ranges() {
+        assert_eq!(grouped("0..n - 1"), "(0..(n - 1))");
+        assert_eq!(grouped("1..=10"), "(1..=10)");
+        assert_eq!(grouped("-a..b * 2"), "((-a)..(b * 2))");
+
+        let source = "new x { logic {\n  for 1..10 as i { }\n  let r = 0..=n\n} }";
+        let logic = logic(source);
+        let StmtKind::For(for_stmt) = &logic[0].kind else {
+            panic!("{:?}", logic);
+        };
+
+        assert!(matches!(
+            for_stmt.source.kind,
+            ExprKind::Range {
+                inclusive: false,
+                ..
+            }
+        ));
+        assert_eq!(for_stmt.source.span.slice_clamped(source), "1..10");
+
+        let (_, diagnostics) = parse_source("new x { logic { let r = 1..2..3 } }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages[0],
+            "error[N0105]: expected an expression, found DotDot"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_destructuring() {
+        let source = "new x { logic {\n  for $ as (key, value) { }\n  let (a, (b, _),) = pair\n  match x { (1, y) => y, z => z }\n} }";
+        let logic = logic(source);
+
+        let [Stmt {
+            kind: StmtKind::For(for_stmt),
+            ..
+        }, Stmt {
+            kind: StmtKind::Let(let_stmt),
+            ..
+        }, Stmt {
+            kind:
+                StmtKind::Expr(Expr {
+                    kind: ExprKind::Match(matched),
+                    ..
+                }),
+            ..
+        }] = &logic[..]
+        else {
+            panic!("{:?}", logic);
+        };
+
+        assert_eq!(for_stmt.binding.bindings(), vec!["key", "value"]);
+        assert_eq!(for_stmt.binding.span.slice_clamped(source), "(key, value)");
+        assert_eq!(let_stmt.pattern.bindings(), vec!["a", "b"]);
+        assert!(matches!(
+            &let_stmt.pattern.kind,
+            PatternKind::Tuple(items) if matches!(items[1].kind, PatternKind::Tuple(_))
+        ));
+        assert_eq!(matched.arms[0].pattern.bindings(), vec!["y"]);
+        assert_eq!(
+            matched.arms[1].pattern.kind,
+            PatternKind::Ident("z".to_owned())
+        );
+
+        let (_, diagnostics) =
+            parse_source("new x { logic {\n  for $ as (a b) { }\n  let (a, $) = 1\n} }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected CloseParen, found Ident",
+                "error[N0107]: expected a pattern, found Sigil",
+            ]
+        );
+        assert_eq!(diagnostics[0].labels[0].message, "the pattern started here");
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    pub fn test_parse_serde_round_trip() {
+        let source = "include \"core\"\nuse core::str\nnew struct P { x, y }\nnew enum S { A }\nfn f(a) {\n  if a { return } else { a -= 1 }\n  return match a { (x, 1) => x, S::A => 'c', _ => -2.5 }\n}\nnew x {\n  conditions { expect ident \"print\" %override }\n  logic {\n    collect as $\n    for 1..=3 as i { ::stdout write $::1 i }\n    p.x = c ? #{ \"k\": [g(1)[0]] } : fn (x) { }\n  }\n}";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let json = serde_json::to_string(&program).unwrap();
+        let back: Program = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(back, program);
+
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+
+        assert_eq!(parsed["items"][0]["Include"]["path"], "core");
+        assert_eq!(parsed["items"][0]["Include"]["span"]["start"], 0);
+    }
+
+    #[test]
+    pub fn test_reparse() {
+        let source = ";;; the first\nnew a {\n  logic { x }\n}\n\n;;; a function\nfn f(p) { return p }\n; between\nnew b { logic { let } }\nnew c { logic { y(1) } }\nuse m::n\n";
+
+        // (what's replaced, with what, if only an item is parsed again)
+        let edits = [
+            ("x", "x + 1", true),        // grows
+            ("return p", "p", true),     // shrinks
+            ("y(1)", "y(1, 2)", true),   // after the broken item
+            ("x", "x }", false),         // breaks the block
+            ("let", "let z = 1", false), // in the broken one
+            ("between", "and", false),   // between items
+            ("a function", "f", false),  // in a doc comment
+            ("m::n", "m::o", false),     // in an item without a `}`
+            ("new a {\n", "", false),    // the start of an item
+            ("(1)", "(\"", false),       // an unclosed string, to the end of the file
+        ];
+
+        for (old, new, incremental) in edits {
+            let at = source.find(old).unwrap();
+            let changed = Span::new(at, at + old.len());
+            let edited = format!("{}{}{}", &source[..at], new, &source[changed.end..]);
+
+            let mut parse = Parse::new(source);
+
+            assert_eq!(parse.reparse(changed, new), incremental, "{:?}", edited);
+            assert_eq!(parse, Parse::new(edited.clone()), "{:?}", edited);
+        }
+    }
+
+    #[test]
+    pub fn test_reparse_many() {
+        let mut parse = Parse::new("new a { logic { x } }\nnew b { logic { 1 } }");
+
+        // typing into the first block, a character at a time, a mistake until the `1` is in,
+        // and the whole source parsed again until the one after it
+        let typed = [
+            ("y", true),
+            (" ", true),
+            ("+", false),
+            (" ", false),
+            ("1", false),
+            ("0", true),
+        ];
+
+        for (i, (text, incremental)) in typed.into_iter().enumerate() {
+            let at = 17 + i;
+
+            assert_eq!(
+                parse.reparse(Span::new(at, at), text),
+                incremental,
+                "{}",
+                text
+            );
+        }
+
+        assert_eq!(
+            parse,
+            Parse::new("new a { logic { xy + 10 } }\nnew b { logic { 1 } }")
+        );
+    }
+
+    #[test]
+    pub fn test_reparse_bad_span() {
+        let source = "new a { logic { \"é\" } }";
+        let mut parse = Parse::new(source);
+        let e = source.find('é').unwrap();
+
+        for changed in [
+            Span::new(0, source.len() + 1),
+            Span::new(source.len() + 1, source.len() + 2),
+            Span::new(e + 1, e + 2),
+            Span::new(e, e + 1),
+            Span::new(5, 3),
+        ] {
+            assert!(!parse.reparse(changed, "x"), "{:?}", changed);
+            assert_eq!(parse, Parse::new(source), "{:?}", changed);
+        }
+    }
+
+    #[test]
+    pub fn test_parse_fn() {
+        let source = "fn greet(name, greeting,) {\n  ::stdout write greeting name\n}\nfn nothing() { }\nnew x { logic { greet(\"hi\", f(1)(2)) } }";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let [Item::Fn(greet), Item::Fn(nothing), Item::Block(block)] = &program.items[..] else {
+            panic!("{:?}", program.items);
+        };
+        let params: Vec<&str> = greet.params.iter().map(|p| p.name.as_str()).collect();
+
+        assert_eq!(greet.name.name, "greet");
+        assert_eq!(params, vec!["name", "greeting"]);
+        assert_eq!(greet.body.len(), 1);
+        assert!(greet.span.slice_clamped(source).ends_with("name\n}"));
+        assert!(nothing.params.is_empty());
+
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let StmtKind::Expr(call) = &logic.body[0].kind else {
+            panic!("{:?}", logic.body);
+        };
+        let ExprKind::FnCall { callee, args } = &call.kind else {
+            panic!("{:?}", call);
+        };
+
+        assert_eq!(callee.kind, ExprKind::Ident("greet".to_owned()));
+        assert_eq!(args.len(), 2);
+        assert_eq!(args[1].span.slice_clamped(source), "f(1)(2)");
+        assert!(matches!(&args[1].kind, ExprKind::FnCall { callee, .. }
+            if matches!(callee.kind, ExprKind::FnCall { .. })));
+    }
+
+    #[test]
+    pub fn test_parse_docs() {
+        let source = ";;; # Print\n;;;\n;;;prints things\nnew print { logic {\n  ;;; not docs\n  x\n} }\n; plain\n;;; says hello\nfn greet() { }\n;;; a point\nnew struct P { x }\nnew plain { }";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let [Item::Block(print), Item::Fn(greet), Item::Struct(_), Item::Block(plain)] =
+            &program.items[..]
+        else {
+            panic!("{:?}", program.items);
+        };
+        let doc = print.doc.as_ref().unwrap();
+
+        assert_eq!(doc.text, "# Print\n\nprints things");
+        assert_eq!(
+            doc.span.slice_clamped(source),
+            ";;; # Print\n;;;\n;;;prints things"
+        );
+        assert_eq!(
+            print.span.slice_clamped(source).lines().next(),
+            Some("new print { logic {")
+        );
+        assert_eq!(greet.doc.as_ref().unwrap().text, "says hello");
+
+        // the one before the struct belongs to nothing, so the block after it has none
+        assert_eq!(plain.doc, None);
+    }
+
+    #[test]
+    pub fn test_parse_fn_recover() {
+        let source = "fn (a b) { }\nfn g { }\nnew x { logic { g(1 2) } }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected Ident, found OpenParen",
+                "error[N0100]: expected CloseParen, found Ident",
+                "error[N0100]: expected OpenParen, found OpenBrace",
+                "error[N0100]: expected CloseParen, found Number",
+            ]
+        );
+        assert_eq!(diagnostics[3].labels[0].span.slice_clamped(source), "(");
+        assert!(matches!(&program.items[..], [Item::Block(_)]));
+    }
+
+    #[test]
+    pub fn test_parse_control_flow() {
+        let source = "fn f(x) {\n  while x {\n    break\n    continue\n  }\n  return x + 1\n  return\n  x\n}\nnew b { logic { break } }";
+        let (program, diagnostics) = parse_source(source);
+
+        // a `break` outside a loop isn't the parser's to report
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let Item::Fn(function) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let StmtKind::While(while_stmt) = &function.body[0].kind else {
+            panic!("{:?}", function.body);
+        };
+
+        assert_eq!(while_stmt.body[0].kind, StmtKind::Break);
+        assert_eq!(while_stmt.body[1].kind, StmtKind::Continue);
+
+        let StmtKind::Return(Some(value)) = &function.body[1].kind else {
+            panic!("{:?}", function.body);
+        };
+
+        assert_eq!(value.span.slice_clamped(source), "x + 1");
+        assert_eq!(function.body[2].kind, StmtKind::Return(None)); // `x` is on the next line
+        assert_eq!(function.body.len(), 4);
+    }
+
+    #[test]
+    pub fn test_parse_lists() {
+        assert_eq!(grouped("[1, 2 + 3, [a],]"), "[1, (2 + 3), [a]]");
+        assert_eq!(grouped("[]"), "[]");
+        assert_eq!(grouped("xs[i + 1][0]"), "xs[(i + 1)][0]");
+        assert_eq!(grouped("[[1]][0]"), "[[1]][0]");
+
+        let stmts = logic("new x { logic {\n  ::stdout write xs [0]\n  [1,\n   2]\n} }");
+
+        let StmtKind::Call(call) = &stmts[0].kind else {
+            panic!("{:?}", stmts[0]);
+        };
+
+        assert_eq!(call.args.len(), 2); // not touching, so not an index
+        assert_eq!(stmts.len(), 2); // a list can go over lines
+
+        let source = "new x { logic {\n  [1 2]\n  xs[0\n  y\n} }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected CloseBracket, found Number",
+                "error[N0100]: expected CloseBracket, found Ident",
+            ]
+        );
+        assert_eq!(diagnostics[1].labels[0].message, "the index started here");
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+
+        assert_eq!(logic.body.len(), 1); // just `y`
+    }
+
+    #[test]
+    pub fn test_parse_maps() {
+        assert_eq!(grouped("#{}"), "#{}");
+        assert_eq!(
+            grouped("#{ \"a\": 1 + 2, b: #{ 1: [c] }, }"),
+            "#{\"a\": (1 + 2), b: #{1: [c]}}"
+        );
+        assert_eq!(grouped("#{ k: v }[k]"), "#{k: v}[k]");
+
+        let stmts = logic("new x { logic {\n  m = #{\n    \"a\": 1,\n    \"b\": 2,\n  }\n  n\n} }");
+
+        assert_eq!(stmts.len(), 2); // a map can go over lines
+
+        let source = "new x { logic {\n  #{ a 1, b: 2 }\n  #{ a: 1 b: 2 }\n  y\n} }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected Colon, found Number",
+                "error[N0100]: expected CloseBrace, found Ident",
+            ]
+        );
+        assert_eq!(diagnostics[1].labels[0].message, "the map started here");
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+
+        assert_eq!(logic.body.len(), 1); // just `y`
+
+        // a broken map inside another is skipped, and the outer one still closes
+        let (_, diagnostics) = parse_source("new x { logic { #{ a: #{ b 1 } } } }");
+
+        assert_eq!(diagnostics.len(), 1, "{:?}", diagnostics);
+
+        // unclosed, the map is what's reported first
+        let (_, diagnostics) = parse_source("new x { logic { #{ a: 1");
+
+        assert_eq!(diagnostics.len(), 3, "{:?}", diagnostics);
+        assert_eq!(diagnostics[0].labels[0].span, Span::new(16, 18));
+    }
+
+    #[test]
+    pub fn test_parse_lambdas() {
+        let source =
+            "new x { logic {\n  ys = map(xs, fn (x) { return x * 2 })\n  fn () {\n    y\n  }\n} }";
+        let stmts = logic(source);
+
+        assert_eq!(stmts.len(), 2); // a lambda on its own is a statement, not an item
+
+        let StmtKind::Assign(assign) = &stmts[0].kind else {
+            panic!("{:?}", stmts[0]);
+        };
+        let ExprKind::FnCall { args, .. } = &assign.value.kind else {
+            panic!("{:?}", assign.value);
+        };
+        let ExprKind::Lambda(lambda) = &args[1].kind else {
+            panic!("{:?}", args[1]);
+        };
+
+        assert_eq!(
+            args[1].span.slice_clamped(source),
+            "fn (x) { return x * 2 }"
+        );
+        assert_eq!(lambda.params[0].name, "x");
+        assert!(matches!(lambda.body[0].kind, StmtKind::Return(Some(_))));
+
+        let (_, diagnostics) =
+            parse_source("new x { logic {\n  f = fn x { }\n  g = fn (a b) { c }\n} }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected OpenParen, found Ident",
+                "error[N0100]: expected CloseParen, found Ident",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_uses() {
+        let source = "use core::str\nuse io\nnew x { }";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let [Item::Use(nested), Item::Use(flat), Item::Block(_)] = &program.items[..] else {
+            panic!("{:?}", program.items);
+        };
+        let path: Vec<&str> = nested.path.iter().map(|n| n.name.as_str()).collect();
+
+        assert_eq!(path, vec!["core", "str"]);
+        assert_eq!(nested.span.slice_clamped(source), "use core::str");
+        assert_eq!(flat.path.len(), 1);
+
+        // a `::` with space around it ends the path, and what's left isn't an item
+        let (program, diagnostics) = parse_source("use core ::str\nuse \"io\"\nnew x { }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0101]: expected `new`, `fn`, `include`, or `use`, found MemberAccess",
+                "error[N0100]: expected Ident, found String",
+            ]
+        );
+        assert_eq!(program.items.len(), 2);
+    }
+
+    #[test]
+    pub fn test_parse_structs() {
+        let source = "new struct Point {\n  x, y,\n  z\n}\nnew struct { }\nnew x { logic { p.x.y = q[0].z } }";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let [Item::Struct(point), Item::Block(named), Item::Block(block)] = &program.items[..]
+        else {
+            panic!("{:?}", program.items);
+        };
+        let fields: Vec<&str> = point.fields.iter().map(|f| f.name.as_str()).collect();
+
+        assert_eq!(point.name.name, "Point");
+        assert_eq!(fields, vec!["x", "y", "z"]);
+        assert_eq!(
+            point.span.slice_clamped(source),
+            "new struct Point {\n  x, y,\n  z\n}"
+        );
+        assert_eq!(named.name.name, "struct"); // without a name, it's just a block
+
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let StmtKind::Assign(assign) = &logic.body[0].kind else {
+            panic!("{:?}", logic.body);
+        };
+
+        assert_eq!(assign.target.span.slice_clamped(source), "p.x.y");
+        assert!(matches!(&assign.target.kind, ExprKind::Field { field, .. } if field.name == "y"));
+        assert_eq!(grouped("-p.x * q[0].z"), "((-p.x) * q[0].z)");
+
+        let (_, diagnostics) =
+            parse_source("new struct P { x, 1, { }, y }\nnew x { logic { p. x } }");
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected Ident, found Number",
+                "error[N0100]: expected Ident, found OpenBrace",
+                "error[N0100]: expected Ident, found Ident",
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_enums() {
+        let source = "new enum State { Idle, Running }\nnew x { logic { s = State::Idle\nmatch s { State::Idle => 1, _ => 2 } } }";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let [Item::Enum(state), Item::Block(block)] = &program.items[..] else {
+            panic!("{:?}", program.items);
+        };
+        let variants: Vec<&str> = state.variants.iter().map(|v| v.name.as_str()).collect();
+
+        assert_eq!(state.name.name, "State");
+        assert_eq!(variants, vec!["Idle", "Running"]);
+        assert_eq!(
+            state.span.slice_clamped(source),
+            "new enum State { Idle, Running }"
+        );
+
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let StmtKind::Expr(Expr {
+            kind: ExprKind::Match(choice),
+            ..
+        }) = &logic.body[1].kind
+        else {
+            panic!("{:?}", logic.body);
+        };
+
+        assert!(matches!(
+            &choice.arms[0].pattern.kind,
+            PatternKind::Variant { name, variant } if name.name == "State" && variant.name == "Idle"
+        ));
+        assert_eq!(
+            choice.arms[0].pattern.span.slice_clamped(source),
+            "State::Idle"
+        );
+        assert_eq!(grouped("State::Idle"), "State::Idle");
+
+        let (_, diagnostics) = parse_source("new x { logic { match s { $ => 1 } } }");
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[N0107]: expected a pattern, found Sigil"
+        );
+        assert!(diagnostics[0].children[0].message.contains("`State::Idle`"));
+    }
+
+    #[test]
+    pub fn test_parse_collect() {
+        let source =
+            "new x { logic {\n  collect as $\n  collect $a\n  collect as b\n  collect as\n} }";
+        let (program, diagnostics) = parse_source(source);
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected `as`, found Sigil",
+                "error[N0100]: expected Sigil, found Ident",
+                "error[N0100]: expected Sigil, found CloseBrace",
+            ]
+        );
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let targets: Vec<&str> = logic
+            .body
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::Collect(collect) => Some(collect.target.name.as_str()),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(targets, vec!["", "a", "b"]);
+
+        // both slips can be fixed for the user
+        let fixed = apply_suggestions(source, &diagnostics);
+
+        assert_eq!(
+            fixed,
+            "new x { logic {\n  collect as $\n  collect as $a\n  collect as $b\n  collect as\n} }"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_for_recover() {
+        let (program, diagnostics) = parse_source(
+            "new x { logic {\n  for $ var { 1 }\n  for $ as { 2 }\n  for $ as v\n  for $ 1\n} }",
+        );
+        let messages: Vec<String> = diagnostics.iter().map(|d| d.to_string()).collect();
+
+        assert_eq!(
+            messages,
+            vec![
+                "error[N0100]: expected `as`, found Ident",
+                "error[N0100]: expected Ident, found OpenBrace",
+                "error[N0100]: expected OpenBrace, found ReservedKeyword",
+                "error[N0100]: expected `as`, found Number",
+            ]
+        );
+
+        // the `as` can go right in
+        assert_eq!(diagnostics[0].suggestions[0].replacement, "as ".to_owned());
+        assert_eq!(diagnostics[0].suggestions[0].span, Span::new(24, 24));
+        assert!(diagnostics[3].suggestions.is_empty());
+
+        let Item::Block(block) = &program.items[0] else {
+            panic!("{:?}", program.items);
+        };
+        let Section::Logic(logic) = &block.sections[0] else {
+            panic!("{:?}", block.sections);
+        };
+        let bindings: Vec<&str> = logic
+            .body
+            .iter()
+            .filter_map(|stmt| match &stmt.kind {
+                StmtKind::For(for_stmt) => Some(for_stmt.binding.bindings()[0]),
+                _ => None,
+            })
+            .collect();
+
+        assert_eq!(bindings, vec!["var", ""]);
+    }
+
+    #[test]
+    pub fn test_parse_for_unclosed() {
+        let source = "new x { logic {\n  for $ as v {\n    ::stdout write v\n";
+        let (_, diagnostics) = parse_source(source);
+
+        // the loop's body first, then the blocks around it
+        assert_eq!(diagnostics.len(), 3);
+        assert_eq!(diagnostics[0].labels[0].span, Span::new(29, 30)); // the loop's `{`
+    }
+
+    /// `source` as one expression, with the structure spelled out in parentheses
+    fn grouped(source: &str) -> String {
+        fn group(expr: &Expr) -> String {
+            match &expr.kind {
+                ExprKind::Binary { op, lhs, rhs } => {
+                    format!("({} {} {})", group(lhs), op.symbol(), group(rhs))
+                }
+                ExprKind::Conditional {
+                    condition,
+                    then,
+                    otherwise,
+                } => format!(
+                    "({} ? {} : {})",
+                    group(condition),
+                    group(then),
+                    group(otherwise)
+                ),
+                ExprKind::Range {
+                    start,
+                    end,
+                    inclusive,
+                } => {
+                    let dots = if *inclusive { "..=" } else { ".." };
+
+                    format!("({}{}{})", group(start), dots, group(end))
+                }
+                ExprKind::Unary { op, operand } => format!("({}{})", op.symbol(), group(operand)),
+                ExprKind::Member { base, member } => format!("{}::{}", group(base), group(member)),
+                ExprKind::Number(n) => n.to_string(),
+                ExprKind::Ident(name) => name.clone(),
+                ExprKind::Sigil(name) => format!("${}", name),
+                ExprKind::ListLit(items) => {
+                    let items: Vec<String> = items.iter().map(group).collect();
+
+                    format!("[{}]", items.join(", "))
+                }
+                ExprKind::Index { base, index } => format!("{}[{}]", group(base), group(index)),
+                ExprKind::Field { base, field } => format!("{}.{}", group(base), field.name),
+                ExprKind::MapLit(entries) => {
+                    let entries: Vec<String> = entries
+                        .iter()
+                        .map(|e| format!("{}: {}", group(&e.key), group(&e.value)))
+                        .collect();
+
+                    format!("#{{{}}}", entries.join(", "))
+                }
+                ExprKind::String(s) => format!("{:?}", s),
+                other => format!("{:?}", other),
+            }
+        }
+
+        let mut parser = Parser::new(Lexer::new(source).lexeme(), Vec::new());
+        let expr = parser.parse_expr().unwrap();
+
+        assert!(parser.sink.is_empty(), "{:?}", parser.sink);
+        assert!(parser.stream.is_eof());
+        assert_eq!(expr.span, Span::new(0, source.len()));
+
+        group(&expr)
+    }
+
+    #[test]
+    pub fn test_parse_precedence() {
+        assert_eq!(grouped("1 + 2 * 3"), "(1 + (2 * 3))");
+        assert_eq!(grouped("1 * 2 + 3"), "((1 * 2) + 3)");
+        assert_eq!(grouped("a - b - c"), "((a - b) - c)");
+        assert_eq!(grouped("a / b % c"), "((a / b) % c)");
+        assert_eq!(grouped("-a * -b"), "((-a) * (-b))");
+        assert_eq!(grouped("- -$::1"), "(-(-$::1))");
+        assert_eq!(grouped("-xs[0] * 2"), "((-xs[0]) * 2)");
+        assert_eq!(
+            grouped("a + 1 < b * 2 && c != d || e >= f"),
+            "((((a + 1) < (b * 2)) && (c != d)) || (e >= f))"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_operators_in_logic() {
+        let stmts = logic("new x { logic {\n  ::stdout write a + 1 b\n  x * 2\n  - y\n} }");
+
+        let StmtKind::Call(call) = &stmts[0].kind else {
+            panic!("{:?}", stmts[0]);
+        };
+
+        assert_eq!(call.args.len(), 2); // `a + 1` and `b`
+
+        // an operator starting a line starts a new statement
+        assert_eq!(stmts.len(), 3);
+        assert!(matches!(
+            stmts[2].kind,
+            StmtKind::Expr(Expr {
+                kind: ExprKind::Unary { .. },
+                ..
+            })
+        ));
+
+        let (_, diagnostics) = parse_source("new x { logic { 1 + } }");
+
+        assert_eq!(
+            diagnostics[0].to_string(),
+            "error[N0105]: expected an expression, found CloseBrace"
+        );
+    }
+
+    #[test]
+    pub fn test_parse_reports_to_sink() {
+        let mut count = 0;
+        let mut parser = Parser::new(Lexer::new("1 new 2 new").lexeme(), |_| count += 1);
+
+        parser.parse_program();
+        drop(parser);
+
+        assert_eq!(count, 3);
+    }
+}
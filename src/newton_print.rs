@@ -0,0 +1,756 @@
+//! # Newton Printer
+//!
+//! Turns a `Program` back into `.newton` source, laid out one way whatever way it was
+//! written, for a formatter, or for a tool that builds programs and writes them out.
+//!
+//! ```
+//! # use newton::newton_parse::parse_source;
+//! # use newton::newton_print::to_source;
+//! let (program, _) = parse_source("new x{logic{let a=1+2*3}}");
+//!
+//! assert_eq!(to_source(&program), "new x {\n  logic {\n    let a = 1 + 2 * 3\n  }\n}\n");
+//! ```
+//!
+//! Comments and a file's lint directives aren't in the AST, so they aren't printed, see
+//! `newton_cst` to keep them. What is printed parses back into the same program,
+//! parentheses are only put in where the precedence needs them.
+
+use crate::newton_ast::{
+    BlockDecl, Call, ConditionKind, Doc, Else, Expr, ExprKind, Ident, Item, MatchArm, Pattern,
+    PatternKind, Program, Section, Stmt, StmtKind, StringPart,
+};
+
+/// # Print Options
+///
+/// How the source is laid out, the builders chain like `ParserOptions`'s.
+///
+/// ```
+/// # use newton::newton_print::PrintOptions;
+/// let options = PrintOptions::new().indent(4);
+///
+/// assert_eq!(options.indent, "    ");
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct PrintOptions {
+    pub indent: String, // in front of a line for each level it's in, two spaces by default
+}
+
+impl Default for PrintOptions {
+    fn default() -> Self {
+        Self {
+            indent: "  ".to_owned(),
+        }
+    }
+}
+
+impl PrintOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// indents each level by `width` spaces
+    pub fn indent(mut self, width: usize) -> Self {
+        self.indent = " ".repeat(width);
+        self
+    }
+
+    /// indents each level by a tab
+    pub fn tabs(mut self) -> Self {
+        self.indent = "\t".to_owned();
+        self
+    }
+}
+
+/// how tightly each kind of expression binds, the loosest first, so an operand binding
+/// looser than where it goes gets parentheses
+const CONDITIONAL: u8 = 0; // a ? b : c
+const RANGE: u8 = 1; // 1..10
+const BINARY: u8 = 2; // 1 + 2, each precedence a level up from here
+const UNARY: u8 = 8; // -x
+const POSTFIX: u8 = 9; // $::1, p.x, f(x), xs[0]
+const ATOM: u8 = 10; // 1, x, [1, 2]
+
+/// # Printer
+///
+/// Writes nodes out as source, keeping track of how deep it is.
+struct Printer<'o> {
+    out: String,               // what's been written so far
+    options: &'o PrintOptions, // how to lay it out
+    depth: usize,              // how many levels in the next line goes
+}
+
+impl Printer<'_> {
+    /// starts a new line, indented to the current depth
+    fn newline(&mut self) {
+        self.out.push('\n');
+
+        for _ in 0..self.depth {
+            self.out.push_str(&self.options.indent);
+        }
+    }
+
+    fn item(&mut self, item: &Item) {
+        match item {
+            Item::Include(include) => {
+                self.out.push_str("include ");
+                string(&mut self.out, &include.path);
+            }
+            Item::Use(import) => {
+                let path: Vec<&str> = import.path.iter().map(|name| name.name.as_str()).collect();
+
+                self.out.push_str("use ");
+                self.out.push_str(&path.join("::"));
+            }
+            Item::Fn(function) => {
+                self.doc(&function.doc);
+                self.out.push_str("fn ");
+                self.out.push_str(&function.name.name);
+                self.params(function.params.iter().map(|param| param.name.as_str()));
+                self.out.push(' ');
+                self.body(&function.body);
+            }
+            Item::Struct(record) => {
+                self.declaration("struct", &record.name.name, &record.fields);
+            }
+            Item::Enum(choice) => {
+                self.declaration("enum", &choice.name.name, &choice.variants);
+            }
+            Item::Block(block) => self.block(block),
+        }
+    }
+
+    /// `;;; prints things`, a line each, before what it documents
+    fn doc(&mut self, doc: &Option<Doc>) {
+        for line in doc.iter().flat_map(|doc| doc.text.split('\n')) {
+            match line.is_empty() {
+                true => self.out.push_str(";;;"),
+                false => {
+                    self.out.push_str(";;; ");
+                    self.out.push_str(line);
+                }
+            }
+
+            self.newline();
+        }
+    }
+
+    /// `(a, b)`
+    fn params<'a>(&mut self, params: impl Iterator<Item = &'a str>) {
+        let params: Vec<&str> = params.collect();
+
+        self.out.push('(');
+        self.out.push_str(&params.join(", "));
+        self.out.push(')');
+    }
+
+    /// `new struct Point { x, y }`, or the same for an `enum`
+    fn declaration(&mut self, word: &str, name: &str, names: &[Ident]) {
+        let names: Vec<&str> = names.iter().map(|name| name.name.as_str()).collect();
+
+        self.out.push_str("new ");
+        self.out.push_str(word);
+        self.out.push(' ');
+        self.out.push_str(name);
+
+        match names.is_empty() {
+            true => self.out.push_str(" { }"),
+            false => {
+                self.out.push_str(" { ");
+                self.out.push_str(&names.join(", "));
+                self.out.push_str(" }");
+            }
+        }
+    }
+
+    fn block(&mut self, block: &BlockDecl) {
+        self.doc(&block.doc);
+        self.out.push_str("new ");
+        self.out.push_str(&block.name.name);
+
+        if block.sections.is_empty() {
+            self.out.push_str(" { }");
+            return;
+        }
+
+        self.out.push_str(" {");
+        self.depth += 1;
+
+        for section in &block.sections {
+            self.newline();
+            self.section(section);
+        }
+
+        self.depth -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn section(&mut self, section: &Section) {
+        match section {
+            Section::Conditions(conditions) => {
+                self.out.push_str("conditions {");
+
+                // line breaks don't end a condition, so they all go on one line
+                for condition in &conditions.conditions {
+                    self.out.push(' ');
+
+                    match &condition.kind {
+                        ConditionKind::Predicate { name, args } => {
+                            self.out.push_str(&name.name);
+
+                            for arg in args {
+                                self.out.push(' ');
+                                self.expr(arg, ATOM);
+                            }
+                        }
+                        ConditionKind::Directive(name) => {
+                            self.out.push('%');
+                            self.out.push_str(&name.name);
+                        }
+                    }
+                }
+
+                self.out.push_str(" }");
+            }
+            Section::Logic(logic) => {
+                self.out.push_str("logic ");
+                self.body(&logic.body);
+            }
+        }
+    }
+
+    /// `{ ... }`, a statement to a line, or `{ }` without any
+    fn body(&mut self, stmts: &[Stmt]) {
+        if stmts.is_empty() {
+            self.out.push_str("{ }");
+            return;
+        }
+
+        self.out.push('{');
+        self.depth += 1;
+
+        for stmt in stmts {
+            self.newline();
+            self.stmt(stmt);
+        }
+
+        self.depth -= 1;
+        self.newline();
+        self.out.push('}');
+    }
+
+    fn stmt(&mut self, stmt: &Stmt) {
+        match &stmt.kind {
+            StmtKind::Expr(value) => self.expr(value, CONDITIONAL),
+            StmtKind::Collect(collect) => {
+                self.out.push_str("collect as $");
+                self.out.push_str(&collect.target.name);
+            }
+            StmtKind::For(for_stmt) => {
+                self.out.push_str("for ");
+                self.expr(&for_stmt.source, CONDITIONAL);
+                self.out.push_str(" as ");
+                self.pattern(&for_stmt.binding);
+                self.out.push(' ');
+                self.body(&for_stmt.body);
+            }
+            StmtKind::Call(call) => self.call(call),
+            StmtKind::Let(let_stmt) => {
+                self.out.push_str("let ");
+                self.pattern(&let_stmt.pattern);
+
+                if let Some(value) = &let_stmt.value {
+                    self.out.push_str(" = ");
+                    self.expr(value, CONDITIONAL);
+                }
+            }
+            StmtKind::Assign(assign) => {
+                self.expr(&assign.target, CONDITIONAL);
+                self.out.push(' ');
+
+                if let Some(op) = assign.op {
+                    self.out.push_str(op.symbol());
+                }
+
+                self.out.push_str("= ");
+                self.expr(&assign.value, CONDITIONAL);
+            }
+            StmtKind::If(if_stmt) => {
+                self.out.push_str("if ");
+                self.expr(&if_stmt.condition, CONDITIONAL);
+                self.out.push(' ');
+                self.body(&if_stmt.then);
+
+                match &if_stmt.otherwise {
+                    Some(Else::If(nested)) => {
+                        self.out.push_str(" else ");
+                        self.stmt(nested);
+                    }
+                    Some(Else::Block(body)) => {
+                        self.out.push_str(" else ");
+                        self.body(body);
+                    }
+                    None => {}
+                }
+            }
+            StmtKind::While(while_stmt) => {
+                self.out.push_str("while ");
+                self.expr(&while_stmt.condition, CONDITIONAL);
+                self.out.push(' ');
+                self.body(&while_stmt.body);
+            }
+            StmtKind::Return(value) => {
+                self.out.push_str("return");
+
+                if let Some(value) = value {
+                    self.out.push(' ');
+                    self.expr(value, CONDITIONAL);
+                }
+            }
+            StmtKind::Break => self.out.push_str("break"),
+            StmtKind::Continue => self.out.push_str("continue"),
+        }
+    }
+
+    /// `::stdout write_newline var`
+    ///
+    /// The arguments are only split up by spaces, so one starting with a `-` goes in
+    /// parentheses, or it would be taken away from the one before it.
+    fn call(&mut self, call: &Call) {
+        self.out.push_str("::");
+        self.out.push_str(&call.module.name);
+        self.out.push(' ');
+        self.out.push_str(&call.function.name);
+
+        for (i, arg) in call.args.iter().enumerate() {
+            self.out.push(' ');
+
+            match i > 0 && starts_with_minus(arg) {
+                true => self.expr(arg, ATOM),
+                false => self.expr(arg, CONDITIONAL),
+            }
+        }
+    }
+
+    /// `value`, in parentheses if it binds looser than `min`
+    fn expr(&mut self, value: &Expr, min: u8) {
+        let binds = binds(value);
+
+        if binds < min {
+            self.out.push('(');
+            self.expr(value, CONDITIONAL);
+            self.out.push(')');
+            return;
+        }
+
+        match &value.kind {
+            ExprKind::Number(n) => self.out.push_str(&n.to_string()),
+            ExprKind::String(s) => string(&mut self.out, s),
+            ExprKind::Interpolated(parts) => {
+                self.out.push('"');
+
+                for part in parts {
+                    match part {
+                        StringPart::Text(text) => string_text(&mut self.out, text),
+                        StringPart::Expr(value) => {
+                            self.out.push('{');
+                            self.expr(value, CONDITIONAL);
+                            self.out.push('}');
+                        }
+                    }
+                }
+
+                self.out.push('"');
+            }
+            ExprKind::Char(c) => character(&mut self.out, *c),
+            ExprKind::Ident(name) => self.out.push_str(name),
+            ExprKind::Sigil(name) => {
+                self.out.push('$');
+                self.out.push_str(name);
+            }
+            ExprKind::Member { base, member } => {
+                self.expr(base, POSTFIX);
+                self.out.push_str("::");
+                self.expr(member, ATOM);
+            }
+            ExprKind::Unary { op, operand } => {
+                self.out.push_str(op.symbol());
+                self.expr(operand, UNARY);
+            }
+            ExprKind::Binary { op, lhs, rhs } => {
+                // left-associative, so only the right side needs to bind tighter
+                self.expr(lhs, binds);
+                self.out.push(' ');
+                self.out.push_str(op.symbol());
+                self.out.push(' ');
+                self.expr(rhs, binds + 1);
+            }
+            ExprKind::Call(call) => self.call(call),
+            ExprKind::Match(matched) => {
+                self.out.push_str("match ");
+                self.expr(&matched.scrutinee, CONDITIONAL);
+
+                if matched.arms.is_empty() {
+                    self.out.push_str(" { }");
+                    return;
+                }
+
+                self.out.push_str(" {");
+                self.depth += 1;
+
+                for arm in &matched.arms {
+                    self.newline();
+                    self.arm(arm);
+                }
+
+                self.depth -= 1;
+                self.newline();
+                self.out.push('}');
+            }
+            ExprKind::FnCall { callee, args } => {
+                self.expr(callee, POSTFIX);
+                self.list('(', args, ')');
+            }
+            ExprKind::ListLit(items) => self.list('[', items, ']'),
+            ExprKind::MapLit(entries) => {
+                if entries.is_empty() {
+                    self.out.push_str("#{ }");
+                    return;
+                }
+
+                self.out.push_str("#{ ");
+
+                for (i, entry) in entries.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+
+                    self.expr(&entry.key, CONDITIONAL);
+                    self.out.push_str(": ");
+                    self.expr(&entry.value, CONDITIONAL);
+                }
+
+                self.out.push_str(" }");
+            }
+            ExprKind::Lambda(lambda) => {
+                self.out.push_str("fn ");
+                self.params(lambda.params.iter().map(|param| param.name.as_str()));
+                self.out.push(' ');
+                self.body(&lambda.body);
+            }
+            ExprKind::Conditional {
+                condition,
+                then,
+                otherwise,
+            } => {
+                self.expr(condition, RANGE);
+                self.out.push_str(" ? ");
+                self.expr(then, CONDITIONAL);
+                self.out.push_str(" : ");
+                self.expr(otherwise, CONDITIONAL);
+            }
+            ExprKind::Range {
+                start,
+                end,
+                inclusive,
+            } => {
+                self.expr(start, BINARY);
+                self.out.push_str(if *inclusive { "..=" } else { ".." });
+                self.expr(end, BINARY);
+            }
+            ExprKind::Field { base, field } => {
+                self.expr(base, POSTFIX);
+                self.out.push('.');
+                self.out.push_str(&field.name);
+            }
+            ExprKind::Index { base, index } => {
+                self.expr(base, POSTFIX);
+                self.out.push('[');
+                self.expr(index, CONDITIONAL);
+                self.out.push(']');
+            }
+        }
+    }
+
+    /// `(1, 2)` or `[1, 2]`
+    fn list(&mut self, open: char, items: &[Expr], close: char) {
+        self.out.push(open);
+
+        for (i, item) in items.iter().enumerate() {
+            if i > 0 {
+                self.out.push_str(", ");
+            }
+
+            self.expr(item, CONDITIONAL);
+        }
+
+        self.out.push(close);
+    }
+
+    /// `1 => "one"`, a body of one statement on the same line, and any other in braces
+    fn arm(&mut self, arm: &MatchArm) {
+        self.pattern(&arm.pattern);
+        self.out.push_str(" => ");
+
+        match arm.body.as_slice() {
+            [stmt] => self.stmt(stmt),
+            body => self.body(body),
+        }
+    }
+
+    fn pattern(&mut self, pattern: &Pattern) {
+        match &pattern.kind {
+            PatternKind::Number(n) => self.out.push_str(&n.to_string()),
+            PatternKind::String(s) => string(&mut self.out, s),
+            PatternKind::Char(c) => character(&mut self.out, *c),
+            PatternKind::Wildcard => self.out.push('_'),
+            PatternKind::Ident(name) => self.out.push_str(name),
+            PatternKind::Tuple(items) => {
+                self.out.push('(');
+
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        self.out.push_str(", ");
+                    }
+
+                    self.pattern(item);
+                }
+
+                self.out.push(')');
+            }
+            PatternKind::Variant { name, variant } => {
+                self.out.push_str(&name.name);
+                self.out.push_str("::");
+                self.out.push_str(&variant.name);
+            }
+        }
+    }
+}
+
+/// how tightly `value` binds, see `CONDITIONAL` through `ATOM`
+fn binds(value: &Expr) -> u8 {
+    match &value.kind {
+        ExprKind::Conditional { .. } => CONDITIONAL,
+        ExprKind::Range { .. } => RANGE,
+        ExprKind::Binary { op, .. } => BINARY + op.precedence() - 1,
+        ExprKind::Unary { .. } => UNARY,
+        ExprKind::Number(n) if n.is_sign_negative() => UNARY,
+        ExprKind::Member { .. }
+        | ExprKind::FnCall { .. }
+        | ExprKind::Field { .. }
+        | ExprKind::Index { .. } => POSTFIX,
+        _ => ATOM,
+    }
+}
+
+/// if `value` is written starting with a `-`
+fn starts_with_minus(value: &Expr) -> bool {
+    match &value.kind {
+        ExprKind::Unary { .. } => true,
+        ExprKind::Number(n) => n.is_sign_negative(),
+        ExprKind::Binary { lhs: first, .. }
+        | ExprKind::Range { start: first, .. }
+        | ExprKind::Conditional {
+            condition: first, ..
+        }
+        | ExprKind::Member { base: first, .. }
+        | ExprKind::FnCall { callee: first, .. }
+        | ExprKind::Field { base: first, .. }
+        | ExprKind::Index { base: first, .. } => starts_with_minus(first),
+        _ => false,
+    }
+}
+
+/// `s` as a string literal, quoted, with whatever can't be written as it is escaped
+fn string(out: &mut String, s: &str) {
+    out.push('"');
+    string_text(out, s);
+    out.push('"');
+}
+
+/// `s` escaped to go between the quotes of a string, braces too
+fn string_text(out: &mut String, s: &str) {
+    for ch in s.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '{' => out.push_str("\\{"),
+            '}' => out.push_str("\\}"),
+            ch => escaped(out, ch),
+        }
+    }
+}
+
+/// `c` as a character literal, quoted and escaped
+fn character(out: &mut String, c: char) {
+    out.push('\'');
+
+    match c {
+        '\'' => out.push_str("\\'"),
+        c => escaped(out, c),
+    }
+
+    out.push('\'');
+}
+
+/// a character of a string or character literal, escaped if it's a backslash or a control
+/// character
+fn escaped(out: &mut String, ch: char) {
+    match ch {
+        '\\' => out.push_str("\\\\"),
+        '\n' => out.push_str("\\n"),
+        '\t' => out.push_str("\\t"),
+        '\r' => out.push_str("\\r"),
+        '\0' => out.push_str("\\0"),
+        c if c.is_control() => out.push_str(&format!("\\u{{{:x}}}", c as u32)),
+        c => out.push(c),
+    }
+}
+
+/// `program` as source, laid out the default way, see `PrintOptions`
+pub fn to_source(program: &Program) -> String {
+    to_source_with(program, &PrintOptions::default())
+}
+
+/// `program` as source, laid out by `options`
+///
+/// Items are split up by a blank line, except for `include`s and `use`s next to each other,
+/// and the source ends with a line break.
+pub fn to_source_with(program: &Program, options: &PrintOptions) -> String {
+    let mut printer = Printer {
+        out: String::new(),
+        options,
+        depth: 0,
+    };
+
+    let mut prev: Option<&Item> = None;
+
+    for item in &program.items {
+        let imports = |item: &Item| matches!(item, Item::Include(_) | Item::Use(_));
+
+        match prev {
+            Some(prev) if imports(prev) && imports(item) => printer.out.push('\n'),
+            Some(_) => printer.out.push_str("\n\n"),
+            None => {}
+        }
+
+        printer.item(item);
+        prev = Some(item);
+    }
+
+    if !program.items.is_empty() {
+        printer.out.push('\n');
+    }
+
+    printer.out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_parse::parse_source;
+
+    /// `source` parsed and printed, asserting it parses without diagnostics both before and
+    /// after, and that printing it again changes nothing
+    fn printed(source: &str) -> String {
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let printed = to_source(&program);
+        let (again, diagnostics) = parse_source(&printed);
+
+        assert!(diagnostics.is_empty(), "{}\n{:?}", printed, diagnostics);
+        assert_eq!(to_source(&again), printed);
+
+        printed
+    }
+
+    #[test]
+    pub fn test_print_program() {
+        let source = "include \"core/internal\"\nuse core::str\nnew struct Point{x,y}\nnew enum State {\n  Idle\n  Running\n}\nfn greet(name,) { ::stdout write name }\nnew hello_world {\n  conditions { expect ident \"print\" 'a' %override any }\n  logic {\n    collect as $\n    for $ as (k, v) { }\n  }\n}";
+
+        assert_eq!(
+            printed(source),
+            "include \"core/internal\"\nuse core::str\n\nnew struct Point { x, y }\n\nnew enum State { Idle, Running }\n\nfn greet(name) {\n  ::stdout write name\n}\n\nnew hello_world {\n  conditions { expect ident \"print\" 'a' %override any }\n  logic {\n    collect as $\n    for $ as (k, v) { }\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    pub fn test_print_docs() {
+        let source =
+            ";;; # Hello\n;;;\n;;;   says hello\nnew hello { }\n;;; greets\nfn greet() { }";
+
+        assert_eq!(
+            printed(source),
+            ";;; # Hello\n;;;\n;;;   says hello\nnew hello { }\n\n;;; greets\nfn greet() { }\n"
+        );
+    }
+
+    #[test]
+    pub fn test_print_stmts() {
+        let source = "fn f(x) {\nlet (a, b) = x\nx += 1\nif x { return } else if y { break } else { continue }\nwhile x > 1 { x = x - 1 }\nreturn match x { 1 => \"one\", State::Idle => { }, _ => { a\nb } }\n}";
+
+        assert_eq!(
+            printed(source),
+            "fn f(x) {\n  let (a, b) = x\n  x += 1\n  if x {\n    return\n  } else if y {\n    break\n  } else {\n    continue\n  }\n  while x > 1 {\n    x = x - 1\n  }\n  return match x {\n    1 => \"one\"\n    State::Idle => { }\n    _ => {\n      a\n      b\n    }\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    pub fn test_print_exprs() {
+        let cases = [
+            ("(1 + 2) * 3", "(1 + 2) * 3"),
+            ("1 + (2 * 3)", "1 + 2 * 3"),
+            ("1 - (2 - 3)", "1 - (2 - 3)"),
+            ("(1 - 2) - 3", "1 - 2 - 3"),
+            ("-(a + 1)", "-(a + 1)"),
+            ("(-a).b", "(-a).b"),
+            ("(0..n - 1)", "0..n - 1"),
+            ("(a ? b : c) ? d : e", "(a ? b : c) ? d : e"),
+            ("a ? b : (c ? d : e)", "a ? b : c ? d : e"),
+            ("(1..2)..=3", "(1..2)..=3"),
+            ("f(x)(y)[0].z", "f(x)(y)[0].z"),
+            ("$::1", "$::1"),
+            (
+                "[1,2.5,\"a\\\"\\{b\\}\\n\",'\\'']",
+                "[1, 2.5, \"a\\\"\\{b\\}\\n\", '\\'']",
+            ),
+            ("#{ \"a\": 1, \"b\": #{} }", "#{ \"a\": 1, \"b\": #{ } }"),
+            ("fn (a,b) { }", "fn (a, b) { }"),
+            ("\"a {b+1}\\{c\\}{ d }\"", "\"a {b + 1}\\{c\\}{d}\""),
+        ];
+
+        for (source, expected) in cases {
+            let source = format!("new x {{ logic {{ y = {} }} }}", source);
+            let expected = format!("new x {{\n  logic {{\n    y = {}\n  }}\n}}\n", expected);
+
+            assert_eq!(printed(&source), expected);
+        }
+    }
+
+    #[test]
+    pub fn test_print_call_args() {
+        let source = "new x { logic { ::stdout write -1 (-a) (b) ::str upper c (-d) } }";
+
+        assert_eq!(
+            printed(source),
+            "new x {\n  logic {\n    ::stdout write -1 (-a) b ::str upper c (-d)\n  }\n}\n"
+        );
+    }
+
+    #[test]
+    pub fn test_print_options() {
+        let (program, _) = parse_source("new x { logic { if y { z } } }");
+
+        assert_eq!(
+            to_source_with(&program, &PrintOptions::new().indent(4)),
+            "new x {\n    logic {\n        if y {\n            z\n        }\n    }\n}\n"
+        );
+        assert_eq!(
+            to_source_with(&program, &PrintOptions::new().tabs()),
+            "new x {\n\tlogic {\n\t\tif y {\n\t\t\tz\n\t\t}\n\t}\n}\n"
+        );
+        assert_eq!(to_source(&parse_source("").0), "");
+    }
+}
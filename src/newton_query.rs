@@ -0,0 +1,264 @@
+//! # Newton Queries
+//!
+//! What's under the cursor. `Program::node_at` finds the innermost node at an offset, and
+//! every node around it, which is where hover, completion, and go to definition all start.
+//!
+//! ```
+//! # use newton::newton_ast::Spanned;
+//! # use newton::newton_parse::parse_source;
+//! # use newton::newton_query::Node;
+//! let source = "new x { logic { let a = b + 1 } }";
+//! let (program, _) = parse_source(source);
+//!
+//! let path = program.node_at(source.find('b').unwrap());
+//!
+//! assert_eq!(path[0].span().slice_clamped(source), "b");
+//! assert_eq!(path[1].span().slice_clamped(source), "b + 1");
+//! assert!(matches!(path.last(), Some(Node::Program(_))));
+//! ```
+//!
+//! A node's span counts as holding the offset right after its end too, so a cursor at the
+//! end of a name that's being typed is still on that name. Where two nodes side by side
+//! both hold an offset, it's in the first one.
+//!
+//! For the tokens themselves, comments and spaces too, see `SyntaxNode::token_at`.
+
+use crate::newton_ast::{
+    BlockDecl, Condition, Expr, Item, MatchArm, Pattern, Program, Section, Spanned, Stmt,
+};
+use crate::newton_lex::Span;
+use crate::newton_visit::{
+    walk_arm, walk_condition, walk_expr, walk_item, walk_pattern, walk_program, walk_stmt, Visitor,
+};
+
+/// # Nodes
+///
+/// Any node of a program, borrowed from it.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub enum Node<'ast> {
+    Program(&'ast Program),     // the whole file
+    Item(&'ast Item),           // new hello_world { ... }
+    Section(&'ast Section),     // conditions { ... }, logic { ... }
+    Condition(&'ast Condition), // expect ident 'print'
+    Stmt(&'ast Stmt),           // ::stdout write $::1
+    Expr(&'ast Expr),           // $::1
+    Arm(&'ast MatchArm),        // 1 => "one"
+    Pattern(&'ast Pattern),     // (a, b)
+}
+
+impl Spanned for Node<'_> {
+    fn span(&self) -> Span {
+        match self {
+            Node::Program(program) => program.span,
+            Node::Item(item) => item.span(),
+            Node::Section(section) => section.span(),
+            Node::Condition(condition) => condition.span,
+            Node::Stmt(stmt) => stmt.span,
+            Node::Expr(expr) => expr.span,
+            Node::Arm(arm) => arm.span,
+            Node::Pattern(pattern) => pattern.span,
+        }
+    }
+}
+
+impl Program {
+    /// the innermost node at `offset`, then each node around it, out to the program
+    ///
+    /// Empty if the offset is past the end of the file.
+    pub fn node_at(&self, offset: usize) -> Vec<Node<'_>> {
+        let mut finder = Finder {
+            offset,
+            path: Vec::new(),
+            depth: 0,
+        };
+
+        finder.visit_program(self);
+        finder.path.reverse();
+        finder.path
+    }
+}
+
+/// # Finder
+///
+/// Goes down into the nodes that hold an offset, and only the first of them at each level.
+struct Finder<'ast> {
+    offset: usize,         // what to look for
+    path: Vec<Node<'ast>>, // the nodes it's in, the outermost first
+    depth: usize,          // how many nodes deep the visit is
+}
+
+impl<'ast> Finder<'ast> {
+    /// if `node` is the one to go into, remembering it if so
+    fn enter(&mut self, node: Node<'ast>) -> bool {
+        let span = node.span();
+
+        // a longer path means a node before this one at the same level had the offset
+        if self.path.len() != self.depth || self.offset < span.start || self.offset > span.end {
+            return false;
+        }
+
+        self.path.push(node);
+        self.depth += 1;
+        true
+    }
+
+    fn leave(&mut self) {
+        self.depth -= 1;
+    }
+}
+
+impl<'ast> Visitor<'ast> for Finder<'ast> {
+    fn visit_program(&mut self, program: &'ast Program) {
+        if self.enter(Node::Program(program)) {
+            walk_program(self, program);
+            self.leave();
+        }
+    }
+
+    fn visit_item(&mut self, item: &'ast Item) {
+        if self.enter(Node::Item(item)) {
+            walk_item(self, item);
+            self.leave();
+        }
+    }
+
+    fn visit_block(&mut self, block: &'ast BlockDecl) {
+        for section in &block.sections {
+            if !self.enter(Node::Section(section)) {
+                continue;
+            }
+
+            match section {
+                Section::Conditions(conditions) => {
+                    for condition in &conditions.conditions {
+                        self.visit_condition(condition);
+                    }
+                }
+                Section::Logic(logic) => {
+                    for stmt in &logic.body {
+                        self.visit_stmt(stmt);
+                    }
+                }
+            }
+
+            self.leave();
+        }
+    }
+
+    fn visit_condition(&mut self, condition: &'ast Condition) {
+        if self.enter(Node::Condition(condition)) {
+            walk_condition(self, condition);
+            self.leave();
+        }
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        if self.enter(Node::Stmt(stmt)) {
+            walk_stmt(self, stmt);
+            self.leave();
+        }
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        if self.enter(Node::Expr(expr)) {
+            walk_expr(self, expr);
+            self.leave();
+        }
+    }
+
+    fn visit_arm(&mut self, arm: &'ast MatchArm) {
+        if self.enter(Node::Arm(arm)) {
+            walk_arm(self, arm);
+            self.leave();
+        }
+    }
+
+    fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+        if self.enter(Node::Pattern(pattern)) {
+            walk_pattern(self, pattern);
+            self.leave();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_parse::parse_source;
+
+    #[test]
+    pub fn test_query_node_at() {
+        let source = "fn f(p) {\n  return match p { (a, 1) => a }\n}\nnew x {\n  conditions { expect ident \"print\" }\n  logic { ::stdout write g(h + 1) }\n}";
+        let (program, diagnostics) = parse_source(source);
+        let block = &source[source.find("new x").unwrap()..];
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        // (where, the nodes there, the innermost first, without the program)
+        let cases = [
+            (
+                "1)",
+                vec![
+                    "1",
+                    "(a, 1)",
+                    "(a, 1) => a",
+                    "match p { (a, 1) => a }",
+                    "return match p { (a, 1) => a }",
+                    "fn f(p) {\n  return match p { (a, 1) => a }\n}",
+                ],
+            ),
+            (
+                "\"print\"",
+                vec![
+                    "\"print\"",
+                    "expect ident \"print\"",
+                    "conditions { expect ident \"print\" }",
+                    block,
+                ],
+            ),
+            (
+                " + 1",
+                vec![
+                    "h",
+                    "h + 1",
+                    "g(h + 1)",
+                    "::stdout write g(h + 1)",
+                    "logic { ::stdout write g(h + 1) }",
+                    block,
+                ],
+            ),
+            ("  logic", vec![block]),
+        ];
+
+        for (at, expected) in cases {
+            let path = program.node_at(source.find(at).unwrap());
+            let spans: Vec<&str> = path
+                .iter()
+                .map(|node| node.span().slice_clamped(source))
+                .collect();
+
+            assert!(matches!(path.last(), Some(Node::Program(_))), "{:?}", spans);
+            assert_eq!(spans[..spans.len() - 1], expected, "{:?}", at);
+        }
+
+        assert!(program.node_at(source.len() + 1).is_empty());
+    }
+
+    #[test]
+    pub fn test_query_node_at_edges() {
+        let source = "new x { logic { let ab = c } }";
+        let (program, _) = parse_source(source);
+        let innermost = |offset| {
+            let path = program.node_at(offset);
+
+            path[0].span().slice_clamped(source)
+        };
+
+        assert_eq!(innermost(20), "ab");
+        assert_eq!(innermost(22), "ab");
+        assert_eq!(innermost(26), "c");
+        assert_eq!(innermost(27), "logic { let ab = c }");
+        assert_eq!(innermost(0), source);
+        assert_eq!(innermost(source.len()), source);
+    }
+}
@@ -0,0 +1,432 @@
+//! # Newton Sources
+//!
+//! A program can span several `.newton` files. The `SourceMap` owns their text and gives
+//! each one a `FileId`, so a `Location` (a file and a span in it) can always be traced back
+//! to a path, a line and column, and the code it points at.
+//!
+//! ```
+//! # use newton::newton_lex::Span;
+//! # use newton::newton_source::{Location, SourceMap};
+//! let mut map = SourceMap::new();
+//! let file = map.add("main.newton", "new x {\n  logic\n}");
+//!
+//! let resolved = map.resolve(Location::new(file, Span::new(10, 15))).unwrap();
+//!
+//! assert_eq!(resolved.to_string(), "main.newton:2:3");
+//! assert_eq!(resolved.snippet, "  logic");
+//! ```
+
+use std::path::{Path, PathBuf};
+
+use crate::newton_lex::Span;
+
+/// # File Ids
+///
+/// Which file in a `SourceMap` something came from.
+#[derive(Debug, PartialEq, Eq, Hash, PartialOrd, Ord, Clone, Copy)]
+pub struct FileId(u32);
+
+impl FileId {
+    /// the file's index in its source map, they count up from 0
+    pub fn index(&self) -> usize {
+        self.0 as usize
+    }
+}
+
+/// # Locations
+///
+/// A span, and the file it's a span of.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Location {
+    pub file: FileId, // the file
+    pub span: Span,   // where in the file
+}
+
+impl Location {
+    pub fn new(file: FileId, span: Span) -> Self {
+        Self { file, span }
+    }
+}
+
+/// # Line and Column
+///
+/// A position for people, both counting from 1. Columns count characters, not bytes.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Clone, Copy)]
+pub struct LineCol {
+    pub line: usize,
+    pub column: usize,
+}
+
+impl LineCol {
+    pub fn new(line: usize, column: usize) -> Self {
+        Self { line, column }
+    }
+}
+
+impl std::fmt::Display for LineCol {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// # Line Index
+///
+/// Where every line of a source starts and ends, worked out once so each lookup after is a
+/// binary search instead of a scan.
+///
+/// `\n`, `\r\n`, and a lone `\r` all end a line, like they do in the lexer. Columns here
+/// count bytes, `SourceFile::line_col` turns them into characters.
+///
+/// ```
+/// # use newton::newton_lex::Span;
+/// # use newton::newton_source::{LineCol, LineIndex};
+/// let index = LineIndex::new("new x {\r\n  logic\n}");
+///
+/// assert_eq!(index.line_col(11), Some(LineCol::new(2, 3)));
+/// assert_eq!(index.line_range(2), Some(Span::new(9, 16)));
+/// assert_eq!(index.line_count(), 3);
+/// ```
+#[derive(Debug, PartialEq, Clone)]
+pub struct LineIndex {
+    lines: Vec<Span>, // each line, without its line break
+    len: usize,       // the length of the source
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let bytes = text.as_bytes();
+        let mut lines = Vec::new();
+        let mut start = 0;
+        let mut i = 0;
+
+        while i < bytes.len() {
+            match bytes[i] {
+                b'\r' if bytes.get(i + 1) == Some(&b'\n') => {
+                    lines.push(Span::new(start, i));
+                    i += 1;
+                    start = i + 1;
+                }
+                b'\n' | b'\r' => {
+                    lines.push(Span::new(start, i));
+                    start = i + 1;
+                }
+                _ => {}
+            }
+
+            i += 1;
+        }
+
+        lines.push(Span::new(start, bytes.len()));
+
+        Self {
+            lines,
+            len: bytes.len(),
+        }
+    }
+
+    /// how many lines there are, an empty source still has one
+    pub fn line_count(&self) -> usize {
+        self.lines.len()
+    }
+
+    /// the line a byte offset is on, counting from 1, `None` past the end
+    pub fn line(&self, offset: usize) -> Option<usize> {
+        if offset > self.len {
+            return None;
+        }
+
+        Some(self.lines.partition_point(|line| line.start <= offset))
+    }
+
+    /// the line and byte column of an offset, both counting from 1
+    pub fn line_col(&self, offset: usize) -> Option<LineCol> {
+        let line = self.line(offset)?;
+
+        Some(LineCol::new(line, offset - self.lines[line - 1].start + 1))
+    }
+
+    /// the span of a line, counting from 1, without its line break
+    pub fn line_range(&self, line: usize) -> Option<Span> {
+        self.lines.get(line.checked_sub(1)?).copied()
+    }
+}
+
+/// # Source Files
+///
+/// One file's path and text, and where its lines are.
+#[derive(Debug, PartialEq, Clone)]
+pub struct SourceFile {
+    pub id: FileId,       // its id in the source map
+    pub path: PathBuf,    // where it came from
+    pub text: String,     // the source code
+    pub lines: LineIndex, // the lines of `text`
+}
+
+impl SourceFile {
+    pub fn new(id: FileId, path: impl Into<PathBuf>, text: impl Into<String>) -> Self {
+        let text = text.into();
+
+        Self {
+            id,
+            path: path.into(),
+            lines: LineIndex::new(&text),
+            text,
+        }
+    }
+
+    /// the line and column of a byte offset, `None` past the end of the file or inside a
+    /// character
+    pub fn line_col(&self, offset: usize) -> Option<LineCol> {
+        let line = self.lines.line(offset)?;
+        let start = self.lines.line_range(line)?.start;
+
+        Some(LineCol::new(
+            line,
+            self.text.get(start..offset)?.chars().count() + 1,
+        ))
+    }
+
+    /// the whole lines a span touches, without the final line break
+    pub fn lines_of(&self, span: Span) -> Option<&str> {
+        let first = self.lines.line(span.start)?;
+        let last = self.lines.line(span.end.max(span.start))?;
+
+        self.text
+            .get(self.lines.line_range(first)?.start..self.lines.line_range(last)?.end)
+    }
+}
+
+/// # Resolved Locations
+///
+/// A `Location` traced back to its file, ready to show to someone. Displays as
+/// `path:line:column`.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Resolved<'map> {
+    pub path: &'map Path,   // the file
+    pub start: LineCol,     // where the span starts
+    pub end: LineCol,       // where the span ends
+    pub snippet: &'map str, // the lines the span is on
+}
+
+impl std::fmt::Display for Resolved<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.path.display(), self.start)
+    }
+}
+
+/// # Source Map
+///
+/// Every file of a program. Files are only ever added, so a `FileId` stays valid for as long
+/// as the map lives.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct SourceMap {
+    files: Vec<SourceFile>,
+}
+
+impl SourceMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// registers a file that is already in memory
+    pub fn add(&mut self, path: impl Into<PathBuf>, text: impl Into<String>) -> FileId {
+        let id = FileId(self.files.len() as u32);
+
+        self.files.push(SourceFile::new(id, path, text));
+
+        id
+    }
+
+    /// reads a file from disk and registers it
+    pub fn load(&mut self, path: impl AsRef<Path>) -> std::io::Result<FileId> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+
+        Ok(self.add(path.as_ref(), text))
+    }
+
+    /// reads many files from disk and registers them, in order
+    ///
+    /// Stops at the first file that can't be read, the ones before it stay registered.
+    pub fn load_all(&mut self, paths: &[impl AsRef<Path>]) -> std::io::Result<Vec<FileId>> {
+        paths.iter().map(|path| self.load(path)).collect()
+    }
+
+    /// the file behind an id
+    ///
+    /// Panics if the id came from another source map.
+    pub fn file(&self, id: FileId) -> &SourceFile {
+        &self.files[id.index()]
+    }
+
+    /// every file, in the order they were added
+    pub fn files(&self) -> impl Iterator<Item = &SourceFile> {
+        self.files.iter()
+    }
+
+    /// traces a location back to its path, line and column, and the code it points at
+    ///
+    /// `None` if the span doesn't fit the file.
+    pub fn resolve(&self, location: Location) -> Option<Resolved<'_>> {
+        let file = self.files.get(location.file.index())?;
+
+        Some(Resolved {
+            path: &file.path,
+            start: file.line_col(location.span.start)?,
+            end: file.line_col(location.span.end)?,
+            snippet: file.lines_of(location.span)?,
+        })
+    }
+}
+
+/// # Mapped Sources
+///
+/// A file memory-mapped instead of read into a `String`, with the `mmap` feature. Lex it with
+/// `Lexer::from_mapped`, the tokens borrow straight from the mapping, so even a huge
+/// generated file is never copied.
+///
+/// The text is checked to be UTF-8 once, when the file is opened.
+///
+/// ```no_run
+/// # use newton::newton_lex::Lexer;
+/// # use newton::newton_source::MappedSource;
+/// let source = MappedSource::open("generated.newton").unwrap();
+/// let tokens = Lexer::from_mapped(&source).lexeme();
+/// ```
+#[cfg(feature = "mmap")]
+#[derive(Debug)]
+pub struct MappedSource {
+    path: PathBuf,      // where it came from
+    map: memmap2::Mmap, // the file's bytes, valid UTF-8
+}
+
+#[cfg(feature = "mmap")]
+impl MappedSource {
+    /// maps a file, failing if it can't be read or isn't UTF-8
+    ///
+    /// The file mustn't change while it's mapped, or the text can change under the tokens.
+    pub fn open(path: impl AsRef<Path>) -> std::io::Result<Self> {
+        let file = std::fs::File::open(path.as_ref())?;
+
+        // SAFETY: the mapping is read only, and the caller promises the file stays as it is
+        let map = unsafe { memmap2::Mmap::map(&file)? };
+
+        std::str::from_utf8(&map)
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::InvalidData, e))?;
+
+        Ok(Self {
+            path: path.as_ref().to_owned(),
+            map,
+        })
+    }
+
+    /// where the file came from
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// the file's text
+    pub fn text(&self) -> &str {
+        // SAFETY: checked to be UTF-8 in `open`, and a read-only mapping can't be changed
+        // through
+        unsafe { std::str::from_utf8_unchecked(&self.map) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_source_map_ids() {
+        let mut map = SourceMap::new();
+
+        let a = map.add("a.newton", "new a");
+        let b = map.add("b.newton", "new b");
+
+        assert_ne!(a, b);
+        assert_eq!(map.file(b).path, Path::new("b.newton"));
+        assert_eq!(map.file(a).text, "new a");
+        assert_eq!(map.files().count(), 2);
+    }
+
+    #[test]
+    pub fn test_source_map_resolve() {
+        let mut map = SourceMap::new();
+        let file = map.add("main.newton", "new x {\r\n  logic { é }\r}\n");
+
+        let resolved = map.resolve(Location::new(file, Span::new(19, 21))).unwrap();
+
+        assert_eq!(resolved.start, LineCol::new(2, 11));
+        assert_eq!(resolved.end, LineCol::new(2, 12));
+        assert_eq!(resolved.snippet, "  logic { é }");
+
+        let closing = map.resolve(Location::new(file, Span::new(24, 25))).unwrap();
+
+        assert_eq!(closing.start, LineCol::new(3, 1));
+        assert_eq!(closing.snippet, "}");
+    }
+
+    #[test]
+    pub fn test_line_index() {
+        let index = LineIndex::new("a\nbc\r\n\rd");
+
+        assert_eq!(index.line_count(), 4);
+        assert_eq!(index.line_range(1), Some(Span::new(0, 1)));
+        assert_eq!(index.line_range(2), Some(Span::new(2, 4)));
+        assert_eq!(index.line_range(3), Some(Span::new(6, 6)));
+        assert_eq!(index.line_range(4), Some(Span::new(7, 8)));
+        assert_eq!(index.line_range(0), None);
+        assert_eq!(index.line_range(5), None);
+
+        assert_eq!(index.line_col(0), Some(LineCol::new(1, 1)));
+        assert_eq!(index.line_col(3), Some(LineCol::new(2, 2)));
+        assert_eq!(index.line_col(7), Some(LineCol::new(4, 1)));
+        assert_eq!(index.line_col(8), Some(LineCol::new(4, 2))); // the very end
+        assert_eq!(index.line_col(9), None);
+    }
+
+    #[test]
+    pub fn test_line_index_empty() {
+        let index = LineIndex::new("");
+
+        assert_eq!(index.line_count(), 1);
+        assert_eq!(index.line_col(0), Some(LineCol::new(1, 1)));
+    }
+
+    #[test]
+    #[cfg(feature = "mmap")]
+    pub fn test_mapped_source() {
+        let dir = std::env::temp_dir().join(format!("newton-mapped-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let text = dir.join("text.newton");
+        let empty = dir.join("empty.newton");
+        let binary = dir.join("binary.newton");
+
+        std::fs::write(&text, "new é").unwrap();
+        std::fs::write(&empty, "").unwrap();
+        std::fs::write(&binary, [0xff, 0xfe, 0x00]).unwrap();
+
+        let source = MappedSource::open(&text).unwrap();
+
+        assert_eq!(source.text(), "new é");
+        assert_eq!(source.path(), text);
+        assert_eq!(MappedSource::open(&empty).unwrap().text(), "");
+        assert_eq!(
+            MappedSource::open(&binary).unwrap_err().kind(),
+            std::io::ErrorKind::InvalidData
+        );
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_source_map_out_of_range() {
+        let mut map = SourceMap::new();
+        let file = map.add("main.newton", "é");
+
+        assert!(map.resolve(Location::new(file, Span::new(0, 9))).is_none());
+        assert!(map.resolve(Location::new(file, Span::new(1, 2))).is_none());
+        assert!(map.resolve(Location::new(file, Span::new(2, 2))).is_some());
+    }
+}
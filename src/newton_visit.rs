@@ -0,0 +1,910 @@
+//! # Newton Visitors
+//!
+//! Walking a `Program` without writing the recursion out each time. A `Visitor` has a
+//! method for each kind of node, and each one, left as it is, goes on into the node's
+//! children through the `walk_*` function of the same name. Override the ones for the nodes
+//! you care about, and call the `walk_*` function from them to keep going deeper.
+//!
+//! ```
+//! # use newton::newton_ast::{Expr, ExprKind};
+//! # use newton::newton_parse::parse_source;
+//! # use newton::newton_visit::{walk_expr, Visitor};
+//! /// every name used in a program
+//! struct Names(Vec<String>);
+//!
+//! impl<'ast> Visitor<'ast> for Names {
+//!     fn visit_expr(&mut self, expr: &'ast Expr) {
+//!         if let ExprKind::Ident(name) = &expr.kind {
+//!             self.0.push(name.clone());
+//!         }
+//!
+//!         walk_expr(self, expr);
+//!     }
+//! }
+//!
+//! let (program, _) = parse_source("new x { logic { let a = b + f(c) } }");
+//! let mut names = Names(Vec::new());
+//!
+//! names.visit_program(&program);
+//! assert_eq!(names.0, vec!["b", "f", "c"]);
+//! ```
+//!
+//! Nodes are visited in the order they're written, so a `let`'s pattern comes before its
+//! value, and an `if`'s condition before its body.
+//!
+//! A `Folder` is the same, for changing a program rather than looking at it. Each method
+//! takes a node and gives back the one to put in its place, and the `fold_*` functions
+//! rebuild a node out of its folded children, in the same order.
+//!
+//! ```
+//! # use newton::newton_ast::{BinOp, Expr, ExprKind};
+//! # use newton::newton_dump::program_sexp;
+//! # use newton::newton_parse::parse_source;
+//! # use newton::newton_visit::{fold_expr, Folder};
+//! /// works out additions of two numbers ahead of time
+//! struct Constants;
+//!
+//! impl Folder for Constants {
+//!     fn fold_expr(&mut self, expr: Expr) -> Expr {
+//!         let expr = fold_expr(self, expr);
+//!
+//!         match &expr.kind {
+//!             ExprKind::Binary { op: BinOp::Add, lhs, rhs } => match (&lhs.kind, &rhs.kind) {
+//!                 (ExprKind::Number(a), ExprKind::Number(b)) => Expr {
+//!                     kind: ExprKind::Number(a + b),
+//!                     span: expr.span,
+//!                 },
+//!                 _ => expr,
+//!             },
+//!             _ => expr,
+//!         }
+//!     }
+//! }
+//!
+//! let (program, _) = parse_source("new x { logic { let a = 1 + 2 + b } }");
+//! let program = Constants.fold_program(program);
+//!
+//! assert_eq!(program_sexp(&program), "(new x (logic (let a (+ 3 b))))");
+//! ```
+
+use crate::newton_ast::{
+    Assign, BlockDecl, Call, CollectStmt, Condition, ConditionKind, ConditionsBlock, Doc, Else,
+    EnumDecl, Expr, ExprKind, FnDecl, ForStmt, Ident, IfStmt, Include, Item, Lambda, LetStmt,
+    LogicBlock, MapEntry, Match, MatchArm, Pattern, PatternKind, Program, Section, Sigil, Stmt,
+    StmtKind, StringPart, StructDecl, Use, WhileStmt,
+};
+use crate::newton_lex::Span;
+
+/// # Visitors
+///
+/// Something that looks at each node of a program, see the module docs.
+pub trait Visitor<'ast> {
+    fn visit_program(&mut self, program: &'ast Program) {
+        walk_program(self, program);
+    }
+
+    fn visit_item(&mut self, item: &'ast Item) {
+        walk_item(self, item);
+    }
+
+    /// a `new hello_world { ... }` block declaration
+    fn visit_block(&mut self, block: &'ast BlockDecl) {
+        walk_block(self, block);
+    }
+
+    fn visit_fn(&mut self, function: &'ast FnDecl) {
+        walk_fn(self, function);
+    }
+
+    fn visit_condition(&mut self, condition: &'ast Condition) {
+        walk_condition(self, condition);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'ast Expr) {
+        walk_expr(self, expr);
+    }
+
+    fn visit_arm(&mut self, arm: &'ast MatchArm) {
+        walk_arm(self, arm);
+    }
+
+    fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+        walk_pattern(self, pattern);
+    }
+}
+
+/// visits each item
+pub fn walk_program<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, program: &'ast Program) {
+    for item in &program.items {
+        visitor.visit_item(item);
+    }
+}
+
+/// visits the block or function an item is, the other items have nothing inside them to
+/// visit
+pub fn walk_item<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, item: &'ast Item) {
+    match item {
+        Item::Block(block) => visitor.visit_block(block),
+        Item::Fn(function) => visitor.visit_fn(function),
+        Item::Include(_) | Item::Use(_) | Item::Struct(_) | Item::Enum(_) => {}
+    }
+}
+
+/// visits the conditions and the statements of each section
+pub fn walk_block<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, block: &'ast BlockDecl) {
+    for section in &block.sections {
+        match section {
+            Section::Conditions(conditions) => {
+                for condition in &conditions.conditions {
+                    visitor.visit_condition(condition);
+                }
+            }
+            Section::Logic(logic) => walk_stmts(visitor, &logic.body),
+        }
+    }
+}
+
+/// visits the body
+pub fn walk_fn<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, function: &'ast FnDecl) {
+    walk_stmts(visitor, &function.body);
+}
+
+/// visits a predicate's arguments
+pub fn walk_condition<'ast, V: Visitor<'ast> + ?Sized>(
+    visitor: &mut V,
+    condition: &'ast Condition,
+) {
+    if let ConditionKind::Predicate { args, .. } = &condition.kind {
+        walk_exprs(visitor, args);
+    }
+}
+
+/// visits each statement of a body
+fn walk_stmts<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmts: &'ast [Stmt]) {
+    for stmt in stmts {
+        visitor.visit_stmt(stmt);
+    }
+}
+
+/// visits each expression of a list
+fn walk_exprs<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, exprs: &'ast [Expr]) {
+    for expr in exprs {
+        visitor.visit_expr(expr);
+    }
+}
+
+/// visits the expressions, patterns, and bodies of a statement
+pub fn walk_stmt<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, stmt: &'ast Stmt) {
+    match &stmt.kind {
+        StmtKind::Expr(expr) => visitor.visit_expr(expr),
+        StmtKind::Collect(_) | StmtKind::Break | StmtKind::Continue => {}
+        StmtKind::For(for_stmt) => {
+            visitor.visit_expr(&for_stmt.source);
+            visitor.visit_pattern(&for_stmt.binding);
+            walk_stmts(visitor, &for_stmt.body);
+        }
+        StmtKind::Call(call) => walk_exprs(visitor, &call.args),
+        StmtKind::Let(let_stmt) => {
+            visitor.visit_pattern(&let_stmt.pattern);
+
+            if let Some(value) = &let_stmt.value {
+                visitor.visit_expr(value);
+            }
+        }
+        StmtKind::Assign(assign) => {
+            visitor.visit_expr(&assign.target);
+            visitor.visit_expr(&assign.value);
+        }
+        StmtKind::If(if_stmt) => {
+            visitor.visit_expr(&if_stmt.condition);
+            walk_stmts(visitor, &if_stmt.then);
+
+            match &if_stmt.otherwise {
+                Some(Else::If(nested)) => visitor.visit_stmt(nested),
+                Some(Else::Block(body)) => walk_stmts(visitor, body),
+                None => {}
+            }
+        }
+        StmtKind::While(while_stmt) => {
+            visitor.visit_expr(&while_stmt.condition);
+            walk_stmts(visitor, &while_stmt.body);
+        }
+        StmtKind::Return(value) => {
+            if let Some(value) = value {
+                visitor.visit_expr(value);
+            }
+        }
+    }
+}
+
+/// visits the operands, arguments, elements, arms, and bodies inside an expression
+pub fn walk_expr<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, expr: &'ast Expr) {
+    match &expr.kind {
+        ExprKind::Number(_)
+        | ExprKind::String(_)
+        | ExprKind::Char(_)
+        | ExprKind::Ident(_)
+        | ExprKind::Sigil(_) => {}
+        ExprKind::Member { base, member } => {
+            visitor.visit_expr(base);
+            visitor.visit_expr(member);
+        }
+        ExprKind::Unary { operand, .. } => visitor.visit_expr(operand),
+        ExprKind::Binary { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+        ExprKind::Call(call) => walk_exprs(visitor, &call.args),
+        ExprKind::Match(matched) => {
+            visitor.visit_expr(&matched.scrutinee);
+
+            for arm in &matched.arms {
+                visitor.visit_arm(arm);
+            }
+        }
+        ExprKind::FnCall { callee, args } => {
+            visitor.visit_expr(callee);
+            walk_exprs(visitor, args);
+        }
+        ExprKind::Interpolated(parts) => {
+            for part in parts {
+                if let StringPart::Expr(value) = part {
+                    visitor.visit_expr(value);
+                }
+            }
+        }
+        ExprKind::ListLit(items) => walk_exprs(visitor, items),
+        ExprKind::MapLit(entries) => {
+            for entry in entries {
+                visitor.visit_expr(&entry.key);
+                visitor.visit_expr(&entry.value);
+            }
+        }
+        ExprKind::Lambda(lambda) => walk_stmts(visitor, &lambda.body),
+        ExprKind::Conditional {
+            condition,
+            then,
+            otherwise,
+        } => {
+            visitor.visit_expr(condition);
+            visitor.visit_expr(then);
+            visitor.visit_expr(otherwise);
+        }
+        ExprKind::Range { start, end, .. } => {
+            visitor.visit_expr(start);
+            visitor.visit_expr(end);
+        }
+        ExprKind::Field { base, .. } => visitor.visit_expr(base),
+        ExprKind::Index { base, index } => {
+            visitor.visit_expr(base);
+            visitor.visit_expr(index);
+        }
+    }
+}
+
+/// visits the pattern, then the body
+pub fn walk_arm<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, arm: &'ast MatchArm) {
+    visitor.visit_pattern(&arm.pattern);
+    walk_stmts(visitor, &arm.body);
+}
+
+/// visits the patterns inside a tuple
+pub fn walk_pattern<'ast, V: Visitor<'ast> + ?Sized>(visitor: &mut V, pattern: &'ast Pattern) {
+    if let PatternKind::Tuple(items) = &pattern.kind {
+        for item in items {
+            visitor.visit_pattern(item);
+        }
+    }
+}
+
+/// # Folders
+///
+/// Something that rebuilds a program, a node at a time, see the module docs. A body is
+/// folded as a whole too, by `fold_body`, for a folder that takes statements out or puts
+/// more of them in, and every span goes through `fold_span`, for one that moves nodes
+/// around in the source.
+pub trait Folder {
+    fn fold_program(&mut self, program: Program) -> Program {
+        fold_program(self, program)
+    }
+
+    fn fold_item(&mut self, item: Item) -> Item {
+        fold_item(self, item)
+    }
+
+    /// a `new hello_world { ... }` block declaration
+    fn fold_block(&mut self, block: BlockDecl) -> BlockDecl {
+        fold_block(self, block)
+    }
+
+    fn fold_fn(&mut self, function: FnDecl) -> FnDecl {
+        fold_fn(self, function)
+    }
+
+    fn fold_condition(&mut self, condition: Condition) -> Condition {
+        fold_condition(self, condition)
+    }
+
+    /// the statements of a `logic` block, a function, a loop, a branch, an arm, or a lambda
+    fn fold_body(&mut self, body: Vec<Stmt>) -> Vec<Stmt> {
+        fold_body(self, body)
+    }
+
+    fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+        fold_stmt(self, stmt)
+    }
+
+    fn fold_expr(&mut self, expr: Expr) -> Expr {
+        fold_expr(self, expr)
+    }
+
+    fn fold_arm(&mut self, arm: MatchArm) -> MatchArm {
+        fold_arm(self, arm)
+    }
+
+    fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+        fold_pattern(self, pattern)
+    }
+
+    /// a name, of an item, a parameter, a field, a module, or anything else
+    fn fold_ident(&mut self, ident: Ident) -> Ident {
+        fold_ident(self, ident)
+    }
+
+    /// the span of a node or a name, kept as it is unless overridden
+    fn fold_span(&mut self, span: Span) -> Span {
+        span
+    }
+}
+
+/// folds each item
+pub fn fold_program<F: Folder + ?Sized>(folder: &mut F, program: Program) -> Program {
+    Program {
+        items: program
+            .items
+            .into_iter()
+            .map(|item| folder.fold_item(item))
+            .collect(),
+        span: folder.fold_span(program.span),
+    }
+}
+
+/// folds the item, the block or function it is, or just the names of the others
+pub fn fold_item<F: Folder + ?Sized>(folder: &mut F, item: Item) -> Item {
+    match item {
+        Item::Block(block) => Item::Block(folder.fold_block(block)),
+        Item::Fn(function) => Item::Fn(folder.fold_fn(function)),
+        Item::Include(include) => Item::Include(Include {
+            path: include.path,
+            span: folder.fold_span(include.span),
+        }),
+        Item::Use(import) => Item::Use(Use {
+            path: fold_idents(folder, import.path),
+            span: folder.fold_span(import.span),
+        }),
+        Item::Struct(record) => Item::Struct(StructDecl {
+            name: folder.fold_ident(record.name),
+            fields: fold_idents(folder, record.fields),
+            span: folder.fold_span(record.span),
+        }),
+        Item::Enum(choice) => Item::Enum(EnumDecl {
+            name: folder.fold_ident(choice.name),
+            variants: fold_idents(folder, choice.variants),
+            span: folder.fold_span(choice.span),
+        }),
+    }
+}
+
+/// folds the conditions and the body of each section
+pub fn fold_block<F: Folder + ?Sized>(folder: &mut F, block: BlockDecl) -> BlockDecl {
+    let name = folder.fold_ident(block.name);
+    let sections = block
+        .sections
+        .into_iter()
+        .map(|section| match section {
+            Section::Conditions(conditions) => Section::Conditions(ConditionsBlock {
+                conditions: conditions
+                    .conditions
+                    .into_iter()
+                    .map(|condition| folder.fold_condition(condition))
+                    .collect(),
+                span: folder.fold_span(conditions.span),
+            }),
+            Section::Logic(logic) => Section::Logic(LogicBlock {
+                body: folder.fold_body(logic.body),
+                span: folder.fold_span(logic.span),
+            }),
+        })
+        .collect();
+
+    BlockDecl {
+        doc: block.doc.map(|doc| fold_doc(folder, doc)),
+        name,
+        sections,
+        span: folder.fold_span(block.span),
+    }
+}
+
+/// folds the parameters and the body
+pub fn fold_fn<F: Folder + ?Sized>(folder: &mut F, function: FnDecl) -> FnDecl {
+    let name = folder.fold_ident(function.name);
+    let params = fold_idents(folder, function.params);
+
+    FnDecl {
+        doc: function.doc.map(|doc| fold_doc(folder, doc)),
+        name,
+        params,
+        body: folder.fold_body(function.body),
+        span: folder.fold_span(function.span),
+    }
+}
+
+/// moves a doc comment's span
+fn fold_doc<F: Folder + ?Sized>(folder: &mut F, doc: Doc) -> Doc {
+    Doc {
+        text: doc.text,
+        span: folder.fold_span(doc.span),
+    }
+}
+
+/// folds a predicate's arguments
+pub fn fold_condition<F: Folder + ?Sized>(folder: &mut F, condition: Condition) -> Condition {
+    let kind = match condition.kind {
+        ConditionKind::Predicate { name, args } => ConditionKind::Predicate {
+            name: folder.fold_ident(name),
+            args: fold_exprs(folder, args),
+        },
+        ConditionKind::Directive(name) => ConditionKind::Directive(folder.fold_ident(name)),
+    };
+
+    Condition {
+        kind,
+        span: folder.fold_span(condition.span),
+    }
+}
+
+/// folds each statement
+pub fn fold_body<F: Folder + ?Sized>(folder: &mut F, body: Vec<Stmt>) -> Vec<Stmt> {
+    body.into_iter()
+        .map(|stmt| folder.fold_stmt(stmt))
+        .collect()
+}
+
+/// folds each expression of a list
+fn fold_exprs<F: Folder + ?Sized>(folder: &mut F, exprs: Vec<Expr>) -> Vec<Expr> {
+    exprs
+        .into_iter()
+        .map(|expr| folder.fold_expr(expr))
+        .collect()
+}
+
+/// folds each name of a list
+fn fold_idents<F: Folder + ?Sized>(folder: &mut F, idents: Vec<Ident>) -> Vec<Ident> {
+    idents
+        .into_iter()
+        .map(|ident| folder.fold_ident(ident))
+        .collect()
+}
+
+/// folds the names and the arguments of a call
+fn fold_call<F: Folder + ?Sized>(folder: &mut F, call: Call) -> Call {
+    Call {
+        module: folder.fold_ident(call.module),
+        function: folder.fold_ident(call.function),
+        args: fold_exprs(folder, call.args),
+    }
+}
+
+/// folds an expression inside another, in the box it was in
+fn fold_boxed<F: Folder + ?Sized>(folder: &mut F, mut expr: Box<Expr>) -> Box<Expr> {
+    *expr = folder.fold_expr(*expr);
+    expr
+}
+
+/// folds the expressions, patterns, and bodies of a statement
+pub fn fold_stmt<F: Folder + ?Sized>(folder: &mut F, stmt: Stmt) -> Stmt {
+    let kind = match stmt.kind {
+        StmtKind::Expr(expr) => StmtKind::Expr(folder.fold_expr(expr)),
+        StmtKind::Collect(collect) => StmtKind::Collect(CollectStmt {
+            target: Sigil {
+                name: collect.target.name,
+                span: folder.fold_span(collect.target.span),
+            },
+        }),
+        StmtKind::For(for_stmt) => StmtKind::For(ForStmt {
+            source: folder.fold_expr(for_stmt.source),
+            binding: folder.fold_pattern(for_stmt.binding),
+            body: folder.fold_body(for_stmt.body),
+        }),
+        StmtKind::Call(call) => StmtKind::Call(fold_call(folder, call)),
+        StmtKind::Let(let_stmt) => {
+            let pattern = folder.fold_pattern(let_stmt.pattern);
+
+            StmtKind::Let(LetStmt {
+                pattern,
+                value: let_stmt.value.map(|value| folder.fold_expr(value)),
+            })
+        }
+        StmtKind::Assign(assign) => {
+            let target = folder.fold_expr(assign.target);
+
+            StmtKind::Assign(Assign {
+                target,
+                op: assign.op,
+                value: folder.fold_expr(assign.value),
+            })
+        }
+        StmtKind::If(if_stmt) => {
+            let condition = folder.fold_expr(if_stmt.condition);
+            let then = folder.fold_body(if_stmt.then);
+            let otherwise = if_stmt.otherwise.map(|otherwise| match otherwise {
+                Else::If(nested) => Else::If(Box::new(folder.fold_stmt(*nested))),
+                Else::Block(body) => Else::Block(folder.fold_body(body)),
+            });
+
+            StmtKind::If(IfStmt {
+                condition,
+                then,
+                otherwise,
+            })
+        }
+        StmtKind::While(while_stmt) => {
+            let condition = folder.fold_expr(while_stmt.condition);
+
+            StmtKind::While(WhileStmt {
+                condition,
+                body: folder.fold_body(while_stmt.body),
+            })
+        }
+        StmtKind::Return(value) => StmtKind::Return(value.map(|value| folder.fold_expr(value))),
+        StmtKind::Break => StmtKind::Break,
+        StmtKind::Continue => StmtKind::Continue,
+    };
+
+    Stmt {
+        kind,
+        span: folder.fold_span(stmt.span),
+    }
+}
+
+/// folds the operands, arguments, elements, arms, and bodies inside an expression
+pub fn fold_expr<F: Folder + ?Sized>(folder: &mut F, expr: Expr) -> Expr {
+    let kind = match expr.kind {
+        ExprKind::Member { base, member } => ExprKind::Member {
+            base: fold_boxed(folder, base),
+            member: fold_boxed(folder, member),
+        },
+        ExprKind::Unary { op, operand } => ExprKind::Unary {
+            op,
+            operand: fold_boxed(folder, operand),
+        },
+        ExprKind::Binary { op, lhs, rhs } => ExprKind::Binary {
+            op,
+            lhs: fold_boxed(folder, lhs),
+            rhs: fold_boxed(folder, rhs),
+        },
+        ExprKind::Call(call) => ExprKind::Call(fold_call(folder, call)),
+        ExprKind::Match(matched) => ExprKind::Match(Match {
+            scrutinee: fold_boxed(folder, matched.scrutinee),
+            arms: matched
+                .arms
+                .into_iter()
+                .map(|arm| folder.fold_arm(arm))
+                .collect(),
+        }),
+        ExprKind::FnCall { callee, args } => ExprKind::FnCall {
+            callee: fold_boxed(folder, callee),
+            args: fold_exprs(folder, args),
+        },
+        ExprKind::Interpolated(parts) => ExprKind::Interpolated(
+            parts
+                .into_iter()
+                .map(|part| match part {
+                    StringPart::Expr(value) => StringPart::Expr(folder.fold_expr(value)),
+                    text => text,
+                })
+                .collect(),
+        ),
+        ExprKind::ListLit(items) => ExprKind::ListLit(fold_exprs(folder, items)),
+        ExprKind::MapLit(entries) => ExprKind::MapLit(
+            entries
+                .into_iter()
+                .map(|entry| MapEntry {
+                    key: folder.fold_expr(entry.key),
+                    value: folder.fold_expr(entry.value),
+                })
+                .collect(),
+        ),
+        ExprKind::Lambda(lambda) => ExprKind::Lambda(Lambda {
+            params: fold_idents(folder, lambda.params),
+            body: folder.fold_body(lambda.body),
+        }),
+        ExprKind::Conditional {
+            condition,
+            then,
+            otherwise,
+        } => ExprKind::Conditional {
+            condition: fold_boxed(folder, condition),
+            then: fold_boxed(folder, then),
+            otherwise: fold_boxed(folder, otherwise),
+        },
+        ExprKind::Range {
+            start,
+            end,
+            inclusive,
+        } => ExprKind::Range {
+            start: fold_boxed(folder, start),
+            end: fold_boxed(folder, end),
+            inclusive,
+        },
+        ExprKind::Field { base, field } => ExprKind::Field {
+            base: fold_boxed(folder, base),
+            field: folder.fold_ident(field),
+        },
+        ExprKind::Index { base, index } => ExprKind::Index {
+            base: fold_boxed(folder, base),
+            index: fold_boxed(folder, index),
+        },
+        literal => literal,
+    };
+
+    Expr {
+        kind,
+        span: folder.fold_span(expr.span),
+    }
+}
+
+/// folds the pattern, then the body
+pub fn fold_arm<F: Folder + ?Sized>(folder: &mut F, arm: MatchArm) -> MatchArm {
+    let pattern = folder.fold_pattern(arm.pattern);
+
+    MatchArm {
+        pattern,
+        body: folder.fold_body(arm.body),
+        span: folder.fold_span(arm.span),
+    }
+}
+
+/// folds the patterns inside a tuple, or the names of a variant
+pub fn fold_pattern<F: Folder + ?Sized>(folder: &mut F, pattern: Pattern) -> Pattern {
+    let kind = match pattern.kind {
+        PatternKind::Tuple(items) => PatternKind::Tuple(
+            items
+                .into_iter()
+                .map(|item| folder.fold_pattern(item))
+                .collect(),
+        ),
+        PatternKind::Variant { name, variant } => PatternKind::Variant {
+            name: folder.fold_ident(name),
+            variant: folder.fold_ident(variant),
+        },
+        other => other,
+    };
+
+    Pattern {
+        kind,
+        span: folder.fold_span(pattern.span),
+    }
+}
+
+/// folds the span of a name
+pub fn fold_ident<F: Folder + ?Sized>(folder: &mut F, ident: Ident) -> Ident {
+    Ident {
+        span: folder.fold_span(ident.span),
+        name: ident.name,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::newton_parse::parse_source;
+
+    /// writes down what it visits, and how many statements deep it is
+    #[derive(Default)]
+    struct Trace {
+        seen: Vec<String>, // what it visited, in order
+        depth: usize,      // how many statements it's in
+        deepest: usize,    // the most it's been in
+    }
+
+    impl<'ast> Visitor<'ast> for Trace {
+        fn visit_block(&mut self, block: &'ast BlockDecl) {
+            self.seen.push(format!("block {}", block.name.name));
+            walk_block(self, block);
+        }
+
+        fn visit_fn(&mut self, function: &'ast FnDecl) {
+            self.seen.push(format!("fn {}", function.name.name));
+            walk_fn(self, function);
+        }
+
+        fn visit_stmt(&mut self, stmt: &'ast Stmt) {
+            self.depth += 1;
+            self.deepest = self.deepest.max(self.depth);
+            walk_stmt(self, stmt);
+            self.depth -= 1;
+        }
+
+        fn visit_expr(&mut self, expr: &'ast Expr) {
+            match &expr.kind {
+                ExprKind::Ident(name) => self.seen.push(name.clone()),
+                ExprKind::Number(n) => self.seen.push(n.to_string()),
+                _ => {}
+            }
+
+            walk_expr(self, expr);
+        }
+
+        fn visit_pattern(&mut self, pattern: &'ast Pattern) {
+            if let PatternKind::Ident(name) = &pattern.kind {
+                self.seen.push(format!("bind {}", name));
+            }
+
+            walk_pattern(self, pattern);
+        }
+    }
+
+    #[test]
+    pub fn test_visit_order() {
+        let source = "include \"core\"\nfn f(a) {\n  if a { return b } else if c { d } else { e }\n}\nnew x {\n  conditions { expect ident 1 }\n  logic {\n    for xs as (k, v) { ::stdout write k }\n    let y = match z { (p, 2) => p, _ => #{ q: [r] } }\n  }\n}";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let mut trace = Trace::default();
+
+        trace.visit_program(&program);
+
+        assert_eq!(
+            trace.seen,
+            vec![
+                "fn f", "a", "b", "c", "d", "e", "block x", "ident", "1", "xs", "bind k", "bind v",
+                "k", "bind y", "z", "bind p", "p", "q", "r",
+            ]
+        );
+
+        // the `d` in the `else if` is in three statements, the `if`s and itself
+        assert_eq!(trace.deepest, 3);
+    }
+
+    #[test]
+    pub fn test_visit_stops() {
+        /// counts the expressions outside lambdas, not going into them
+        struct Outside(usize);
+
+        impl<'ast> Visitor<'ast> for Outside {
+            fn visit_expr(&mut self, expr: &'ast Expr) {
+                self.0 += 1;
+
+                if !matches!(expr.kind, ExprKind::Lambda(_)) {
+                    walk_expr(self, expr);
+                }
+            }
+        }
+
+        let (program, _) = parse_source("new x { logic { f(fn (a) { a + 1 }, 2) } }");
+        let mut outside = Outside(0);
+
+        outside.visit_program(&program);
+
+        // the call, `f`, the lambda, and `2`
+        assert_eq!(outside.0, 4);
+    }
+
+    /// `x += 1` as `x = x + 1`, with any `break` taken out and each `continue` written twice
+    struct Desugar;
+
+    impl Folder for Desugar {
+        fn fold_body(&mut self, body: Vec<Stmt>) -> Vec<Stmt> {
+            let mut folded = Vec::new();
+
+            for stmt in fold_body(self, body) {
+                match stmt.kind {
+                    StmtKind::Break => {}
+                    StmtKind::Continue => folded.extend([stmt.clone(), stmt]),
+                    _ => folded.push(stmt),
+                }
+            }
+
+            folded
+        }
+
+        fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+            let stmt = fold_stmt(self, stmt);
+
+            let StmtKind::Assign(Assign {
+                target,
+                op: Some(op),
+                value,
+            }) = stmt.kind
+            else {
+                return stmt;
+            };
+
+            let value = Expr {
+                span: value.span,
+                kind: ExprKind::Binary {
+                    op,
+                    lhs: Box::new(target.clone()),
+                    rhs: Box::new(value),
+                },
+            };
+
+            Stmt {
+                kind: StmtKind::Assign(Assign {
+                    target,
+                    op: None,
+                    value,
+                }),
+                span: stmt.span,
+            }
+        }
+
+        fn fold_pattern(&mut self, pattern: Pattern) -> Pattern {
+            match pattern.kind {
+                PatternKind::Ident(name) => Pattern {
+                    kind: PatternKind::Ident(name.to_uppercase()),
+                    span: pattern.span,
+                },
+                _ => fold_pattern(self, pattern),
+            }
+        }
+    }
+
+    #[test]
+    pub fn test_fold() {
+        let source = "fn f(a) {
+  while a {
+    a -= 1
+    break
+    continue
+  }
+  let (b, c) = g(fn (x) { x *= match x { (y, 2) => { y += 1 } } })
+}";
+        let (program, diagnostics) = parse_source(source);
+
+        assert!(diagnostics.is_empty(), "{:?}", diagnostics);
+
+        let folded = Desugar.fold_program(program.clone());
+
+        assert_eq!(
+            crate::newton_dump::program_sexp(&folded),
+            "(fn f (a) (while a (= a (- a 1)) continue continue) (let (tuple B C) (call g (fn (x) (= x (* x (match x ((tuple Y 2) (= y (+ y 1))))))))))"
+        );
+
+        // a folder that changes nothing gives back the same program
+        struct Nothing;
+
+        impl Folder for Nothing {}
+
+        assert_eq!(Nothing.fold_program(program.clone()), program);
+    }
+
+    #[test]
+    pub fn test_fold_spans() {
+        /// moves everything later by as many bytes as it holds
+        struct Shift(isize);
+
+        impl Folder for Shift {
+            fn fold_span(&mut self, span: Span) -> Span {
+                span.shift(self.0)
+            }
+        }
+
+        let source = "use a::b\nnew struct P { x }\nnew enum S { A }\nfn f(a) { collect as $c\n::m g S::A }\nnew x {\n  conditions { expect ident 1 %d }\n  logic { p.x = match y { (S::A, z) => fn (q) { } } }\n}";
+        let (program, _) = parse_source(source);
+        let (moved, _) = parse_source(&format!("   {}", source));
+
+        let mut shifted = Shift(3).fold_program(program);
+
+        // the file is longer, but the program in it is where it was moved to
+        shifted.span = moved.span;
+
+        assert_eq!(shifted, moved);
+    }
+}
@@ -0,0 +1,477 @@
+//! # Newton Workspaces
+//!
+//! A project is a set of `.newton` files that pull each other in with `include` and `use`.
+//! A `Workspace` parses them into one unit, with one `SourceMap` for their text and one
+//! `Interner` for their names, and `resolve` works out which file each `include` and `use`
+//! points at, and where every block, function, struct, and enum is declared, whichever file
+//! it's in.
+//!
+//! ```
+//! # use newton::newton_workspace::Workspace;
+//! let mut workspace = Workspace::new();
+//! let main = workspace.add("main.newton", "include \"core\"\nnew x { logic { greet(1) } }");
+//! let core = workspace.add("core.newton", "fn greet(name) { ::stdout write name }");
+//!
+//! let resolution = workspace.resolve();
+//! let greet = workspace.interner.get("greet").unwrap();
+//!
+//! assert_eq!(resolution.imports_of(main).collect::<Vec<_>>(), [core]);
+//! assert_eq!(resolution.definition(greet).unwrap().location.file, core);
+//! assert!(resolution.diagnostics.is_empty());
+//! ```
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::newton_ast::{Ident, Item, Program};
+use crate::newton_diag::Diagnostic;
+use crate::newton_intern::{Interner, Symbol};
+use crate::newton_lex::{Lexer, LexerOptions, Span};
+use crate::newton_parse::parse;
+use crate::newton_source::{FileId, Location, SourceMap};
+
+/// # Resolve Errors
+///
+/// Something in one file that doesn't line up with the rest of the workspace, and where.
+#[derive(Debug, PartialEq, Clone)]
+pub struct ResolveError {
+    pub kind: ResolveErrorKind, // what went wrong
+    pub span: Span,             // where it went wrong
+}
+
+#[derive(Debug, PartialEq, Clone)]
+pub enum ResolveErrorKind {
+    UnresolvedInclude { path: String }, // `include "core"`, without a `core.newton`
+    UnresolvedUse { path: String },     // `use core::str`, without a `core/str.newton`
+    Duplicate { name: String },         // `fn greet` in two places
+}
+
+impl ResolveErrorKind {
+    /// the stable error code, see `newton_codes`
+    pub fn code(&self) -> &'static str {
+        match self {
+            ResolveErrorKind::UnresolvedInclude { .. } => "N0200",
+            ResolveErrorKind::UnresolvedUse { .. } => "N0200",
+            ResolveErrorKind::Duplicate { .. } => "N0201",
+        }
+    }
+}
+
+impl std::fmt::Display for ResolveErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolveErrorKind::UnresolvedInclude { path } => {
+                write!(f, "no file to include at `{}`", path)
+            }
+            ResolveErrorKind::UnresolvedUse { path } => write!(f, "no module `{}`", path),
+            ResolveErrorKind::Duplicate { name } => {
+                write!(f, "`{}` is declared more than once", name)
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} at {}", self.kind, self.span)
+    }
+}
+
+/// # Definition Kinds
+///
+/// What kind of item a definition is.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+pub enum DefinitionKind {
+    Block,  // new hello_world { ... }
+    Fn,     // fn greet(name) { ... }
+    Struct, // new struct Point { x, y }
+    Enum,   // new enum State { Idle, Running }
+}
+
+/// # Definitions
+///
+/// A name declared at the top level of a file, which every file of the workspace can refer
+/// to.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Definition {
+    pub kind: DefinitionKind, // what it is
+    pub name: Symbol,         // what it's called
+    pub location: Location,   // where the name is written
+}
+
+/// # Imports
+///
+/// An `include` or a `use`, and the file it pulls in.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct Import {
+    pub location: Location, // the item
+    pub target: FileId,     // the file it points at
+}
+
+/// # Resolutions
+///
+/// How the files of a workspace fit together, from `Workspace::resolve`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Resolution {
+    pub imports: Vec<Import>, // every include and use that points at a file, file by file
+    pub definitions: HashMap<Symbol, Vec<Definition>>, // by name, file by file
+    pub diagnostics: Vec<(FileId, Diagnostic)>, // imports to nowhere, names declared twice
+}
+
+impl Resolution {
+    /// where `name` is declared, the first declaration if there's more than one
+    pub fn definition(&self, name: Symbol) -> Option<&Definition> {
+        self.definitions(name).first()
+    }
+
+    /// every declaration of `name`, in the order of the files
+    pub fn definitions(&self, name: Symbol) -> &[Definition] {
+        self.definitions.get(&name).map_or(&[], Vec::as_slice)
+    }
+
+    /// the files `file` pulls in, in the order it does
+    pub fn imports_of(&self, file: FileId) -> impl Iterator<Item = FileId> + '_ {
+        self.imports
+            .iter()
+            .filter(move |import| import.location.file == file)
+            .map(|import| import.target)
+    }
+}
+
+/// # Workspaces
+///
+/// Every file of a project, parsed. Files are only ever added, like in a `SourceMap`, and the
+/// program of each one is kept under its `FileId`.
+#[derive(Debug, PartialEq, Clone, Default)]
+pub struct Workspace {
+    sources: SourceMap,                         // every file's text
+    programs: Vec<Program>,                     // every file's program, by `FileId`
+    pub interner: Interner,                     // every name, from every file
+    pub diagnostics: Vec<(FileId, Diagnostic)>, // what the lexer and parser reported
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// parses a file that is already in memory, and adds it
+    pub fn add(&mut self, path: impl Into<PathBuf>, text: impl Into<String>) -> FileId {
+        let id = self.sources.add(path, text);
+        let interner = std::mem::take(&mut self.interner);
+
+        let mut lexer = Lexer::new_with(
+            &self.sources.file(id).text,
+            LexerOptions::new().trivia(true),
+        )
+        .with_interner(interner);
+        let mut diagnostics = Vec::new();
+
+        let tokens = lexer.lexeme_into(&mut diagnostics);
+        let (program, parsed) = parse(tokens);
+
+        self.interner = lexer.interner.take().unwrap_or_default();
+        self.programs.push(program);
+        self.diagnostics.extend(
+            diagnostics
+                .into_iter()
+                .chain(parsed)
+                .map(|diagnostic| (id, diagnostic)),
+        );
+
+        id
+    }
+
+    /// reads a file from disk, parses it, and adds it
+    pub fn load(&mut self, path: impl AsRef<Path>) -> std::io::Result<FileId> {
+        let text = std::fs::read_to_string(path.as_ref())?;
+
+        Ok(self.add(path.as_ref(), text))
+    }
+
+    /// reads many files from disk, parses them, and adds them, in order
+    ///
+    /// Stops at the first file that can't be read, the ones before it stay added.
+    pub fn load_all(&mut self, paths: &[impl AsRef<Path>]) -> std::io::Result<Vec<FileId>> {
+        paths.iter().map(|path| self.load(path)).collect()
+    }
+
+    /// the text of every file
+    pub fn sources(&self) -> &SourceMap {
+        &self.sources
+    }
+
+    /// the program a file parsed into
+    ///
+    /// Panics if the id came from another workspace.
+    pub fn program(&self, id: FileId) -> &Program {
+        &self.programs[id.index()]
+    }
+
+    /// every file and its program, in the order they were added
+    pub fn programs(&self) -> impl Iterator<Item = (FileId, &Program)> {
+        self.sources.files().map(|file| file.id).zip(&self.programs)
+    }
+
+    /// # Resolving
+    ///
+    /// Works out how the files fit together, as they are now.
+    ///
+    /// An `include` is a path relative to the file it's in, and a `use` is the same path
+    /// written with `::`, both with or without the `.newton` at the end. Every block,
+    /// function, struct, and enum is recorded under its name, and one declared again after
+    /// the first time, in the same file or another, is an error.
+    pub fn resolve(&mut self) -> Resolution {
+        let mut resolution = Resolution::default();
+        let mut interner = std::mem::take(&mut self.interner);
+
+        for (file, program) in self.sources.files().map(|file| file.id).zip(&self.programs) {
+            for item in &program.items {
+                match item {
+                    Item::Include(include) if !include.path.is_empty() => {
+                        let kind = ResolveErrorKind::UnresolvedInclude {
+                            path: include.path.clone(),
+                        };
+
+                        self.import(&mut resolution, file, &include.path, include.span, kind);
+                    }
+                    Item::Use(import) if import.path.iter().all(|name| !name.name.is_empty()) => {
+                        let names: Vec<_> = import.path.iter().map(|name| &*name.name).collect();
+                        let kind = ResolveErrorKind::UnresolvedUse {
+                            path: names.join("::"),
+                        };
+                        let path: PathBuf = names.iter().collect();
+
+                        self.import(&mut resolution, file, &path, import.span, kind);
+                    }
+                    Item::Block(block) => {
+                        self.define(
+                            &mut interner,
+                            &mut resolution,
+                            file,
+                            &block.name,
+                            DefinitionKind::Block,
+                        );
+                    }
+                    Item::Fn(function) => {
+                        self.define(
+                            &mut interner,
+                            &mut resolution,
+                            file,
+                            &function.name,
+                            DefinitionKind::Fn,
+                        );
+                    }
+                    Item::Struct(record) => {
+                        self.define(
+                            &mut interner,
+                            &mut resolution,
+                            file,
+                            &record.name,
+                            DefinitionKind::Struct,
+                        );
+                    }
+                    Item::Enum(choice) => {
+                        self.define(
+                            &mut interner,
+                            &mut resolution,
+                            file,
+                            &choice.name,
+                            DefinitionKind::Enum,
+                        );
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        self.interner = interner;
+        resolution
+    }
+
+    /// records where an `include` or `use` in `file` points, or that it points nowhere
+    fn import(
+        &self,
+        resolution: &mut Resolution,
+        file: FileId,
+        path: impl AsRef<Path>,
+        span: Span,
+        kind: ResolveErrorKind,
+    ) {
+        match self.find(file, path.as_ref()) {
+            Some(target) => resolution.imports.push(Import {
+                location: Location::new(file, span),
+                target,
+            }),
+            None => {
+                let error = ResolveError { kind, span };
+
+                resolution.diagnostics.push((file, Diagnostic::from(error)));
+            }
+        }
+    }
+
+    /// the file at `path`, relative to the directory `from` is in
+    fn find(&self, from: FileId, path: &Path) -> Option<FileId> {
+        let dir = self
+            .sources
+            .file(from)
+            .path
+            .parent()
+            .unwrap_or(Path::new(""));
+        let exact = dir.join(path);
+
+        let mut newton = exact.clone().into_os_string();
+        newton.push(".newton");
+
+        self.sources
+            .files()
+            .find(|file| file.path == exact || file.path.as_os_str() == newton)
+            .map(|file| file.id)
+    }
+
+    /// records a declaration of `name` in `file`, and whether it's the first one
+    fn define(
+        &self,
+        interner: &mut Interner,
+        resolution: &mut Resolution,
+        file: FileId,
+        name: &Ident,
+        kind: DefinitionKind,
+    ) {
+        if name.name.is_empty() {
+            return;
+        }
+
+        let symbol = interner.intern(&name.name);
+        let definitions = resolution.definitions.entry(symbol).or_default();
+
+        if let Some(first) = definitions.first() {
+            let error = ResolveError {
+                kind: ResolveErrorKind::Duplicate {
+                    name: name.name.clone(),
+                },
+                span: name.span,
+            };
+            let mut diagnostic = Diagnostic::from(error);
+
+            if let Some(resolved) = self.sources.resolve(first.location) {
+                diagnostic = diagnostic.with_note(format!("first declared at {}", resolved));
+            }
+
+            resolution.diagnostics.push((file, diagnostic));
+        }
+
+        definitions.push(Definition {
+            kind,
+            name: symbol,
+            location: Location::new(file, name.span),
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_workspace_resolve() {
+        let mut workspace = Workspace::new();
+        let main = workspace.add(
+            "src/main.newton",
+            "include \"lib/core\"\nuse lib::text\nnew hello { logic { greet(1) } }",
+        );
+        let core = workspace.add(
+            "src/lib/core.newton",
+            "include \"text.newton\"\nfn greet(name) { ::stdout write name }",
+        );
+        let text = workspace.add(
+            "src/lib/text.newton",
+            "new struct Line { text }\nnew enum Case { Upper, Lower }",
+        );
+
+        let resolution = workspace.resolve();
+        let lookup = |name| {
+            let symbol = workspace.interner.get(name).unwrap();
+            let definition = resolution.definition(symbol).unwrap();
+
+            (definition.kind, definition.location.file)
+        };
+
+        assert!(workspace.diagnostics.is_empty());
+        assert!(resolution.diagnostics.is_empty());
+
+        assert_eq!(
+            resolution.imports_of(main).collect::<Vec<_>>(),
+            [core, text]
+        );
+        assert_eq!(resolution.imports_of(core).collect::<Vec<_>>(), [text]);
+        assert_eq!(resolution.imports_of(text).count(), 0);
+
+        assert_eq!(lookup("hello"), (DefinitionKind::Block, main));
+        assert_eq!(lookup("greet"), (DefinitionKind::Fn, core));
+        assert_eq!(lookup("Line"), (DefinitionKind::Struct, text));
+        assert_eq!(lookup("Case"), (DefinitionKind::Enum, text));
+
+        let greet = resolution
+            .definition(workspace.interner.get("greet").unwrap())
+            .unwrap();
+        let resolved = workspace.sources().resolve(greet.location).unwrap();
+
+        assert_eq!(resolved.to_string(), "src/lib/core.newton:2:4");
+    }
+
+    #[test]
+    pub fn test_workspace_errors() {
+        let mut workspace = Workspace::new();
+        let a = workspace.add("a.newton", "include \"b\"\ninclude \"nowhere\"\nfn f() { }");
+        let b = workspace.add("b.newton", "use a::b\nnew f { }\nfn g( { }");
+
+        let resolution = workspace.resolve();
+        let f = workspace.interner.get("f").unwrap();
+        let messages: Vec<_> = resolution
+            .diagnostics
+            .iter()
+            .map(|(file, diagnostic)| (*file, diagnostic.to_string()))
+            .collect();
+
+        assert_eq!(workspace.diagnostics.len(), 1);
+        assert_eq!(workspace.diagnostics[0].0, b);
+
+        assert_eq!(
+            messages,
+            [
+                (
+                    a,
+                    "error[N0200]: no file to include at `nowhere`".to_owned()
+                ),
+                (b, "error[N0200]: no module `a::b`".to_owned()),
+                (b, "error[N0201]: `f` is declared more than once".to_owned()),
+            ]
+        );
+        assert_eq!(
+            resolution.diagnostics[2].1.children[0].message,
+            "first declared at a.newton:3:4"
+        );
+
+        assert_eq!(resolution.definitions(f).len(), 2);
+        assert_eq!(resolution.definition(f).unwrap().kind, DefinitionKind::Fn);
+        assert_eq!(resolution.imports_of(a).collect::<Vec<_>>(), [b]);
+    }
+
+    #[test]
+    pub fn test_workspace_shares_interner() {
+        let mut workspace = Workspace::new();
+        let a = workspace.add("a.newton", "new x { logic { ::stdout write var } }");
+        let b = workspace.add("b.newton", "fn f(var) { ::stdout write var }");
+
+        let names = workspace.interner.len();
+
+        workspace.add("c.newton", "fn g() { ::stdout write var }");
+
+        assert_eq!(workspace.interner.len(), names + 1);
+        assert_eq!(workspace.programs().count(), 3);
+        assert_eq!(workspace.program(a).items.len(), 1);
+        assert_eq!(workspace.program(b).span.end, 32);
+    }
+}